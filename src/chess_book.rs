@@ -0,0 +1,278 @@
+use crate::chess_minmax::BoardHash;
+use crate::chess_notation::{parse_coordinate_move, parse_fen};
+
+use chess::{Board, ChessMove};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use std::collections::HashMap;
+use std::fs;
+
+// number of ply (half move) the AI stay in book by default
+pub const DEFAULT_BOOK_DEPTH: usize = 16;
+
+/// Result of looking up a position in the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookProbe {
+    Hit(ChessMove),
+    OutOfBook,
+    /// position may be in book but the game is already longer than the book depth
+    DepthExceeded,
+    /// book suggest a move that is illegal in this position
+    Corrupt(ChessMove),
+}
+
+pub struct OpeningBook {
+    // book moves of each position with number of line they appear in
+    moves: HashMap<BoardHash, Vec<(ChessMove, u32)>>,
+    max_depth: usize,
+}
+
+impl Default for OpeningBook {
+    fn default() -> Self {
+        OpeningBook {
+            moves: HashMap::new(),
+            max_depth: DEFAULT_BOOK_DEPTH,
+        }
+    }
+}
+
+impl OpeningBook {
+    /// Load book from file, see `OpeningBook::parse` for the format
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self::parse(&text))
+    }
+
+    /// Parse opening lines, one line per opening, each line is coordinate moves
+    /// from the starting position (e.g. `e2e4 c7c5 g1f3`), or from a FEN followed by `:`
+    /// (e.g. `rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 : g1f3`)
+    /// to map a position to its book moves.
+    ///
+    /// Comments in `{}` and line starting with `#` are ignored,
+    /// a line with illegal move is kept up until that move.
+    pub fn parse(text: &str) -> Self {
+        let mut book = Self::default();
+
+        for (line_num, line) in text.lines().enumerate() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let line = strip_comments(line);
+            let (mut board, moves) = match line.split_once(':') {
+                Some((fen, moves)) => match parse_fen(fen) {
+                    Ok((board, _)) => (board, moves),
+                    Err(e) => {
+                        println!(
+                            "Book line {}: invalid FEN: {}, skipping line",
+                            line_num + 1,
+                            e
+                        );
+                        continue;
+                    }
+                },
+                None => (Board::default(), line.as_str()),
+            };
+            for token in moves.split_whitespace() {
+                match parse_coordinate_move(token, &board) {
+                    Ok(mov) => {
+                        book.insert(&board, mov);
+                        board = board.make_move_new(mov);
+                    }
+                    Err(e) => {
+                        println!("Book line {}: {}, skipping rest of line", line_num + 1, e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        book
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn insert(&mut self, board: &Board, mov: ChessMove) {
+        let entry = self.moves.entry(BoardHash::new(board)).or_default();
+        match entry.iter_mut().find(|(book_mov, _)| *book_mov == mov) {
+            Some((_, count)) => *count += 1,
+            None => entry.push((mov, 1)),
+        }
+    }
+
+    /// Every book move of `board` with its frequency, most frequent first
+    pub fn book_moves(&self, board: &Board) -> Vec<(ChessMove, u32)> {
+        let mut moves = self
+            .moves
+            .get(&BoardHash::new(board))
+            .cloned()
+            .unwrap_or_default();
+        moves.sort_by(|(_, a), (_, b)| b.cmp(a));
+        moves
+    }
+
+    /// number of position in the book
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Pick random book move for `board`, weighted by how many lines play it,
+    /// `ply` is number of move already played in the game
+    pub fn probe(&self, board: &Board, ply: usize, rng: &mut impl Rng) -> BookProbe {
+        if ply >= self.max_depth {
+            return BookProbe::DepthExceeded;
+        }
+
+        match self
+            .moves
+            .get(&BoardHash::new(board))
+            .and_then(|moves| moves.choose_weighted(rng, |&(_, count)| count).ok())
+            .map(|(mov, _)| mov)
+        {
+            None => BookProbe::OutOfBook,
+            Some(&mov) if board.legal(mov) => BookProbe::Hit(mov),
+            Some(&mov) => BookProbe::Corrupt(mov),
+        }
+    }
+}
+
+fn strip_comments(line: &str) -> String {
+    let mut depth = 0;
+    line.chars()
+        .filter(|&c| match c {
+            '{' => {
+                depth += 1;
+                false
+            }
+            '}' => {
+                depth -= 1;
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BookProbe, OpeningBook};
+    use crate::chess_minmax::test_rng;
+    use chess::{Board, ChessMove, Square};
+
+    fn sicilian_book() -> OpeningBook {
+        OpeningBook::parse("e2e4 c7c5 {Sicilian Defense} g1f3 d7d6\n# comment line\n")
+    }
+
+    #[test]
+    fn test_in_book() {
+        let book = sicilian_book();
+        let rng = &mut test_rng();
+
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        assert_eq!(book.len(), 4);
+        assert_eq!(book.probe(&Board::default(), 0, rng), BookProbe::Hit(e4));
+
+        let board = Board::default().make_move_new(e4);
+        let c5 = ChessMove::new(Square::C7, Square::C5, None);
+        assert_eq!(book.probe(&board, 1, rng), BookProbe::Hit(c5));
+    }
+
+    #[test]
+    fn test_out_of_book() {
+        let book = sicilian_book();
+        let rng = &mut test_rng();
+
+        let d4 = ChessMove::new(Square::D2, Square::D4, None);
+        let board = Board::default().make_move_new(d4);
+        assert_eq!(book.probe(&board, 1, rng), BookProbe::OutOfBook);
+    }
+
+    #[test]
+    fn test_book_depth_exceeded() {
+        let book = sicilian_book().with_max_depth(1);
+        let rng = &mut test_rng();
+
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let board = Board::default().make_move_new(e4);
+        assert!(matches!(
+            book.probe(&Board::default(), 0, rng),
+            BookProbe::Hit(_)
+        ));
+        assert_eq!(book.probe(&board, 1, rng), BookProbe::DepthExceeded);
+    }
+
+    #[test]
+    fn test_corrupt_entry() {
+        let mut book = OpeningBook::default();
+        let rng = &mut test_rng();
+
+        let illegal = ChessMove::new(Square::E2, Square::E5, None);
+        book.insert(&Board::default(), illegal);
+        assert_eq!(
+            book.probe(&Board::default(), 0, rng),
+            BookProbe::Corrupt(illegal)
+        );
+    }
+
+    #[test]
+    fn test_illegal_line_is_truncated() {
+        let book = OpeningBook::parse("e2e4 e7e5 e4e5 g1f3");
+        assert_eq!(book.len(), 2);
+    }
+
+    #[test]
+    fn test_book_moves_frequency() {
+        let book = OpeningBook::parse("e2e4 c7c5\nd2d4 d7d5\ne2e4 e7e5\ne2e4 c7c5 g1f3\n");
+
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let d4 = ChessMove::new(Square::D2, Square::D4, None);
+        assert_eq!(book.book_moves(&Board::default()), vec![(e4, 3), (d4, 1)]);
+
+        let board = Board::default().make_move_new(d4);
+        let d5 = ChessMove::new(Square::D7, Square::D5, None);
+        assert_eq!(book.book_moves(&board), vec![(d5, 1)]);
+
+        let board = board.make_move_new(d5);
+        assert!(book.book_moves(&board).is_empty());
+    }
+
+    #[test]
+    fn test_probe_weighted_by_count() {
+        // e4 is played by 3 lines, d4 by 1
+        let book = OpeningBook::parse("e2e4 c7c5\nd2d4 d7d5\ne2e4 e7e5\ne2e4 c7c5 g1f3\n");
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let rng = &mut test_rng();
+
+        let e4_count = (0..1000)
+            .filter(|_| book.probe(&Board::default(), 0, rng) == BookProbe::Hit(e4))
+            .count();
+        assert!((650..850).contains(&e4_count), "{}", e4_count);
+    }
+
+    #[test]
+    fn test_fen_line() {
+        let book = OpeningBook::parse(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 : g1f3 {Open Sicilian} d7d6\n\
+             8/8/8 w : e2e4\n",
+        );
+        assert_eq!(book.len(), 2);
+
+        let sicilian = [
+            ChessMove::new(Square::E2, Square::E4, None),
+            ChessMove::new(Square::C7, Square::C5, None),
+        ]
+        .iter()
+        .fold(Board::default(), |board, &mov| board.make_move_new(mov));
+        let nf3 = ChessMove::new(Square::G1, Square::F3, None);
+        assert_eq!(book.book_moves(&sicilian), vec![(nf3, 1)]);
+        assert!(book.book_moves(&Board::default()).is_empty());
+    }
+}
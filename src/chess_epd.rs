@@ -0,0 +1,116 @@
+use chess::Board;
+
+use std::str::FromStr;
+
+/// Single line of an EPD file: position (first 4 FEN fields) followed by `opcode operand;` operations
+/// (e.g. `bm e4; ce 35; acd 12;`)
+pub struct EpdRecord {
+    pub board: Board,
+    pub operations: Vec<(String, String)>,
+}
+
+impl EpdRecord {
+    /// Operand of the first operation with `opcode`, quote around string operand are removed
+    pub fn operation(&self, opcode: &str) -> Option<&str> {
+        self.operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operand)| operand.as_str())
+    }
+
+    /// Parse operand of `opcode` as number, None if missing or malformed
+    pub fn number<T: FromStr>(&self, opcode: &str) -> Option<T> {
+        self.operation(opcode)
+            .and_then(|operand| operand.parse().ok())
+    }
+}
+
+impl FromStr for EpdRecord {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut fields = line.trim().splitn(5, ' ');
+        let position = fields.by_ref().take(4).collect::<Vec<_>>();
+        if position.len() < 4 {
+            return Err(format!("\"{}\" doesn't have 4 position fields", line));
+        }
+
+        let board = Board::from_str(&position.join(" ")).map_err(|e| e.to_string())?;
+
+        let operations = fields
+            .next()
+            .unwrap_or("")
+            .split(';')
+            .map(str::trim)
+            .filter(|op| !op.is_empty())
+            .map(|op| {
+                let (opcode, operand) = op.split_once(' ').unwrap_or((op, ""));
+                (
+                    opcode.to_string(),
+                    operand.trim().trim_matches('"').to_string(),
+                )
+            })
+            .collect();
+
+        Ok(EpdRecord { board, operations })
+    }
+}
+
+/// Parse every record of EPD text, blank line and line starting with `#` are skipped,
+/// malformed line are reported and skipped
+pub fn parse_epd(text: &str) -> Vec<EpdRecord> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .filter_map(|(line_num, line)| match EpdRecord::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                println!("EPD line {}: {}, skipped", line_num + 1, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_epd, EpdRecord};
+    use chess::{Board, Color};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_epd_record() {
+        let record = EpdRecord::from_str(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 ce -25; acd 10; id \"after e4\";",
+        )
+        .unwrap();
+
+        assert_eq!(record.board.side_to_move(), Color::Black);
+        assert_eq!(record.number::<i16>("ce"), Some(-25));
+        assert_eq!(record.number::<u8>("acd"), Some(10));
+        assert_eq!(record.operation("id"), Some("after e4"));
+        assert_eq!(record.operation("bm"), None);
+    }
+
+    #[test]
+    fn test_parse_epd_without_operation() {
+        let record =
+            EpdRecord::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -").unwrap();
+
+        assert_eq!(record.board, Board::default());
+        assert!(record.operations.is_empty());
+    }
+
+    #[test]
+    fn test_parse_epd_skip_malformed() {
+        let records = parse_epd(
+            "# comment\n\
+             4k3/8/8/8/8/8/8/4K3 w - - ce 0;\n\
+             \n\
+             not epd\n\
+             4k3/8/8/8/8/8/8/4K3 b - - ce 0;\n",
+        );
+
+        assert_eq!(records.len(), 2);
+    }
+}
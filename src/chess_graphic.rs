@@ -1,7 +1,22 @@
-use crate::chess_minmax::{negamax_prelude, negamax_prelude_2nd, BoardHash, TranspositionItem};
+use crate::chess_book::{BookProbe, OpeningBook};
+use crate::chess_epd::EpdRecord;
+use crate::chess_minmax::main_evalation::{
+    evaluation_pieces_worth_plus, format_debug_evaluation, static_exchange_evaluation, PIECE_VALUE,
+};
+use crate::chess_minmax::{
+    find_blunders, format_pv_trace, merge_cache, negamax_prelude_2nd, principal_variation,
+    random_move, ranked_moves, search_root, second_best_within, win_probability, BoardHash,
+    RepetitionTracker, SearchOptions, SearchStats, TranspositionItem, MAX_TIMED_DEPTH,
+};
+use crate::chess_notation::{fen_halfmove_clock, parse_fen, san};
+use crate::chess_outcome::{game_outcome, GameOutcome};
+use crate::chess_pgn::{append_pgn, parse_pgn, pgn_date, pgn_record};
+use crate::chess_sound::{MoveSound, SoundPlayer};
 
+use self::theme::{Theme, THEMES};
 use chess::{
-    Action, BitBoard, Board, BoardStatus, ChessMove, Color, File, Game, Piece, Rank, Square,
+    Action, BitBoard, Board, BoardStatus, ChessMove, Color, File, Game, MoveGen, Piece, Rank,
+    Square, ALL_FILES, ALL_PIECES, ALL_RANKS, EMPTY,
 };
 use itertools::Itertools;
 use lru::LruCache;
@@ -9,16 +24,104 @@ use piston_window::*;
 use rand::rngs::ThreadRng;
 use rand::{thread_rng, Rng};
 use seahash::SeaHasher;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::fs;
 use std::hash::{BuildHasher, BuildHasherDefault};
 use std::io::{stdin, stdout, Write};
-use std::str::FromStr;
-use std::collections::HashSet;
+use std::iter;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
 
 pub mod colors;
+pub mod theme;
 
 const NUM_FILE: usize = 8;
 const NUM_RANK: usize = 8;
 
+// font of the board coordinate labels
+pub const FONT_PATH: &str = "asset/DejaVuSans.ttf";
+// size (px) of the board coordinate labels
+const COORDINATE_FONT_SIZE: u32 = 14;
+// gap (px) between a coordinate label and the edge of its square
+const COORDINATE_MARGIN: f64 = 3.0;
+// size (px) of the game over banner text
+const BANNER_FONT_SIZE: u32 = 32;
+
+// width of the evaluation bar on the right of the board, in pixel
+const EVAL_BAR_WIDTH: f64 = 24.0;
+// width of the move list on the right of the evaluation bar, in pixel
+const MOVE_LIST_WIDTH: f64 = 160.0;
+// width of the move number column of the move list, in pixel
+const MOVE_NUMBER_WIDTH: f64 = 36.0;
+// size (px) of the move list text, and height of one of its rows
+const MOVE_LIST_FONT_SIZE: u32 = 14;
+const MOVE_LIST_ROW_HEIGHT: f64 = 20.0;
+// rows scrolled by one notch of the mouse wheel
+const MOVE_LIST_SCROLL_ROWS: usize = 3;
+// fraction of the move list column taken by the evaluation graph (at its bottom) when shown
+const EVAL_GRAPH_FRACTION: f64 = 0.25;
+// score (in centipawn) at which the evaluation bar is filled by one side
+const EVAL_BAR_CLAMP: f64 = 1000.0;
+
+// minimum centipawn lost for a move to be consider blunder
+const BLUNDER_THRESHOLD: i16 = 150;
+
+// number of ply after which the game is declared drawn
+pub const DEFAULT_MAX_GAME_LENGTH: usize = 600;
+
+// material lead (centipawn) at which stalemating the opponent is throwing away a win
+const STALEMATE_WARNING_MARGIN: i16 = 500;
+
+const EVAL_TRACE_HEADER: &str = "ply,side,move,eval_before,eval_after,delta";
+
+// pieces offered by the promotion picker, from the promotion square toward the center
+const PROMOTION_PIECES: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+
+// longest move trail selectable with T key
+const MAX_MOVE_TRAIL: usize = 8;
+
+// plies taken back (or replayed) at once by undo (or redo) while holding shift
+const SHIFT_UNDO_PLIES: usize = 5;
+
+// candidate moves printed by the analysis (J key) unless configured, and the most selectable
+const DEFAULT_ANALYSIS_LINES: usize = 3;
+const MAX_ANALYSIS_LINES: usize = 10;
+
+// change of the AI contempt (centipawn) per N key press, and its bound either way
+const CONTEMPT_STEP: i16 = 10;
+const MAX_CONTEMPT: i16 = 200;
+
+// moves per second of the review mode autoplay
+pub const DEFAULT_AUTOPLAY_SPEED: f64 = 1.0;
+const MIN_AUTOPLAY_SPEED: f64 = 0.25;
+const MAX_AUTOPLAY_SPEED: f64 = 16.0;
+
+// minimum time before the AI reply to a human move appears, zero to reply instantly
+const DEFAULT_AI_MOVE_DELAY: Duration = Duration::ZERO;
+
+// seconds a moved piece take to slide to its destination
+const MOVE_ANIMATION_TIME: f64 = 0.2;
+// seconds the board take to flip over
+const FLIP_ANIMATION_TIME: f64 = 0.3;
+
+// file the game is saved to and loaded from (F5/F9) unless configured
+const DEFAULT_SAVE_PATH: &str = "chess_try_save.pgn";
+
+// thinking time per AI move in fixed-time mode unless configured
+const DEFAULT_TIME_BUDGET: Duration = Duration::from_secs(1);
+
+// how often the ponder search is checked while waiting for it to use up the time budget
+const PONDER_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// 2nd best move is only played if it is at most this much centipawn worse than the best move
+pub const DEFAULT_SECOND_BEST_MARGIN: i16 = 100;
+
+// centipawn advantage that give roughly 91% (10:1) winning chance
+pub const DEFAULT_WIN_PROBABILITY_SCALE: f64 = 400.0;
+
 struct ChessTexture {
     white_pawn: G2dTexture,
     black_pawn: G2dTexture,
@@ -37,6 +140,23 @@ struct ChessTexture {
 impl ChessTexture {
     const IMG_SIZE: u32 = 90;
 
+    fn get(&self, piece: Piece, color: Color) -> &G2dTexture {
+        match (color, piece) {
+            (Color::White, Piece::Pawn) => &self.white_pawn,
+            (Color::White, Piece::Knight) => &self.white_knight,
+            (Color::White, Piece::Bishop) => &self.white_bishop,
+            (Color::White, Piece::Rook) => &self.white_rook,
+            (Color::White, Piece::Queen) => &self.white_queen,
+            (Color::White, Piece::King) => &self.white_king,
+            (Color::Black, Piece::Pawn) => &self.black_pawn,
+            (Color::Black, Piece::Knight) => &self.black_knight,
+            (Color::Black, Piece::Bishop) => &self.black_bishop,
+            (Color::Black, Piece::Rook) => &self.black_rook,
+            (Color::Black, Piece::Queen) => &self.black_queen,
+            (Color::Black, Piece::King) => &self.black_king,
+        }
+    }
+
     pub fn new(context: &mut G2dTextureContext) -> Self {
         let empty_setting = TextureSettings::new();
 
@@ -60,20 +180,358 @@ impl ChessTexture {
     }
 }
 
+/// Named AI strength, weaker preset search shallower, evaluate with more noise
+/// and sometimes play the 2nd best move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl Difficulty {
+    fn depth(self) -> u8 {
+        match self {
+            Difficulty::Beginner => 2,
+            Difficulty::Intermediate => 3,
+            Difficulty::Advanced => 4,
+        }
+    }
+
+    /// see `SearchOptions::eval_noise`
+    fn eval_noise(self) -> i16 {
+        match self {
+            Difficulty::Beginner => 60,
+            Difficulty::Intermediate => 25,
+            Difficulty::Advanced => 0,
+        }
+    }
+
+    /// probability of playing the 2nd best move instead of the best
+    fn second_best_chance(self) -> f64 {
+        match self {
+            Difficulty::Beginner => 0.3,
+            Difficulty::Intermediate => 0.1,
+            Difficulty::Advanced => 0.0,
+        }
+    }
+
+    /// next preset, wrapping from the strongest back to the weakest
+    fn next(self) -> Self {
+        match self {
+            Difficulty::Beginner => Difficulty::Intermediate,
+            Difficulty::Intermediate => Difficulty::Advanced,
+            Difficulty::Advanced => Difficulty::Beginner,
+        }
+    }
+}
+
+/// Board animations in progress, advanced by the update event's elapsed time
+#[derive(Debug, Default)]
+struct Animation {
+    // source, destination and progress (0 to 1) of the piece sliding after a move
+    slide: Option<(Square, Square, f64)>,
+    // progress (0 to 1) of the board flip, None once it is done
+    flip: Option<f64>,
+}
+
+impl Animation {
+    fn advance(&mut self, dt: f64) {
+        self.slide = self
+            .slide
+            .map(|(source, dest, t)| (source, dest, t + dt / MOVE_ANIMATION_TIME))
+            .filter(|&(_, _, t)| t < 1.0);
+        self.flip = self
+            .flip
+            .map(|t| t + dt / FLIP_ANIMATION_TIME)
+            .filter(|&t| t < 1.0);
+    }
+
+    fn is_running(&self) -> bool {
+        self.slide.is_some() || self.flip.is_some()
+    }
+}
+
+type PonderResult = (
+    Option<(ChessMove, i16)>,
+    SearchStats,
+    LruCache<BoardHash, TranspositionItem, BuildHasherDefault<SeaHasher>>,
+);
+
+/// Search of the position the AI expect after the human reply, run on another thread
+/// while the human think
+struct PonderSearch {
+    // human move the search expect, and the position before and after it
+    expected: ChessMove,
+    before: Board,
+    board: Board,
+    started: Instant,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<PonderResult>,
+}
+
+impl PonderSearch {
+    /// Start searching `before` after `expected`, with its own cache (merged back once accepted)
+    fn start(
+        before: Board,
+        expected: ChessMove,
+        depth: u8,
+        options: SearchOptions<'static>,
+        repetition: HashSet<BoardHash>,
+    ) -> Self {
+        let board = before.make_move_new(expected);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let options = SearchOptions {
+                stop: Some(&thread_stop),
+                ..options
+            };
+            let mut cache = LruCache::with_hasher(crate::CACHE_SIZE, Default::default());
+            let (best, stats) = search_root(
+                &board,
+                depth,
+                &options,
+                &mut thread_rng(),
+                &mut cache,
+                &repetition,
+            );
+            (best, stats, cache)
+        });
+
+        PonderSearch {
+            expected,
+            before,
+            board,
+            started: Instant::now(),
+            stop,
+            handle,
+        }
+    }
+
+    /// Whether the game at `position` may still reach the searched position
+    fn expects(&self, position: &Board) -> bool {
+        *position == self.before || *position == self.board
+    }
+
+    fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the search to finish
+    fn join(self) -> PonderResult {
+        self.handle.join().expect("ponder search panicked")
+    }
+
+    /// Cut the search short, the result is the deepest completed iteration
+    fn stop(self) -> PonderResult {
+        self.stop.store(true, atomic::Ordering::Relaxed);
+        self.join()
+    }
+}
+
+/// Timer of the review mode autoplay
+struct Autoplay {
+    moves_per_second: f64,
+    // time of the last step, None when paused
+    last_step: Option<Instant>,
+}
+
+impl Autoplay {
+    fn new(moves_per_second: f64) -> Self {
+        Autoplay {
+            moves_per_second,
+            last_step: None,
+        }
+    }
+
+    fn is_playing(&self) -> bool {
+        self.last_step.is_some()
+    }
+
+    fn start(&mut self, now: Instant) {
+        self.last_step = Some(now);
+    }
+
+    fn pause(&mut self) {
+        self.last_step = None;
+    }
+
+    /// Whether the next move is due at `now`, restart the timer if it is
+    fn tick(&mut self, now: Instant) -> bool {
+        match self.last_step {
+            Some(last) if (now - last).as_secs_f64() >= 1.0 / self.moves_per_second => {
+                self.last_step = Some(now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn change_speed(&mut self, factor: f64) {
+        self.moves_per_second =
+            (self.moves_per_second * factor).clamp(MIN_AUTOPLAY_SPEED, MAX_AUTOPLAY_SPEED);
+    }
+}
+
+/// Moves taken back by undo and the takeback limit, takebacks are counted per ply:
+/// undoing a move and the reply to it use 2 of them
+struct Takebacks {
+    // maximum number of plies taken back per game, None for unlimited
+    limit: Option<usize>,
+    // plies taken back in the current game
+    count: usize,
+    // moves taken back, the next one to redo last, cleared by any other move
+    redo_stack: Vec<ChessMove>,
+    // evaluation graph score after each move of `redo_stack` (same order), restored on redo
+    redo_scores: Vec<Option<i16>>,
+}
+
+impl Takebacks {
+    fn new(limit: Option<usize>) -> Self {
+        Takebacks {
+            limit,
+            count: 0,
+            redo_stack: Vec::new(),
+            redo_scores: Vec::new(),
+        }
+    }
+
+    /// Takebacks left in the current game, None for unlimited
+    fn left(&self) -> Option<usize> {
+        self.limit.map(|limit| limit.saturating_sub(self.count))
+    }
+
+    /// `base_game` replayed with only the first `move_count` of `moves` (the moves of the
+    /// current game), the others are pushed on the redo stack with their `eval_history` score.
+    /// Err with the reason when the takeback limit doesn't allow it.
+    fn undo_to(
+        &mut self,
+        base_game: &Game,
+        moves: &[ChessMove],
+        eval_history: &[i16],
+        move_count: usize,
+    ) -> Result<Game, String> {
+        let plies = moves.len() - move_count;
+        match self.left() {
+            Some(0) => {
+                return Err(format!(
+                    "takeback limit ({}) reached for this game",
+                    self.count
+                ))
+            }
+            Some(left) if plies > left => {
+                return Err(format!(
+                    "{} moves to take back but only {} takeback left",
+                    plies, left
+                ))
+            }
+            _ => {}
+        }
+
+        self.count += plies;
+        self.redo_stack
+            .extend(ChessGraphic::redo_order(&moves[move_count..]));
+        self.redo_scores.extend(
+            (move_count..moves.len())
+                .rev()
+                .map(|i| eval_history.get(i).copied()),
+        );
+        Ok(ChessGraphic::replayed_game(base_game, &moves[..move_count]))
+    }
+
+    /// Next move to redo and its score, if any
+    fn pop_redo(&mut self) -> Option<(ChessMove, Option<i16>)> {
+        let mov = self.redo_stack.pop()?;
+        Some((mov, self.redo_scores.pop().flatten()))
+    }
+
+    fn clear_redo(&mut self) {
+        self.redo_stack.clear();
+        self.redo_scores.clear();
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.clear_redo();
+    }
+}
+
 pub struct ChessGraphic {
     base_game: Game,
     chess_game: Game,
     selecting: Option<Square>,
+    // piece being dragged (its square) and the cursor position, dropped on mouse release
+    dragging: Option<(Square, [f64; 2])>,
     mouse_x: f64,
     mouse_y: f64,
-    draw_size: [u32; 2],
+    // size of the window, the board layout (`board_rect`) is derived from it for drawing and clicking
+    window_size: [f64; 2],
     rng: ThreadRng,
     cache: LruCache<BoardHash, TranspositionItem, BuildHasherDefault<SeaHasher>>,
     dirty: bool,
     textures: ChessTexture,
+    // index into `THEMES`
+    theme: usize,
+    // font of the coordinate labels and the move list, no text is drawn without it
+    glyphs: Option<Glyphs>,
+    // first row of the move list once scrolled by the mouse wheel, None to follow the shown move
+    move_list_scroll: Option<usize>,
     depth: u8,
     enable_ai: bool,
     display_swap_side: bool,
+    eval_history: Vec<i16>,
+    show_eval_graph: bool,
+    show_win_probability: bool,
+    win_probability_scale: f64,
+    book: Option<OpeningBook>,
+    in_book: bool,
+    // total AI thinking time of the current game, indexed by `Color::to_index`
+    think_time: [Duration; 2],
+    // number of recent moves highlighted on the board
+    move_trail_length: usize,
+    repetition: RepetitionTracker,
+    max_game_length: usize,
+    search_options: SearchOptions<'static>,
+    takebacks: Takebacks,
+    shift_held: bool,
+    // number of candidate moves printed by the analysis
+    analysis_lines: usize,
+    // set once the game is over, every move input is rejected until reset
+    game_outcome: Option<GameOutcome>,
+    show_book_explorer: bool,
+    // mark where the selected piece can move, ring on captures and dot on quiet moves
+    show_legal_moves: bool,
+    // static exchange evaluation of capturing on the clicked square
+    exchange_display: Option<(Square, i16)>,
+    // number of move shown while reviewing the game, None when playing
+    review_ply: Option<usize>,
+    autoplay: Autoplay,
+    animation: Animation,
+    // warn before a stalemating move while far ahead, the move must be entered again to confirm
+    stalemate_warning: bool,
+    pending_stalemate: Option<ChessMove>,
+    // print evaluation before and after every move as CSV
+    eval_trace: bool,
+    // source and destination of a promotion waiting for the piece to be picked
+    promotion_pending: Option<(Square, Square)>,
+    ai_move_delay: Duration,
+    // when the AI reply to the human move is due, None when no reply is waiting
+    ai_reply_due: Option<Instant>,
+    // PGN file every finished game is appended to
+    pgn_database: Option<String>,
+    // thinking time per move when searching in fixed-time mode (`search_options.time_budget`)
+    time_budget: Duration,
+    // file of F5 (save) and F9 (load)
+    save_path: String,
+    // preset of the AI noise and 2nd best move chance, the depth can still be changed alone
+    difficulty: Difficulty,
+    sound_enabled: bool,
+    // opened on the first sound so only the GUI ever touch the audio device, None if it failed
+    sound_player: OnceCell<Option<SoundPlayer>>,
+    // search the expected human reply on another thread after every AI move
+    pondering: bool,
+    ponder: Option<PonderSearch>,
 }
 
 impl ChessGraphic {
@@ -81,25 +539,49 @@ impl ChessGraphic {
         Self::from_game(Game::new(), texture_context)
     }
 
-    pub fn from_str(
-        fen: &str,
-        texture_context: &mut G2dTextureContext,
-    ) -> Result<Self, <Game as FromStr>::Err> {
-        Ok(Self::from_game(Game::from_str(fen)?, texture_context))
-    }
-
     fn print_control_message() {
         println!("SEMICOLON (;): make AI play");
         println!("BACKSLASH (/): make AI play 2nd best move");
-        println!("Z: Undo move (if possible)");
+        println!("Z: Undo move (if possible), SHIFT+Z: undo 5 moves");
+        println!("   while reviewing: take back every move after the shown one");
+        println!("Y: Redo undone move, SHIFT+Y: redo 5 moves");
         println!("A: toggle AI");
         println!("F: print FEN");
+        println!("E: print evaluation breakdown");
+        println!("P: print evaluation along the principal variation");
+        println!("J: print the best moves of the shown position, SHIFT+J: change how many");
         println!("I: Input FEN");
-        println!("H: print PNG history");
+        println!("H: print PGN of the current game");
         println!("R: Reset Game");
         println!("S: Swap Side");
+        println!("G: toggle evaluation graph");
+        println!("W: toggle win probability display");
+        println!("B: Practice from the worst blunder of this game");
+        println!("T: change number of highlighted last moves");
+        println!("L: toggle move-by-move evaluation trace (CSV)");
+        println!("K: toggle fixed-depth/fixed-time AI search");
+        println!("F5: save game, F9: load saved game");
+        println!("M: toggle legal move markers of the selected piece");
+        println!("O: toggle opening explorer (1-9: play listed book move)");
+        println!("V: toggle review mode (COMMA/PERIOD: step, HOME/END: jump)");
+        println!("SPACE: start/pause review autoplay (UP/DOWN: change speed)");
+        println!("Click a move of the move list to show its position, scroll to browse the list");
+        println!("X: toggle sound");
+        println!("U: toggle pondering (AI think during your turn)");
+        println!("N: increase AI contempt (avoid draws), SHIFT+N: decrease it (seek draws)");
+        println!("C: cycle board theme");
+        println!("D: cycle AI difficulty (Beginner/Intermediate/Advanced)");
         println!("RIGHT: increase AI depth");
-        println!("LEFT: decrease AI depth");
+        println!("LEFT: decrease AI depth (depth 0 play random move)");
+    }
+
+    /// Game replayed from PGN movetext (SAN), starting from its `FEN` tag if present
+    pub fn from_pgn(pgn: &str, texture_context: &mut G2dTextureContext) -> Result<Self, String> {
+        let (base, moves) = parse_pgn(pgn)?;
+        let mut graphic = Self::from_game(Game::new_with_board(base), texture_context);
+        graphic.replay(&moves)?;
+        println!("Loaded {} moves from PGN", moves.len());
+        Ok(graphic)
     }
 
     pub fn from_game(game: Game, texture_context: &mut G2dTextureContext) -> Self {
@@ -113,16 +595,58 @@ impl ChessGraphic {
             base_game: game.clone(),
             chess_game: game,
             selecting: None,
+            dragging: None,
             mouse_x: Default::default(),
             mouse_y: Default::default(),
-            draw_size: Default::default(),
+            window_size: Default::default(),
             rng: thread_rng(),
             cache: LruCache::with_hasher(crate::CACHE_SIZE, Default::default()),
             dirty: true,
             textures: ChessTexture::new(texture_context),
+            theme: 0,
+            glyphs: None,
+            move_list_scroll: None,
             depth: DEFAULT_DEPTH,
             enable_ai: true,
             display_swap_side: false,
+            eval_history: Vec::new(),
+            show_eval_graph: false,
+            show_win_probability: false,
+            win_probability_scale: DEFAULT_WIN_PROBABILITY_SCALE,
+            book: None,
+            in_book: true,
+            think_time: Default::default(),
+            move_trail_length: 1,
+            repetition: RepetitionTracker::default(),
+            max_game_length: DEFAULT_MAX_GAME_LENGTH,
+            search_options: SearchOptions {
+                second_best_margin: Some(DEFAULT_SECOND_BEST_MARGIN),
+                ..Default::default()
+            },
+            takebacks: Takebacks::new(None),
+            shift_held: false,
+            analysis_lines: DEFAULT_ANALYSIS_LINES,
+            game_outcome: None,
+            show_book_explorer: false,
+            show_legal_moves: true,
+            exchange_display: None,
+            review_ply: None,
+            autoplay: Autoplay::new(DEFAULT_AUTOPLAY_SPEED),
+            animation: Animation::default(),
+            stalemate_warning: false,
+            pending_stalemate: None,
+            eval_trace: false,
+            promotion_pending: None,
+            ai_move_delay: DEFAULT_AI_MOVE_DELAY,
+            ai_reply_due: None,
+            pgn_database: None,
+            time_budget: DEFAULT_TIME_BUDGET,
+            save_path: DEFAULT_SAVE_PATH.to_string(),
+            difficulty: Difficulty::Advanced,
+            sound_enabled: cfg!(feature = "sound"),
+            sound_player: OnceCell::new(),
+            pondering: false,
+            ponder: None,
         }
     }
 
@@ -130,6 +654,19 @@ impl ChessGraphic {
         self.chess_game = Game::new();
         self.base_game = self.chess_game.clone();
         self.selecting = None;
+        self.dragging = None;
+        self.exchange_display = None;
+        self.eval_history.clear();
+        self.repetition.clear();
+        self.takebacks.reset();
+        self.game_outcome = None;
+        self.review_ply = None;
+        self.move_list_scroll = None;
+        self.autoplay.pause();
+        self.promotion_pending = None;
+        self.ai_reply_due = None;
+        self.in_book = true;
+        self.think_time = Default::default();
 
         println!("Clearing Cache...");
         self.cache.clear();
@@ -147,61 +684,252 @@ impl ChessGraphic {
         let mut fen = String::with_capacity(70 /* normal length of FEN string */);
         stdin().read_line(&mut fen).unwrap();
 
-        debug_assert!(fen.len() <= 70);
-
-        match Game::from_str(fen.as_str()) {
+        match parse_fen(&fen) {
             Err(e) => println!("{}", e),
-            Ok(game) => {
+            Ok((board, defaulted)) => {
+                if !defaulted.is_empty() {
+                    println!("FEN defaulted {}", defaulted.join(", "));
+                }
                 self.reset();
-                self.chess_game = game;
+                self.chess_game = Game::new_with_board(board);
                 self.base_game = self.chess_game.clone();
+                self.repetition.set_halfmove_clock(fen_halfmove_clock(&fen));
+                self.check_and_report_result();
+            }
+        }
+    }
+
+    /// PGN record (seven-tag roster and SAN movetext) of the current game
+    pub fn export_pgn(&self) -> String {
+        pgn_record(
+            &self.base_game.current_position(),
+            &Self::game_moves(&self.chess_game),
+            self.game_outcome,
+            &pgn_date(SystemTime::now()),
+        )
+    }
+
+    fn print_pgn(&self) {
+        println!("{}", self.export_pgn());
+    }
+
+    /// Play `moves` on the current game (updating evaluation and repetition history)
+    fn replay(&mut self, moves: &[ChessMove]) -> Result<(), String> {
+        for &mov in moves {
+            if !self.make_move(mov)? {
+                return Err(format!("{} is not a legal move", Self::format_move(&mov)));
             }
         }
+        Ok(())
+    }
+
+    /// Write the game (with its starting position) as PGN to `save_path`
+    fn save_game(&self) {
+        match fs::write(&self.save_path, self.export_pgn()) {
+            Ok(()) => println!("Game saved to {}", self.save_path),
+            Err(e) => println!("Error: can't save game to {}: {}", self.save_path, e),
+        }
     }
 
-    pub fn png_history(&mut self) {
-        // let s = String::new();
-        for act in self.chess_game.actions() {
-            if let Action::MakeMove(mov) = act {
-                print!("{} ", Self::format_move(mov));
+    /// Replace the game by the one saved in `save_path`,
+    /// the current game is kept if the file can't be read or has an illegal move
+    fn load_game(&mut self) {
+        let pgn = match fs::read_to_string(&self.save_path) {
+            Ok(pgn) => pgn,
+            Err(e) => {
+                println!("Error: can't load game from {}: {}", self.save_path, e);
+                return;
+            }
+        };
+        // every move is checked before the current game is touched
+        let (base, moves) = match parse_pgn(&pgn) {
+            Ok(game) => game,
+            Err(e) => {
+                println!(
+                    "Error: {} is not a valid game ({}), current game kept",
+                    self.save_path, e
+                );
+                return;
             }
+        };
+
+        self.reset();
+        self.chess_game = Game::new_with_board(base);
+        self.base_game = self.chess_game.clone();
+        match self.replay(&moves) {
+            Ok(()) => println!(
+                "Game loaded from {} ({} moves)",
+                self.save_path,
+                moves.len()
+            ),
+            Err(e) => println!("Error: {}", e),
         }
-        println!();
+        self.check_and_report_result();
+        self.mark_dirty();
     }
 
     // DRAW
-    pub fn draw(&mut self, c: Context, g: &mut G2d) {
+    /// Redraw the board if anything changed since the last draw, return whether it did
+    /// (the window shouldn't swap buffers otherwise, the back buffer is stale)
+    pub fn draw(&mut self, c: Context, g: &mut G2d) -> bool {
         if self.dirty {
             self.redraw(c, g);
-            self.dirty = true;
+            self.dirty = false;
+            true
+        } else {
+            false
         }
     }
 
-    fn redraw(&self, c: Context, g: &mut G2d) {
-        Self::draw_grid(c, g, 8, 8);
-        if let Some(&last_mov) = self.chess_game.actions().iter().rev().find_map(|act| {
-            if let Action::MakeMove(mov) = act {
-                Some(mov)
-            } else {
-                None
+    fn redraw(&mut self, c: Context, g: &mut G2d) {
+        let theme = &THEMES[self.theme];
+        let window_c = c;
+        self.window_size = c.viewport.unwrap().window_size;
+        clear(colors::LETTERBOX, g);
+
+        // everything but the evaluation bar lay out in the board viewport, its origin on the board corner
+        let [x0, y0, side, _] = Self::board_rect(self.window_size);
+        let mut c = c.trans(x0, y0);
+        c.viewport = c.viewport.map(|viewport| Viewport {
+            window_size: [side, side],
+            ..viewport
+        });
+
+        // the board (but not the overlays) is squashed vertically while flipping
+        let (swap, flip_scale) = Self::flip_view(self.display_swap_side, self.animation.flip);
+        let [_, h] = c.viewport.unwrap().window_size;
+        let board_c = c
+            .trans(0.0, h / 2.0)
+            .scale(1.0, flip_scale)
+            .trans(0.0, -h / 2.0);
+
+        Self::draw_grid(board_c, g, theme, 8, 8);
+        if let Some(glyphs) = &mut self.glyphs {
+            Self::draw_coordinates(board_c, g, glyphs, theme, swap);
+        }
+
+        let mut moves = Self::game_moves(&self.chess_game);
+        let board = match self.review_ply {
+            Some(ply) => {
+                moves.truncate(ply);
+                Self::position_after(&self.base_game.current_position(), &moves)
+            }
+            None => self.chess_game.current_position(),
+        };
+
+        let trail = moves
+            .iter()
+            .rev()
+            .take(self.move_trail_length)
+            .copied()
+            .collect::<Vec<_>>();
+        Self::draw_move_trail(board_c, g, &trail, theme, swap);
+        if let Some(king) = Self::king_in_check(&board) {
+            let rect = Self::square_to_rect(&king, &board_c.viewport.unwrap(), swap);
+            rectangle(colors::KING_IN_CHECK, rect, board_c.transform, g);
+        }
+        // the reviewed position isn't the one the move was played on
+        let slide = self.animation.slide.filter(|_| self.review_ply.is_none());
+        let hidden = self
+            .dragging
+            .map(|(square, _)| square)
+            .into_iter()
+            .chain(slide.map(|(_, dest, _)| dest))
+            .fold(EMPTY, |hidden, square| {
+                hidden | BitBoard::from_square(square)
+            });
+        Self::draw_pieces(board_c, g, &board, &self.textures, hidden, swap);
+
+        if let Some((source, dest, t)) = slide {
+            if let (Some(piece), Some(color)) = (board.piece_on(dest), board.color_on(dest)) {
+                let viewport = board_c.viewport.unwrap();
+                let pos = Self::slide_position(
+                    Self::square_to_rect(&source, &viewport, swap),
+                    Self::square_to_rect(&dest, &viewport, swap),
+                    t,
+                );
+                Self::draw_floating_piece(board_c, g, self.textures.get(piece, color), pos);
+            }
+        }
+
+        if self.show_book_explorer {
+            if let Some(book) = &self.book {
+                let book_moves = book.book_moves(&self.chess_game.current_position());
+                Self::draw_book_moves(board_c, g, &book_moves, swap);
             }
-        }) {
-            Self::draw_last_move(c, g, last_mov, self.display_swap_side);
         }
-        Self::draw_pieces(
-            c,
-            g,
-            &self.chess_game.current_position(),
-            &self.textures,
-            self.display_swap_side,
-        );
 
         if let Some(square) = self.selecting {
-            Self::draw_selecting(c, g, square, self.display_swap_side);
+            Self::draw_selecting(board_c, g, square, theme.selected, swap);
+            if self.show_legal_moves {
+                let destinations = Self::legal_destinations(&board, square);
+                Self::draw_legal_moves(board_c, g, &destinations, swap);
+            }
+        }
+
+        if let Some((square, pos)) = self.dragging {
+            if let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square)) {
+                let [x, y] = pos;
+                let texture = self.textures.get(piece, color);
+                Self::draw_floating_piece(c, g, texture, [x - x0, y - y0]);
+            }
+        }
+
+        if let Some((square, value)) = self.exchange_display {
+            Self::draw_exchange_value(board_c, g, square, value, swap);
+        }
+
+        if let Some((_, dest)) = self.promotion_pending {
+            let side = self.chess_game.side_to_move();
+            Self::draw_promotion_picker(board_c, g, dest, side, &self.textures, swap);
+        }
+
+        // searched score of the shown position if the AI played it, static evaluation otherwise
+        let score = moves
+            .len()
+            .checked_sub(1)
+            .and_then(|i| self.eval_history.get(i).copied())
+            .unwrap_or_else(|| evaluation_pieces_worth_plus(&board));
+        let bar_rect = Self::eval_bar_rect(self.window_size);
+        Self::draw_eval_bar(window_c, g, bar_rect, score);
+        self.draw_move_list(window_c, g);
+        if self.show_eval_graph {
+            let graph_rect = Self::eval_graph_rect(self.window_size);
+            Self::draw_eval_graph(window_c, g, graph_rect, &self.eval_history);
+        }
+
+        if self.game_outcome.is_some() {
+            let [w, h] = c.viewport.unwrap().window_size;
+            rectangle(colors::GAME_OVER_OVERLAY, [0.0, 0.0, w, h], c.transform, g);
+        }
+
+        if let (Some(outcome), Some(glyphs)) = (self.game_outcome, &mut self.glyphs) {
+            Self::draw_game_over_banner(c, g, glyphs, &outcome.banner());
         }
     }
 
-    fn draw_grid(c: Context, g: &mut G2d, n_width: u32, n_height: u32) {
+    /// Result text centered on a band across the middle of the board
+    fn draw_game_over_banner(c: Context, g: &mut G2d, glyphs: &mut Glyphs, banner: &str) {
+        let [w, h] = c.viewport.unwrap().window_size;
+        let font_size = f64::from(BANNER_FONT_SIZE);
+        let band_height = 2.0 * font_size;
+        rectangle(
+            colors::GAME_OVER_BANNER,
+            [0.0, (h - band_height) / 2.0, w, band_height],
+            c.transform,
+            g,
+        );
+
+        let text_width = glyphs.width(BANNER_FONT_SIZE, banner).unwrap_or_default();
+        // baseline a bit below the middle so the capital letters look centered
+        let transform = c
+            .transform
+            .trans((w - text_width) / 2.0, (h + font_size * 0.7) / 2.0);
+        let label = text::Text::new_color(colors::GAME_OVER_TEXT, BANNER_FONT_SIZE);
+        let _ = label.draw(banner, glyphs, &c.draw_state, transform, g);
+    }
+
+    fn draw_grid(c: Context, g: &mut G2d, theme: &Theme, n_width: u32, n_height: u32) {
         let [w, h] = c.viewport.unwrap().window_size;
 
         let dw = w as u32 / n_width;
@@ -215,8 +943,8 @@ impl ChessGraphic {
             let grid_rect = rectangle::rectangle_by_corners(x0, y0, x1, y1);
 
             let grid_color = match (i + j) % 2 {
-                0 => colors::GRID_COLOR_1,
-                1 => colors::GRID_COLOR_2,
+                0 => theme.grid_color_1,
+                1 => theme.grid_color_2,
                 _ => unreachable!(),
             };
 
@@ -224,27 +952,112 @@ impl ChessGraphic {
         }
     }
 
-    fn draw_last_move(c: Context, g: &mut G2d, last_mov: ChessMove, swap: bool) {
+    /// File letters along the bottom edge and rank numbers along the left edge
+    fn draw_coordinates(c: Context, g: &mut G2d, glyphs: &mut Glyphs, theme: &Theme, swap: bool) {
+        let label = text::Text::new_color(theme.coordinate_label, COORDINATE_FONT_SIZE);
+        for (text, [x, y]) in Self::coordinate_labels(&c.viewport.unwrap(), swap) {
+            let transform = c.transform.trans(x, y);
+            // a glyph failing to render only lose a label
+            let _ = label.draw(&text.to_string(), glyphs, &c.draw_state, transform, g);
+        }
+    }
+
+    /// Coordinate labels with the position of their baseline start,
+    /// files in the bottom right corner of the bottom row and ranks in the top left corner
+    /// of the left column, following the displayed orientation
+    fn coordinate_labels(viewport: &Viewport, swap: bool) -> Vec<(char, [f64; 2])> {
+        let (bottom_rank, left_file) = if swap {
+            (Rank::Eighth, File::H)
+        } else {
+            (Rank::First, File::A)
+        };
+        let font_size = f64::from(COORDINATE_FONT_SIZE);
+
+        let files = ALL_FILES.iter().map(|&file| {
+            let square = Square::make_square(bottom_rank, file);
+            let [x, y, w, h] = Self::square_to_rect(&square, viewport, swap);
+            let text = (b'a' + file.to_index() as u8) as char;
+            // rectangle_by_corners normalize to top left corner with positive size
+            (text, [x + w - font_size, y + h - COORDINATE_MARGIN])
+        });
+        let ranks = ALL_RANKS.iter().map(|&rank| {
+            let square = Square::make_square(rank, left_file);
+            let [x, y, _, _] = Self::square_to_rect(&square, viewport, swap);
+            let text = (b'1' + rank.to_index() as u8) as char;
+            (text, [x + COORDINATE_MARGIN, y + font_size])
+        });
+        files.chain(ranks).collect()
+    }
+
+    /// Highlight recent moves (most recent first), older move fade out
+    fn draw_move_trail(c: Context, g: &mut G2d, trail: &[ChessMove], theme: &Theme, swap: bool) {
+        let n = trail.len();
+        // draw oldest first so newer move are on top
+        for (i, mov) in trail.iter().enumerate().rev() {
+            let fade = (n - i) as f32 / n as f32;
+            Self::draw_last_move(c, g, *mov, theme.grid_color_moved, fade, swap);
+        }
+    }
+
+    fn draw_last_move(
+        c: Context,
+        g: &mut G2d,
+        last_mov: ChessMove,
+        color: [f32; 4],
+        fade: f32,
+        swap: bool,
+    ) {
+        let [r, gr, b, a] = color;
+        let color = [r, gr, b, a * fade];
+
         let source_rect = Self::square_to_rect(&last_mov.get_source(), &c.viewport.unwrap(), swap);
-        rectangle(colors::GRID_COLOR_MOVED, source_rect, c.transform, g);
+        rectangle(color, source_rect, c.transform, g);
         let destination_rect =
             Self::square_to_rect(&last_mov.get_dest(), &c.viewport.unwrap(), swap);
-        rectangle(colors::GRID_COLOR_MOVED, destination_rect, c.transform, g);
+        rectangle(color, destination_rect, c.transform, g);
     }
 
-    fn draw_pieces(c: Context, g: &mut G2d, board: &Board, textures: &ChessTexture, swap: bool) {
+    /// Highlight destination of every book move, more frequent move are more opaque
+    fn draw_book_moves(c: Context, g: &mut G2d, book_moves: &[(ChessMove, u32)], swap: bool) {
+        let total = book_moves.iter().map(|(_, count)| count).sum::<u32>();
+        let [r, gr, b, a] = colors::BOOK_MOVE;
+        for (mov, count) in book_moves {
+            let color = [r, gr, b, a * *count as f32 / total as f32];
+            let rect = Self::square_to_rect(&mov.get_dest(), &c.viewport.unwrap(), swap);
+            rectangle(color, rect, c.transform, g);
+        }
+    }
+
+    /// Square of the side to move's king if it is in check
+    fn king_in_check(board: &Board) -> Option<Square> {
+        if *board.checkers() == EMPTY {
+            None
+        } else {
+            Some(board.king_square(board.side_to_move()))
+        }
+    }
+
+    /// Draw every piece except those on `hidden` (being dragged or sliding)
+    fn draw_pieces(
+        c: Context,
+        g: &mut G2d,
+        board: &Board,
+        textures: &ChessTexture,
+        hidden: BitBoard,
+        swap: bool,
+    ) {
         let vp_ref = &c.viewport.unwrap();
 
         let img_size = ChessTexture::IMG_SIZE as f64;
         let [view_width, view_height] = vp_ref.window_size;
-        let [view_width, view_height] = [view_width as f64, view_height as f64];
         let grid_width = view_width / NUM_FILE as f64; // grid width
         let grid_height = view_height / NUM_RANK as f64; // grid height
         let sx = grid_width / img_size;
         let sy = grid_height / img_size;
 
-        let white = board.color_combined(Color::White);
-        let black = board.color_combined(Color::Black);
+        let shown = !hidden;
+        let white = board.color_combined(Color::White) & shown;
+        let black = board.color_combined(Color::Black) & shown;
 
         let pawn = board.pieces(Piece::Pawn);
         let bishop = board.pieces(Piece::Bishop);
@@ -275,207 +1088,1768 @@ impl ChessGraphic {
         draw_piece(black & queen, &textures.black_queen);
     }
 
-    fn draw_selecting(c: Context, g: &mut G2d, square: Square, swap: bool) {
+    /// Piece centered on `[x, y]` (the cursor or along its slide), sized like a square
+    fn draw_floating_piece(c: Context, g: &mut G2d, texture: &G2dTexture, [x, y]: [f64; 2]) {
+        let [view_width, view_height] = c.viewport.unwrap().window_size;
+        let grid_width = view_width / NUM_FILE as f64;
+        let grid_height = view_height / NUM_RANK as f64;
+        let img_size = ChessTexture::IMG_SIZE as f64;
+
+        let transform = c
+            .trans(x - grid_width / 2.0, y - grid_height / 2.0)
+            .scale(grid_width / img_size, grid_height / img_size)
+            .transform;
+        image(texture, transform, g);
+    }
+
+    fn draw_promotion_picker(
+        c: Context,
+        g: &mut G2d,
+        dest: Square,
+        side: Color,
+        textures: &ChessTexture,
+        swap: bool,
+    ) {
+        let vp_ref = &c.viewport.unwrap();
+        let img_size = ChessTexture::IMG_SIZE as f64;
+
+        for (square, piece) in Self::promotion_picker(dest, side).iter() {
+            let rect = Self::square_to_rect(square, vp_ref, swap);
+            let [x0, y0, w, h] = rect;
+            rectangle(colors::PROMOTION_PICKER, rect, c.transform, g);
+            image(
+                textures.get(*piece, side),
+                c.trans(x0, y0).scale(w / img_size, h / img_size).transform,
+                g,
+            );
+        }
+    }
+
+    fn draw_selecting(c: Context, g: &mut G2d, square: Square, color: [f32; 4], swap: bool) {
         let draw_rect = Self::square_to_rect(&square, &c.viewport.unwrap(), swap);
         let marking_rect = rectangle::margin(draw_rect, 0.5);
 
-        ellipse(colors::COLOR_SELECTED, marking_rect, c.transform, g);
+        ellipse(color, marking_rect, c.transform, g);
     }
 
-    // INPUT HANDLING
-    pub fn button_input(&mut self, button: &Button) {
-        match button {
-            Button::Keyboard(key) => self.keyboard_input(*key),
-            Button::Mouse(mouse) => self.mouse_input(*mouse),
-            Button::Controller(_) => {}
-            Button::Hat(_) => {}
+    /// Ring around capture destinations and centered dot on quiet move destinations
+    fn draw_legal_moves(c: Context, g: &mut G2d, destinations: &[(Square, bool)], swap: bool) {
+        for &(square, is_capture) in destinations {
+            let [x, y, w, h] = Self::square_to_rect(&square, &c.viewport.unwrap(), swap);
+            if is_capture {
+                Ellipse::new_border(colors::LEGAL_CAPTURE, w * 0.06).draw(
+                    [x, y, w, h],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            } else {
+                let dot = [x + w * 0.35, y + h * 0.35, w * 0.3, h * 0.3];
+                ellipse(colors::LEGAL_MOVE, dot, c.transform, g);
+            }
         }
     }
 
-    fn mouse_input(&mut self, mouse: MouseButton) {
-        if mouse != MouseButton::Left {
-            return;
-        }
+    /// Bar along the bottom of `square`, green for winning exchange and orange for losing,
+    /// its length is proportional to the value (full width at a queen)
+    fn draw_exchange_value(c: Context, g: &mut G2d, square: Square, value: i16, swap: bool) {
+        let [x, y, w, h] = Self::square_to_rect(&square, &c.viewport.unwrap(), swap);
+        let ratio = (f64::from(value.abs()) / 900.0).clamp(0.1, 1.0);
+        let color = if value >= 0 {
+            colors::EXCHANGE_GOOD
+        } else {
+            colors::EXCHANGE_BAD
+        };
 
-        self.mark_dirty();
-        let clicking_square = Self::pos_to_square(
-            self.draw_size,
-            self.mouse_x,
-            self.mouse_y,
-            self.display_swap_side,
+        rectangle(
+            color,
+            [x, y + h * 0.85, w * ratio, h * 0.15],
+            c.transform,
+            g,
         );
+    }
 
-        match self.selecting {
-            // no square previously select
-            None => {
-                self.selecting = Some(clicking_square);
-            }
-            // predicate "there exist square for which the user previously select" is true
-            Some(select_square) => {
-                // handle promotion
-                let is_selecting_pawn = || {
-                    self.chess_game
-                        .current_position()
-                        .piece_on(select_square)
-                        .map_or(false, |x| x == Piece::Pawn)
-                };
-                let is_clicking_at_promotable_square = || {
-                    let promotable_rank = match self.chess_game.side_to_move() {
-                        Color::White => Rank::Eighth,
-                        Color::Black => Rank::First,
-                    };
-                    clicking_square.get_rank() == promotable_rank
-                };
-
-                let promotion = if is_clicking_at_promotable_square() && is_selecting_pawn() {
-                    // TODO: user select promotion?
-                    Some(Piece::Queen)
-                } else {
-                    None
-                };
+    /// Vertical bar in `[x, y, w, h]`, the white part grow with White advantage
+    fn draw_eval_bar(c: Context, g: &mut G2d, [x, y, w, h]: [f64; 4], score: i16) {
+        let white_height = h * Self::eval_bar_fraction(score);
 
-                // generate user's move
-                let mov = ChessMove::new(select_square, clicking_square, promotion);
+        rectangle(colors::EVAL_BAR_BLACK, [x, y, w, h], c.transform, g);
+        rectangle(
+            colors::EVAL_BAR_WHITE,
+            [x, y + h - white_height, w, white_height],
+            c.transform,
+            g,
+        );
+        let mid_y = y + h / 2.0;
+        line(
+            colors::EVAL_BAR_MIDDLE,
+            1.0,
+            [x, mid_y, x + w, mid_y],
+            c.transform,
+            g,
+        );
+    }
 
-                // check legality
-                if self.chess_game.current_position().legal(mov) {
-                    // move is legal
-                    self.make_move_msg(mov); // make that legal move
-                    self.selecting = None; // deselect the pieces
+    /// Numbered SAN moves, one row per move pair, the move of the shown position highlighted
+    fn draw_move_list(&mut self, c: Context, g: &mut G2d) {
+        let rect = Self::move_list_rect(self.window_size, self.show_eval_graph);
+        let [x0, y0, w, _] = rect;
+        rectangle(colors::MOVE_LIST_BACKGROUND, rect, c.transform, g);
+
+        let base = self.base_game.current_position();
+        let moves = Self::game_moves(&self.chess_game);
+        let (first_side, current) = self.move_list_state();
+        let top = self.move_list_top();
+        let column_width = (w - MOVE_NUMBER_WIDTH) / 2.0;
+        let cell_x = |column: usize| x0 + MOVE_NUMBER_WIDTH + column as f64 * column_width;
+        let row_y = |row: usize| y0 + (row - top) as f64 * MOVE_LIST_ROW_HEIGHT;
+        let bottom =
+            Self::move_list_rows(first_side, moves.len()).min(top + Self::move_list_visible(rect));
+        let rows = top..bottom;
+
+        if let Some((row, column)) = current.map(|index| Self::move_list_cell(first_side, index)) {
+            if rows.contains(&row) {
+                let cell = [
+                    cell_x(column),
+                    row_y(row),
+                    column_width,
+                    MOVE_LIST_ROW_HEIGHT,
+                ];
+                rectangle(colors::MOVE_LIST_CURRENT, cell, c.transform, g);
+            }
+        }
 
-                    if self.enable_ai {
-                        self.ai_play(false);
-                    }
-                } else {
-                    // move is illegal
-                    self.selecting = None; // deselect the pieces
-                }
+        let Some(glyphs) = &mut self.glyphs else {
+            return;
+        };
+        let mut draw_text = |text: &str, color, x: f64, row: usize| {
+            // baseline a bit below the middle of the row, as for the game over banner
+            let y =
+                row_y(row) + (MOVE_LIST_ROW_HEIGHT + f64::from(MOVE_LIST_FONT_SIZE) * 0.7) / 2.0;
+            let label = text::Text::new_color(color, MOVE_LIST_FONT_SIZE);
+            let _ = label.draw(
+                text,
+                glyphs,
+                &c.draw_state,
+                c.transform.trans(x + 4.0, y),
+                g,
+            );
+        };
+        for row in rows.clone() {
+            draw_text(&format!("{}.", row + 1), colors::MOVE_LIST_NUMBER, x0, row);
+        }
+        if first_side == Color::Black && rows.contains(&0) {
+            draw_text("...", colors::MOVE_LIST_TEXT, cell_x(0), 0);
+        }
+        for (index, text) in Self::move_list(&base, &moves).iter().enumerate() {
+            let (row, column) = Self::move_list_cell(first_side, index);
+            if rows.contains(&row) {
+                draw_text(text, colors::MOVE_LIST_TEXT, cell_x(column), row);
             }
         }
     }
 
-    fn keyboard_input(&mut self, key: Key) {
-        match key {
-            Key::F => println!("{}", self.chess_game.current_position()),
-            Key::Semicolon => self.ai_play(false),
-            Key::Backslash => self.ai_play(true),
-            Key::Z => self.undo(),
-            Key::Right | Key::Plus | Key::NumPadPlus => {
-                self.depth += 1;
-                println!("AI: Set Depth={}", self.depth)
-            }
-            Key::Left | Key::Minus | Key::NumPadMinus => {
-                self.depth = self.depth.saturating_sub(1);
-                println!("AI: Set Depth={}", self.depth)
-            }
-            Key::H => self.png_history(),
-            Key::A => {
-                if self.enable_ai {
-                    println!("Disable AI");
-                    self.enable_ai = false;
-                } else {
-                    println!("Enable AI");
-                    self.enable_ai = true;
+    /// SAN of every move of the game played from `base`
+    fn move_list(base: &Board, moves: &[ChessMove]) -> Vec<String> {
+        let mut board = *base;
+        moves
+            .iter()
+            .map(|&mov| {
+                let text = san(&board, mov);
+                board = board.make_move_new(mov);
+                text
+            })
+            .collect()
+    }
+
+    /// Side of the first move of the move list, and index of the move of the shown position
+    /// (None at the start of the game)
+    fn move_list_state(&self) -> (Color, Option<usize>) {
+        let move_count = Self::game_moves(&self.chess_game).len();
+        let shown = Self::shown_ply(self.review_ply, move_count);
+        (self.base_game.side_to_move(), shown.checked_sub(1))
+    }
+
+    /// Number of moves played in the shown position, `review_ply` clamped to the `move_count`
+    /// moves of the game (the draw offers ending a game aren't moves)
+    fn shown_ply(review_ply: Option<usize>, move_count: usize) -> usize {
+        review_ply.unwrap_or(move_count).min(move_count)
+    }
+
+    /// First row shown by the move list
+    fn move_list_top(&self) -> usize {
+        let (first_side, current) = self.move_list_state();
+        let move_count = Self::game_moves(&self.chess_game).len();
+        Self::move_list_first_row(
+            self.move_list_scroll,
+            current.map(|index| Self::move_list_cell(first_side, index).0),
+            Self::move_list_rows(first_side, move_count),
+            Self::move_list_visible(Self::move_list_rect(self.window_size, self.show_eval_graph)),
+        )
+    }
+
+    /// Row and column (0 for White, 1 for Black) of the `index`th move of the move list,
+    /// a game set up with Black to move start in the Black column
+    fn move_list_cell(first_side: Color, index: usize) -> (usize, usize) {
+        let cell = index + first_side.to_index();
+        (cell / 2, cell % 2)
+    }
+
+    /// Index of the move in the `row` and `column` cell, None for an empty cell
+    fn move_list_index(
+        first_side: Color,
+        row: usize,
+        column: usize,
+        move_count: usize,
+    ) -> Option<usize> {
+        (row * 2 + column)
+            .checked_sub(first_side.to_index())
+            .filter(|&index| index < move_count)
+    }
+
+    fn move_list_rows(first_side: Color, move_count: usize) -> usize {
+        (move_count + first_side.to_index()).div_ceil(2)
+    }
+
+    /// Number of whole rows fitting in the move list `[x, y, w, h]`
+    fn move_list_visible([_, _, _, h]: [f64; 4]) -> usize {
+        (h / MOVE_LIST_ROW_HEIGHT) as usize
+    }
+
+    /// First row of a move list showing `visible` of its `rows`: the scrolled one, or the one
+    /// keeping `current_row` in view (at the bottom once the list overflow)
+    fn move_list_first_row(
+        scroll: Option<usize>,
+        current_row: Option<usize>,
+        rows: usize,
+        visible: usize,
+    ) -> usize {
+        let first = match scroll {
+            Some(first) => first,
+            None => current_row.map_or(0, |row| (row + 1).saturating_sub(visible)),
+        };
+        first.min(rows.saturating_sub(visible))
+    }
+
+    /// Row and column of the move list cell at `[x, y]` of the window, `top` being the first
+    /// row shown, None outside the move columns
+    fn move_list_hit(rect: [f64; 4], top: usize, x: f64, y: f64) -> Option<(usize, usize)> {
+        let [x0, y0, w, _] = rect;
+        let column_width = (w - MOVE_NUMBER_WIDTH) / 2.0;
+        let (x, y) = (x - x0 - MOVE_NUMBER_WIDTH, y - y0);
+        let height = Self::move_list_visible(rect) as f64 * MOVE_LIST_ROW_HEIGHT;
+        if !(0.0..2.0 * column_width).contains(&x) || !(0.0..height).contains(&y) {
+            return None;
+        }
+        Some((
+            top + (y / MOVE_LIST_ROW_HEIGHT) as usize,
+            (x / column_width) as usize,
+        ))
+    }
+
+    /// Index of the move under the cursor in the move list
+    fn hovered_move(&self) -> Option<usize> {
+        let rect = Self::move_list_rect(self.window_size, self.show_eval_graph);
+        let (row, column) =
+            Self::move_list_hit(rect, self.move_list_top(), self.mouse_x, self.mouse_y)?;
+        let move_count = Self::game_moves(&self.chess_game).len();
+        Self::move_list_index(self.base_game.side_to_move(), row, column, move_count)
+    }
+
+    /// Show the position after the `index`th move, in review mode unless it is the last move
+    fn jump_to_move(&mut self, index: usize) {
+        self.autoplay.pause();
+        self.selecting = None;
+        self.dragging = None;
+        self.exchange_display = None;
+        if self.promotion_pending.take().is_some() {
+            println!("Promotion cancelled");
+        }
+
+        let move_count = Self::game_moves(&self.chess_game).len();
+        let ply = index + 1;
+        if ply >= move_count {
+            self.review_ply = None;
+        } else {
+            self.review_ply = Some(ply);
+            println!(
+                "Showing move {} of {} (V to return to the game, \
+                 Z to take back every move after the shown one)",
+                ply, move_count
+            );
+        }
+        self.move_list_scroll = None;
+        self.mark_dirty();
+    }
+
+    /// Share of the evaluation bar filled by White, saturating at `EVAL_BAR_CLAMP`
+    fn eval_bar_fraction(score: i16) -> f64 {
+        let rel_score = f64::from(score).clamp(-EVAL_BAR_CLAMP, EVAL_BAR_CLAMP) / EVAL_BAR_CLAMP;
+        0.5 + rel_score / 2.0
+    }
+
+    /// `[x, y, side, side]` of the board in a window of `window_size`: the largest square
+    /// that fit beside the evaluation bar and move list, all together centered in the window
+    fn board_rect([w, h]: [f64; 2]) -> [f64; 4] {
+        let sidebar = EVAL_BAR_WIDTH + MOVE_LIST_WIDTH;
+        let side = (w - sidebar).min(h).max(0.0);
+        let x = ((w - side - sidebar) / 2.0).max(0.0);
+        let y = (h - side) / 2.0;
+        [x, y, side, side]
+    }
+
+    /// `[x, y, w, h]` of the evaluation bar, right of the board and as tall as it
+    fn eval_bar_rect(window_size: [f64; 2]) -> [f64; 4] {
+        let [x, y, side, _] = Self::board_rect(window_size);
+        [x + side, y, EVAL_BAR_WIDTH, side]
+    }
+
+    /// `[x, y, w, h]` of the move list, right of the evaluation bar and as tall as the board
+    /// less the evaluation graph below it when `eval_graph` is shown
+    fn move_list_rect(window_size: [f64; 2], eval_graph: bool) -> [f64; 4] {
+        let [x, y, w, h] = Self::eval_bar_rect(window_size);
+        let graph_height = if eval_graph {
+            Self::eval_graph_rect(window_size)[3]
+        } else {
+            0.0
+        };
+        [x + w, y, MOVE_LIST_WIDTH, h - graph_height]
+    }
+
+    /// `[x, y, w, h]` of the evaluation graph, bottom `EVAL_GRAPH_FRACTION` of the move list column
+    fn eval_graph_rect(window_size: [f64; 2]) -> [f64; 4] {
+        let [x, y, w, h] = Self::eval_bar_rect(window_size);
+        let graph_height = h * EVAL_GRAPH_FRACTION;
+        [x + w, y + h - graph_height, MOVE_LIST_WIDTH, graph_height]
+    }
+
+    /// Square under the cursor, None when it is off the board
+    fn hovered_square(&self) -> Option<Square> {
+        Self::window_pos_to_square(
+            self.window_size,
+            self.mouse_x,
+            self.mouse_y,
+            self.display_swap_side,
+        )
+    }
+
+    /// Square at `[x, y]` of a window of `window_size`, None outside the board
+    fn window_pos_to_square(window_size: [f64; 2], x: f64, y: f64, swap: bool) -> Option<Square> {
+        let [x0, y0, side, _] = Self::board_rect(window_size);
+        let (x, y) = (x - x0, y - y0);
+        if !(0.0..side).contains(&x) || !(0.0..side).contains(&y) {
+            return None;
+        }
+        Some(Self::pos_to_square([side, side], x, y, swap))
+    }
+
+    /// Score after every move in `graph_rect`, White advantage upward
+    fn draw_eval_graph(c: Context, g: &mut G2d, graph_rect: [f64; 4], eval_history: &[i16]) {
+        // score (in centipawn) at which the graph saturate
+        const GRAPH_CLAMP: f64 = 1000.0;
+
+        let [x0, y0, graph_width, graph_height] = graph_rect;
+        let mid_y = y0 + graph_height / 2.0;
+
+        rectangle(colors::GRAPH_BACKGROUND, graph_rect, c.transform, g);
+        line(
+            colors::GRAPH_AXIS,
+            1.0,
+            [x0, mid_y, x0 + graph_width, mid_y],
+            c.transform,
+            g,
+        );
+
+        if eval_history.len() < 2 {
+            return;
+        }
+
+        // higher = better for white
+        let score_to_y = |score: i16| {
+            let rel_score = f64::from(score).clamp(-GRAPH_CLAMP, GRAPH_CLAMP) / GRAPH_CLAMP;
+            mid_y - rel_score * graph_height / 2.0
+        };
+        let dx = graph_width / (eval_history.len() - 1) as f64;
+
+        for (i, (&s0, &s1)) in eval_history.iter().tuple_windows().enumerate() {
+            let x = x0 + dx * i as f64;
+            line(
+                colors::GRAPH_LINE,
+                1.5,
+                [x, score_to_y(s0), x + dx, score_to_y(s1)],
+                c.transform,
+                g,
+            );
+        }
+    }
+
+    // INPUT HANDLING
+    pub fn button_input(&mut self, button: &Button) {
+        match button {
+            Button::Keyboard(key) => self.keyboard_input(*key),
+            Button::Mouse(mouse) => self.mouse_input(*mouse),
+            Button::Controller(_) => {}
+            Button::Hat(_) => {}
+        }
+    }
+
+    /// Drop the dragged piece, releasing on its own square keep it selected
+    /// so the move can be finished by clicking the destination
+    pub fn button_release(&mut self, button: &Button) {
+        if let Button::Keyboard(Key::LShift | Key::RShift) = button {
+            self.shift_held = false;
+        }
+        if *button != Button::Mouse(MouseButton::Left) {
+            return;
+        }
+        let Some((source, _)) = self.dragging.take() else {
+            return;
+        };
+
+        self.mark_dirty();
+        // dropped on the evaluation bar, the piece go back
+        let Some(dest) = self.hovered_square() else {
+            return;
+        };
+        if dest != source {
+            self.exchange_display = None;
+            self.move_selected(source, dest);
+            // the piece was dropped in place, nothing to slide
+            self.animation.slide = None;
+        }
+    }
+
+    fn mouse_input(&mut self, mouse: MouseButton) {
+        if mouse != MouseButton::Left {
+            return;
+        }
+        // the move list can be browsed while reviewing or after the game is over
+        if let Some(index) = self.hovered_move() {
+            self.jump_to_move(index);
+            return;
+        }
+
+        if !self.check_not_reviewing()
+            || !self.check_game_ongoing()
+            || !self.check_no_pending_reply()
+        {
+            self.selecting = None;
+            self.dragging = None;
+            return;
+        }
+
+        self.mark_dirty();
+        let Some(clicking_square) = self.hovered_square() else {
+            return;
+        };
+
+        self.exchange_display = None;
+
+        if let Some((source, dest)) = self.promotion_pending.take() {
+            let side = self.chess_game.side_to_move();
+            match Self::promotion_choice(dest, side, clicking_square) {
+                Some(piece) => self.play_human_move(ChessMove::new(source, dest, Some(piece))),
+                None => println!("Promotion cancelled"),
+            }
+            return;
+        }
+
+        match self.selecting {
+            // no square previously select
+            None => {
+                self.selecting = Some(clicking_square);
+                self.show_exchange_value(clicking_square);
+                if Self::is_draggable(&self.chess_game.current_position(), clicking_square) {
+                    self.dragging = Some((clicking_square, [self.mouse_x, self.mouse_y]));
+                }
+            }
+            // predicate "there exist square for which the user previously select" is true
+            Some(select_square) => self.move_selected(select_square, clicking_square),
+        }
+    }
+
+    /// Whether the piece on `square` can be picked up, only the side to move's pieces can
+    fn is_draggable(board: &Board, square: Square) -> bool {
+        board.color_on(square) == Some(board.side_to_move())
+    }
+
+    /// Move the selected piece to `clicking_square` (by click or drop), deselecting it
+    fn move_selected(&mut self, select_square: Square, clicking_square: Square) {
+        // handle promotion
+        let is_selecting_pawn =
+            || self.chess_game.current_position().piece_on(select_square) == Some(Piece::Pawn);
+        let is_clicking_at_promotable_square = || {
+            let promotable_rank = match self.chess_game.side_to_move() {
+                Color::White => Rank::Eighth,
+                Color::Black => Rank::First,
+            };
+            clicking_square.get_rank() == promotable_rank
+        };
+
+        let is_promotion = is_clicking_at_promotable_square() && is_selecting_pawn();
+        self.selecting = None; // deselect the pieces
+        if is_promotion {
+            // let the user pick the piece if promoting is legal at all
+            let queening = ChessMove::new(select_square, clicking_square, Some(Piece::Queen));
+            if self.chess_game.current_position().legal(queening) {
+                self.promotion_pending = Some((select_square, clicking_square));
+                println!("Promotion: click a piece or press Q/R/B/N");
+            }
+            return;
+        }
+
+        // generate user's move
+        let mov = ChessMove::new(select_square, clicking_square, None);
+        self.play_human_move(mov);
+    }
+
+    /// Play the move entered by the user (if legal) and let the AI respond
+    fn play_human_move(&mut self, mov: ChessMove) {
+        let board = self.chess_game.current_position();
+        if self.stalemate_warning
+            && self.pending_stalemate != Some(mov)
+            && Self::is_winning_stalemate(&board, mov)
+        {
+            println!("Warning: this move stalemates - the game will be a draw");
+            println!("(make the same move again to play it anyway)");
+            self.pending_stalemate = Some(mov);
+            return;
+        }
+        self.pending_stalemate = None;
+
+        // check legality
+        if board.legal(mov) {
+            self.make_move_msg(mov);
+            self.request_ai_reply();
+        }
+    }
+
+    /// Let the AI reply now, or once the move delay passed (see `update`)
+    fn request_ai_reply(&mut self) {
+        if !self.enable_ai {
+            return;
+        }
+
+        if self.ai_move_delay.is_zero() {
+            self.ai_play(false);
+        } else {
+            self.ai_reply_due = Some(Instant::now() + self.ai_move_delay);
+        }
+    }
+
+    /// Whether the AI reply scheduled at `due` should be played at `now`
+    fn is_reply_due(due: Option<Instant>, now: Instant) -> bool {
+        due.is_some_and(|due| now >= due)
+    }
+
+    /// Destination of every legal move of the piece on `source` and whether it is a capture
+    /// (promotions to each piece share a destination so it is listed once)
+    fn legal_destinations(board: &Board, source: Square) -> Vec<(Square, bool)> {
+        let is_pawn = board.piece_on(source) == Some(Piece::Pawn);
+        MoveGen::new_legal(board)
+            .filter(|mov| mov.get_source() == source)
+            .map(|mov| {
+                let dest = mov.get_dest();
+                // a pawn moving diagonally onto an empty square is capturing en passant
+                let en_passant = is_pawn && source.get_file() != dest.get_file();
+                (dest, board.piece_on(dest).is_some() || en_passant)
+            })
+            .unique()
+            .collect()
+    }
+
+    /// Squares of the promotion picker of a pawn promoting on `dest`,
+    /// the queen is on the promotion square then the others toward the center of the board
+    fn promotion_picker(dest: Square, side: Color) -> [(Square, Piece); 4] {
+        let mut square = Some(dest);
+        PROMOTION_PIECES.map(|piece| {
+            let current = square.expect("promotion square is on the last rank");
+            square = current.backward(side);
+            (current, piece)
+        })
+    }
+
+    /// Piece picked by clicking `clicked` while the promotion picker is shown, None to cancel
+    fn promotion_choice(dest: Square, side: Color, clicked: Square) -> Option<Piece> {
+        Self::promotion_picker(dest, side)
+            .iter()
+            .find(|(square, _)| *square == clicked)
+            .map(|(_, piece)| *piece)
+    }
+
+    /// Promotion piece picked with keyboard while the promotion picker is shown
+    fn promotion_key(key: Key) -> Option<Piece> {
+        match key {
+            Key::Q => Some(Piece::Queen),
+            Key::R => Some(Piece::Rook),
+            Key::B => Some(Piece::Bishop),
+            Key::N => Some(Piece::Knight),
+            _ => None,
+        }
+    }
+
+    fn keyboard_input(&mut self, key: Key) {
+        // most keys change something shown, redraw on any of them
+        self.mark_dirty();
+
+        if let Some((source, dest)) = self.promotion_pending {
+            if let Some(piece) = Self::promotion_key(key) {
+                self.promotion_pending = None;
+                self.mark_dirty();
+                self.play_human_move(ChessMove::new(source, dest, Some(piece)));
+                return;
+            }
+        }
+
+        match key {
+            Key::F => println!("{}", self.chess_game.current_position()),
+            Key::E => print!(
+                "{}",
+                format_debug_evaluation(&self.chess_game.current_position())
+            ),
+            Key::P => self.print_pv_trace(),
+            Key::J if self.shift_held => {
+                self.set_analysis_lines(self.analysis_lines % MAX_ANALYSIS_LINES + 1);
+                println!("Analysis show {} move(s)", self.analysis_lines);
+            }
+            Key::J => self.print_analysis(),
+            Key::N if self.shift_held => {
+                self.set_contempt(self.search_options.contempt - CONTEMPT_STEP)
+            }
+            Key::N => self.set_contempt(self.search_options.contempt + CONTEMPT_STEP),
+            Key::Semicolon => self.ai_play(false),
+            Key::Backslash => self.ai_play(true),
+            Key::LShift | Key::RShift => self.shift_held = true,
+            Key::Z if self.review_ply.is_some() => self.undo_to_review(),
+            Key::Z => self.undo(self.undo_plies()),
+            Key::Y => self.redo(self.undo_plies()),
+            Key::Right | Key::Plus | Key::NumPadPlus => {
+                self.set_depth(self.depth.saturating_add(1));
+            }
+            Key::Left | Key::Minus | Key::NumPadMinus => {
+                self.set_depth(self.depth.saturating_sub(1));
+            }
+            Key::D => self.set_difficulty(self.difficulty.next()),
+            Key::C => {
+                self.theme = (self.theme + 1) % THEMES.len();
+                println!("Theme: {}", THEMES[self.theme].name);
+            }
+            Key::X => {
+                self.sound_enabled = !self.sound_enabled;
+                println!("Sound {}", if self.sound_enabled { "on" } else { "off" });
+            }
+            Key::U => self.set_pondering(!self.pondering),
+            Key::H => self.print_pgn(),
+            Key::A => {
+                if self.enable_ai {
+                    println!("Disable AI");
+                    self.enable_ai = false;
+                } else {
+                    println!("Enable AI");
+                    self.enable_ai = true;
+                }
+            }
+            Key::S => {
+                self.display_swap_side = !self.display_swap_side;
+                self.animation.flip = Some(0.0);
+                self.mark_dirty();
+            }
+            Key::G => {
+                self.show_eval_graph = !self.show_eval_graph;
+                self.mark_dirty();
+            }
+            Key::W => {
+                self.show_win_probability = !self.show_win_probability;
+                println!("Show win probability: {}", self.show_win_probability);
+            }
+            Key::L => {
+                self.eval_trace = !self.eval_trace;
+                if self.eval_trace {
+                    println!("{}", EVAL_TRACE_HEADER);
+                } else {
+                    println!("Evaluation trace off");
+                }
+            }
+            Key::T => {
+                self.move_trail_length = self.move_trail_length % MAX_MOVE_TRAIL + 1;
+                println!("Highlight last {} move(s)", self.move_trail_length);
+                self.mark_dirty();
+            }
+            Key::V => self.toggle_review(),
+            Key::Comma => self.review_step(-1),
+            Key::Period => self.review_step(1),
+            Key::Home => self.review_step(isize::MIN),
+            Key::End => self.review_step(isize::MAX),
+            Key::Space => self.toggle_autoplay(),
+            Key::Up => {
+                self.autoplay.change_speed(2.0);
+                println!(
+                    "Autoplay: {} move(s) per second",
+                    self.autoplay.moves_per_second
+                );
+            }
+            Key::Down => {
+                self.autoplay.change_speed(0.5);
+                println!(
+                    "Autoplay: {} move(s) per second",
+                    self.autoplay.moves_per_second
+                );
+            }
+            Key::K => self.toggle_time_mode(),
+            Key::F5 => self.save_game(),
+            Key::F9 => self.load_game(),
+            Key::M => {
+                self.show_legal_moves = !self.show_legal_moves;
+                println!(
+                    "Legal move markers: {}",
+                    if self.show_legal_moves { "on" } else { "off" }
+                );
+                self.mark_dirty();
+            }
+            Key::O => {
+                self.show_book_explorer = !self.show_book_explorer;
+                if self.show_book_explorer {
+                    self.print_book_explorer();
+                }
+                self.mark_dirty();
+            }
+            Key::D1
+            | Key::D2
+            | Key::D3
+            | Key::D4
+            | Key::D5
+            | Key::D6
+            | Key::D7
+            | Key::D8
+            | Key::D9
+                if self.show_book_explorer =>
+            {
+                let index = key as usize - Key::D1 as usize;
+                self.play_book_move(index);
+            }
+            Key::B => self.practice_blunder(),
+            Key::R => self.reset(),
+            Key::I => self.input_fen(),
+            _ => {}
+        }
+    }
+
+    /// Advance the move animation and the review autoplay and play the delayed AI reply,
+    /// call on every update event with `dt` the seconds elapsed since the last one
+    pub fn update(&mut self, dt: f64) {
+        if self.animation.is_running() {
+            self.animation.advance(dt);
+            self.mark_dirty();
+        }
+
+        if let Some(ponder) = &self.ponder {
+            if !ponder.expects(&self.chess_game.current_position()) {
+                println!(
+                    "AI: ponder miss (expected {}), search cancelled",
+                    Self::format_move(&ponder.expected)
+                );
+                self.cancel_ponder();
+            }
+        }
+
+        if Self::is_reply_due(self.ai_reply_due, Instant::now()) {
+            self.ai_play(false);
+        }
+
+        if !self.autoplay.tick(Instant::now()) {
+            return;
+        }
+
+        self.review_step(1);
+        if self.review_ply == Some(Self::game_moves(&self.chess_game).len()) {
+            self.autoplay.pause();
+            println!("Autoplay: end of game");
+        }
+    }
+
+    fn toggle_review(&mut self) {
+        self.autoplay.pause();
+        if self.review_ply.take().is_some() {
+            println!("Review mode off");
+        } else {
+            self.review_ply = Some(Self::game_moves(&self.chess_game).len());
+            println!(
+                "Review mode on (COMMA/PERIOD to step, V to return to the game, \
+                 Z to take back every move after the shown one)"
+            );
+        }
+        self.mark_dirty();
+    }
+
+    /// Move the reviewed position by `delta` move (clamped to the game), enter review mode if needed
+    fn review_step(&mut self, delta: isize) {
+        let move_count = Self::game_moves(&self.chess_game).len();
+        let ply = Self::shown_ply(self.review_ply, move_count);
+        let ply = if delta < 0 {
+            ply.saturating_sub(delta.unsigned_abs())
+        } else {
+            ply.saturating_add(delta as usize).min(move_count)
+        };
+
+        self.review_ply = Some(ply);
+        self.move_list_scroll = None;
+        self.mark_dirty();
+    }
+
+    /// Start autoplay from the reviewed position (from the start if already at the end) or pause it
+    fn toggle_autoplay(&mut self) {
+        if self.autoplay.is_playing() {
+            self.autoplay.pause();
+            println!("Autoplay paused");
+            return;
+        }
+
+        let move_count = Self::game_moves(&self.chess_game).len();
+        if move_count == 0 {
+            println!("Autoplay: no move to replay");
+            return;
+        }
+        if self.review_ply.is_none_or(|ply| ply >= move_count) {
+            self.review_ply = Some(0);
+            self.mark_dirty();
+        }
+
+        println!(
+            "Autoplay: {} move(s) per second",
+            self.autoplay.moves_per_second
+        );
+        self.autoplay.start(Instant::now());
+    }
+
+    fn print_pv_trace(&mut self) {
+        let board = self.chess_game.current_position();
+        let pv = principal_variation(
+            &board,
+            self.depth,
+            &self.search_options,
+            &mut self.rng,
+            &mut self.cache,
+            self.repetition.repeated(),
+        );
+        println!(
+            "Principal variation at depth {} (white perspective)",
+            self.depth
+        );
+        print!("{}", format_pv_trace(&board, &pv));
+    }
+
+    /// Search every move of the shown position and print the best `analysis_lines` of them
+    fn print_analysis(&mut self) {
+        let mut moves = Self::game_moves(&self.chess_game);
+        if let Some(ply) = self.review_ply {
+            moves.truncate(ply);
+        }
+        let board = Self::position_after(&self.base_game.current_position(), &moves);
+        if MoveGen::new_legal(&board).len() == 0 {
+            println!("Analysis: no legal move");
+            return;
+        }
+
+        // repetitions and halfmove clock of the game up to the shown position only
+        let mut repetition = self.repetition.clone();
+        repetition.truncate(moves.len());
+        let options = SearchOptions {
+            halfmove_clock: repetition.halfmove_clock(),
+            ..self.search_options
+        };
+        let ranked = ranked_moves(
+            &board,
+            self.depth,
+            &options,
+            &mut self.rng,
+            &mut self.cache,
+            repetition.repeated(),
+        );
+        println!(
+            "Best {} move(s) at depth {} (white perspective)",
+            self.analysis_lines.min(ranked.len()),
+            self.depth.max(1)
+        );
+        for (i, (san, score)) in Self::analysis_lines(&board, &ranked, self.analysis_lines)
+            .into_iter()
+            .enumerate()
+        {
+            println!("{:>2}. {:<8} {}", i + 1, san, self.format_score(score));
+        }
+    }
+
+    /// SAN and white perspective score of the first `count` moves ranked by `ranked_moves`
+    fn analysis_lines(
+        board: &Board,
+        ranked: &[(ChessMove, i16)],
+        count: usize,
+    ) -> Vec<(String, i16)> {
+        let side = board.side_to_move();
+        ranked
+            .iter()
+            .take(count)
+            .map(|&(mov, score)| (san(board, mov), Self::white_perspective(side, score)))
+            .collect()
+    }
+
+    /// Whether `mov` stalemate the opponent while the mover has overwhelming material
+    fn is_winning_stalemate(board: &Board, mov: ChessMove) -> bool {
+        if !board.legal(mov) || board.make_move_new(mov).status() != BoardStatus::Stalemate {
+            return false;
+        }
+
+        let side = board.side_to_move();
+        let material = |color| {
+            ALL_PIECES[..5]
+                .iter()
+                .map(|&piece| {
+                    let count = (board.pieces(piece) & board.color_combined(color)).popcnt();
+                    PIECE_VALUE[piece.to_index()] * count as i16
+                })
+                .sum::<i16>()
+        };
+        material(side) - material(!side) >= STALEMATE_WARNING_MARGIN
+    }
+
+    /// Guard for input that change the game, print message while reviewing
+    fn check_not_reviewing(&self) -> bool {
+        if self.review_ply.is_some() {
+            println!("Reviewing the game - press V to return to the game");
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Guard for human move, print message while the delayed AI reply is waiting
+    fn check_no_pending_reply(&self) -> bool {
+        if self.ai_reply_due.is_some() {
+            println!("Waiting for the AI reply");
+            false
+        } else {
+            true
+        }
+    }
+
+    pub fn on_mouse_position(&mut self, mouse_pos: [f64; 2]) {
+        self.mouse_x = mouse_pos[0];
+        self.mouse_y = mouse_pos[1];
+        if let Some((_, pos)) = &mut self.dragging {
+            *pos = mouse_pos;
+            self.mark_dirty();
+        }
+    }
+
+    /// Scroll the move list when the cursor is over it, `dy` is positive scrolling up
+    pub fn on_mouse_scroll(&mut self, [_, dy]: [f64; 2]) {
+        let [x0, y0, w, h] = Self::move_list_rect(self.window_size, self.show_eval_graph);
+        if !(x0..x0 + w).contains(&self.mouse_x) || !(y0..y0 + h).contains(&self.mouse_y) {
+            return;
+        }
+        let top = self.move_list_top();
+        let top = if dy > 0.0 {
+            top.saturating_sub(MOVE_LIST_SCROLL_ROWS)
+        } else if dy < 0.0 {
+            top + MOVE_LIST_SCROLL_ROWS
+        } else {
+            return;
+        };
+        // clamped to the list when used
+        self.move_list_scroll = Some(top);
+        self.mark_dirty();
+    }
+
+    pub fn on_resize(&mut self, resize_args: ResizeArgs) {
+        self.window_size = resize_args.window_size;
+        self.mark_dirty();
+    }
+
+    /// Plies undone or redone by one key press
+    fn undo_plies(&self) -> usize {
+        if self.shift_held {
+            SHIFT_UNDO_PLIES
+        } else {
+            1
+        }
+    }
+
+    /// Take back the last `plies` moves (or as many as were played)
+    fn undo(&mut self, plies: usize) {
+        if !self.check_not_reviewing() {
+            return;
+        }
+
+        let move_count = Self::game_moves(&self.chess_game).len();
+        if move_count == 0 {
+            println!("Undo queue is empty");
+            return;
+        }
+        self.undo_to(move_count.saturating_sub(plies));
+    }
+
+    /// Take back every move after the one shown in review mode, then leave review mode
+    fn undo_to_review(&mut self) {
+        let Some(ply) = self.review_ply else {
+            return;
+        };
+        if ply >= Self::game_moves(&self.chess_game).len() {
+            println!("Nothing to take back, the last move is shown");
+            return;
+        }
+        self.autoplay.pause();
+        self.review_ply = None;
+        self.undo_to(ply);
+    }
+
+    /// Keep only the first `move_count` moves, the others can be played again by `redo`
+    fn undo_to(&mut self, move_count: usize) {
+        self.ai_reply_due = None;
+
+        let moves = Self::game_moves(&self.chess_game);
+        let undone =
+            self.takebacks
+                .undo_to(&self.base_game, &moves, &self.eval_history, move_count);
+        let game = match undone {
+            Ok(game) => game,
+            Err(reason) => {
+                println!("Undo rejected: {}", reason);
+                return;
+            }
+        };
+        match self.takebacks.left() {
+            Some(left) => println!(
+                "Undo Success! ({} undo left, {} takeback left)",
+                move_count, left
+            ),
+            None => println!("Undo Success! ({} undo left)", move_count),
+        }
+
+        self.set_game(game);
+        self.selecting = None;
+        self.dragging = None;
+        self.mark_dirty();
+    }
+
+    /// Taken back `moves` in the order they are pushed on the redo stack,
+    /// the first of them is popped first
+    fn redo_order(moves: &[ChessMove]) -> impl Iterator<Item = ChessMove> + '_ {
+        moves.iter().rev().copied()
+    }
+
+    /// Replay `base_game` with only the first `move_count` moves of the current game
+    fn rebuild_game(&mut self, move_count: usize) {
+        let moves = Self::game_moves(&self.chess_game);
+        self.set_game(Self::replayed_game(&self.base_game, &moves[..move_count]));
+    }
+
+    /// Switch to `game`, the current game with only its first moves played
+    fn set_game(&mut self, game: Game) {
+        let move_count = Self::game_moves(&game).len();
+        self.chess_game = game;
+        // the exchange shown was evaluated on the position before
+        self.exchange_display = None;
+        self.eval_history.truncate(move_count);
+        self.repetition.truncate(move_count);
+        self.game_outcome = None;
+        self.move_list_scroll = None;
+    }
+
+    fn replayed_game(base_game: &Game, moves: &[ChessMove]) -> Game {
+        let mut game = base_game.clone();
+        for &mov in moves {
+            game.make_move(mov);
+        }
+        game
+    }
+
+    /// Play again up to `plies` moves taken back by `undo`
+    /// (human or AI move alike, the AI doesn't reply)
+    fn redo(&mut self, plies: usize) {
+        if !self.check_not_reviewing() || !self.check_no_pending_reply() {
+            return;
+        }
+        if self.takebacks.redo_stack.is_empty() {
+            println!("Redo queue is empty");
+            return;
+        }
+
+        for _ in 0..plies {
+            let Some((mov, score)) = self.takebacks.pop_redo() else {
+                break;
+            };
+            // `make_move` clear the stack as for any fresh move, keep the rest of it
+            let rest = std::mem::take(&mut self.takebacks.redo_stack);
+            let rest_scores = std::mem::take(&mut self.takebacks.redo_scores);
+            match self.make_move(mov) {
+                Ok(true) => {
+                    self.takebacks.redo_stack = rest;
+                    self.takebacks.redo_scores = rest_scores;
+                    // the score from before the undo (e.g. searched by the AI) over the static one
+                    if let (Some(score), Some(last_score)) = (score, self.eval_history.last_mut()) {
+                        *last_score = score;
+                    }
+                }
+                Ok(false) | Err(_) => {
+                    println!("Redo failed: {} can't be played", Self::format_move(&mov));
+                    return;
+                }
+            }
+        }
+        println!(
+            "Redo Success! ({} redo left)",
+            self.takebacks.redo_stack.len()
+        );
+        self.mark_dirty();
+    }
+
+    /// Rebuild the game so that only the first `move_count` moves are played
+    fn rewind_to(&mut self, move_count: usize) {
+        self.rebuild_game(move_count);
+        self.takebacks.clear_redo();
+        self.review_ply = None;
+        self.autoplay.pause();
+        self.selecting = None;
+        self.dragging = None;
+        self.mark_dirty();
+    }
+
+    fn practice_blunder(&mut self) {
+        println!("Searching for blunders...");
+        let moves = Self::game_moves(&self.chess_game);
+        let blunders = find_blunders(
+            &self.base_game.current_position(),
+            &moves,
+            self.depth,
+            BLUNDER_THRESHOLD,
+            &mut self.rng,
+            &mut self.cache,
+        );
+
+        for blunder in &blunders {
+            println!(
+                "Blunder at ply {}: {} (best {}) lose {:.2} pawn",
+                blunder.ply + 1,
+                Self::format_move(&blunder.mov),
+                Self::format_move(&blunder.best_mov),
+                blunder.loss as f32 / 100.0
+            );
+        }
+
+        if let Some(worst) = blunders.iter().max_by_key(|blunder| blunder.loss) {
+            let ply = worst.ply;
+            self.rewind_to(ply);
+            self.enable_ai = true;
+            println!(
+                "Rewind to before {}, find a better move for {:?} (AI play the other side)",
+                Self::format_move(&worst.mov),
+                self.chess_game.side_to_move()
+            );
+        } else {
+            println!("No blunder found");
+        }
+    }
+
+    // AI BIND
+    fn ai_play(&mut self, play_2nd_best: bool) {
+        self.ai_reply_due = None;
+        if !self.enable_ai {
+            println!("AI: AI not enable");
+            return;
+        }
+
+        if !self.check_not_reviewing() || !self.check_game_ongoing() {
+            return;
+        }
+
+        if self.depth == 0 {
+            let ai_side = self.chess_game.side_to_move();
+            let board = self.chess_game.current_position();
+            match random_move(&board, &mut self.rng) {
+                Some(mov) => {
+                    println!(
+                        "AI ({:?}): Random move {}",
+                        ai_side,
+                        Self::format_move(&mov)
+                    );
+                    self.make_move_msg(mov);
+                }
+                None => println!("AI: Game Ended"),
+            }
+            return;
+        }
+
+        if let Some(book_move) = self.book_move() {
+            println!(
+                "AI ({:?}): Book move {}",
+                self.chess_game.side_to_move(),
+                book_move
+            );
+            self.make_move_msg(book_move);
+            return;
+        }
+
+        // the difficulty or pressing for a win (see `SearchOptions::with_press`), whichever more
+        let second_best_chance = self
+            .difficulty
+            .second_best_chance()
+            .max(self.search_options.second_best_chance);
+        let play_2nd_best = play_2nd_best || self.rng.gen_bool(second_best_chance);
+
+        let depth = self.search_depth();
+        self.search_options.halfmove_clock = self.repetition.halfmove_clock();
+        let ai_side = self.chess_game.side_to_move();
+        let board = self.chess_game.current_position();
+        let start_time = Instant::now();
+        let pondered = if play_2nd_best {
+            self.cancel_ponder();
+            None
+        } else {
+            self.ponder_result(&board)
+        };
+        let ai_result = match pondered {
+            // the ponder search node count include the human's thinking time, no speed for it
+            Some(best) => (best, None),
+            None => (if play_2nd_best {
+                Self::run_ai_2nd
+            } else {
+                Self::run_ai
+            })(
+                &board,
+                &mut self.rng,
+                depth,
+                &self.search_options,
+                &mut self.cache,
+                self.repetition.repeated(),
+            ),
+        };
+
+        let think_time = start_time.elapsed();
+        self.think_time[ai_side.to_index()] += think_time;
+
+        if let (Some((ai_move, expect_score)), stats) = ai_result {
+            let speed = stats.map_or(String::new(), |stats| {
+                format!(", {}", Self::format_search_speed(stats.nodes, think_time))
+            });
+            println!(
+                "AI ({:?}): Expected Advantage: {} pawn (took {:.2?}{})",
+                ai_side,
+                self.format_score(expect_score),
+                think_time,
+                speed
+            );
+            // the rest of the line is searched again, cheap with the cache just filled
+            let reply_pv = principal_variation(
+                &board.make_move_new(ai_move),
+                self.depth.saturating_sub(1),
+                &self.search_options,
+                &mut self.rng,
+                &mut self.cache,
+                self.repetition.repeated(),
+            );
+            let pv: Vec<_> = iter::once(ai_move)
+                .chain(reply_pv.into_iter().map(|(mov, _)| mov))
+                .collect();
+            println!(
+                "AI ({:?}): Expected line: {}",
+                ai_side,
+                pv.iter().map(Self::format_move).join(" ")
+            );
+            if self.make_move_msg(ai_move) {
+                // searched score is more accurate than static evaluation, prefer it
+                if let Some(last_score) = self.eval_history.last_mut() {
+                    *last_score = Self::white_perspective(ai_side, expect_score);
+                }
+                if self.pondering && self.game_outcome.is_none() {
+                    if let Some(&expected) = pv.get(1) {
+                        self.start_ponder(expected);
+                    }
+                }
+            }
+        } else {
+            println!("AI: Game Ended");
+        }
+    }
+
+    /// Depth of the AI search, in fixed-time mode the search go as deep as the time allow
+    fn search_depth(&self) -> u8 {
+        if self.search_options.time_budget.is_some() {
+            MAX_TIMED_DEPTH
+        } else {
+            self.depth
+        }
+    }
+
+    /// Search the position after `expected` (the human reply the AI predict) on another thread
+    fn start_ponder(&mut self, expected: ChessMove) {
+        self.cancel_ponder();
+        let before = self.chess_game.current_position();
+        let mut repetition = self.repetition.clone();
+        repetition.push(&before, expected);
+        // the time budget start counting once the human actually play `expected`
+        let options = SearchOptions {
+            time_budget: None,
+            halfmove_clock: repetition.halfmove_clock(),
+            ..self.search_options
+        };
+
+        self.ponder = Some(PonderSearch::start(
+            before,
+            expected,
+            self.search_depth(),
+            options,
+            repetition.repeated().clone(),
+        ));
+    }
+
+    fn cancel_ponder(&mut self) {
+        if let Some(ponder) = self.ponder.take() {
+            ponder.stop();
+        }
+    }
+
+    /// Best move of the ponder search if it searched `board`, None (and the ponder cancelled)
+    /// otherwise. In fixed-depth mode the search is waited for, in fixed-time mode it is given
+    /// the time budget counted from when it started.
+    fn ponder_result(&mut self, board: &Board) -> Option<Option<(ChessMove, i16)>> {
+        let ponder = self.ponder.take()?;
+        if ponder.board != *board {
+            ponder.stop();
+            return None;
+        }
+
+        let started = ponder.started;
+        let (best, stats, cache) = match self.search_options.time_budget {
+            Some(budget) => {
+                while !ponder.is_finished() && started.elapsed() < budget {
+                    thread::sleep(PONDER_POLL_INTERVAL);
+                }
+                ponder.stop()
+            }
+            None => ponder.join(),
+        };
+        merge_cache(&mut self.cache, cache);
+        println!(
+            "AI: ponder hit ({} nodes searched, started {:.2?} ago)",
+            stats.nodes,
+            started.elapsed()
+        );
+        Some(best)
+    }
+
+    /// Move from the opening book, None when the AI should search instead
+    fn book_move(&mut self) -> Option<ChessMove> {
+        let book = self.book.as_ref()?;
+        let board = self.chess_game.current_position();
+        let ply = Self::game_moves(&self.chess_game).len();
+
+        match book.probe(&board, ply, &mut self.rng) {
+            BookProbe::Hit(mov) => {
+                self.in_book = true;
+                return Some(mov);
+            }
+            BookProbe::Corrupt(mov) => {
+                println!(
+                    "AI: Warning: book move {} is illegal in this position (corrupt book?), searching instead",
+                    mov
+                );
+            }
+            BookProbe::OutOfBook if self.in_book => {
+                println!("AI: leaving book (position not in book)")
+            }
+            BookProbe::DepthExceeded if self.in_book => {
+                println!("AI: leaving book (book depth reached)")
+            }
+            BookProbe::OutOfBook | BookProbe::DepthExceeded => {}
+        }
+
+        self.in_book = false;
+        None
+    }
+
+    /// Show static exchange evaluation of capturing on `square` (if any capture is possible)
+    fn show_exchange_value(&mut self, square: Square) {
+        let board = self.chess_game.current_position();
+        if let Some(value) = static_exchange_evaluation(&board, square) {
+            println!("Exchange on {}: {:+}", square, value);
+            self.exchange_display = Some((square, value));
+        }
+    }
+
+    /// List book moves of the current position with their frequency (opening explorer)
+    fn print_book_explorer(&self) {
+        let book = match &self.book {
+            Some(book) => book,
+            None => {
+                println!("Explorer: no opening book loaded (use --book <file>)");
+                return;
+            }
+        };
+
+        let book_moves = book.book_moves(&self.chess_game.current_position());
+        if book_moves.is_empty() {
+            println!("Explorer: out of book");
+            return;
+        }
+
+        let total = book_moves.iter().map(|(_, count)| count).sum::<u32>();
+        println!("Explorer:");
+        for (i, (mov, count)) in book_moves.iter().enumerate() {
+            println!(
+                "  {}. {} ({} game(s), {:.0}%)",
+                i + 1,
+                Self::format_move(mov),
+                count,
+                *count as f32 * 100.0 / total as f32
+            );
+        }
+    }
+
+    /// Play the `index`-th move listed by the opening explorer
+    fn play_book_move(&mut self, index: usize) {
+        if !self.check_not_reviewing()
+            || !self.check_game_ongoing()
+            || !self.check_no_pending_reply()
+        {
+            return;
+        }
+
+        let mov = self.book.as_ref().and_then(|book| {
+            book.book_moves(&self.chess_game.current_position())
+                .get(index)
+                .map(|(mov, _)| *mov)
+        });
+
+        match mov {
+            Some(mov) => {
+                self.make_move_msg(mov);
+                self.selecting = None;
+                self.request_ai_reply();
+            }
+            None => println!("Explorer: no book move #{}", index + 1),
+        }
+    }
+
+    // HELPER
+    /// Allow at most `undo_limit` plies to be taken back per game
+    pub fn set_undo_limit(&mut self, undo_limit: usize) {
+        self.takebacks.limit = Some(undo_limit);
+    }
+
+    /// Cap quiescence search at `depth` ply, 0 disable it
+    pub fn set_quiescence_depth(&mut self, depth: u8) {
+        self.search_options.quiescence_depth = depth;
+    }
+
+    /// Maximum centipawn the 2nd best move may lose compared to the best move, negative to disable
+    pub fn set_second_best_margin(&mut self, margin: i16) {
+        self.search_options.second_best_margin = if margin < 0 { None } else { Some(margin) };
+    }
+
+    /// See `SearchOptions::with_press`
+    pub fn set_press(&mut self, press: f64) {
+        self.search_options = self.search_options.with_press(press);
+    }
+
+    /// See `SearchOptions::contempt`, clamped to `MAX_CONTEMPT` either way
+    pub fn set_contempt(&mut self, contempt: i16) {
+        self.search_options.contempt = contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+        println!(
+            "AI contempt: {:+} centipawn (positive avoid draws)",
+            self.search_options.contempt
+        );
+    }
+
+    pub fn set_node_limit(&mut self, node_limit: u64) {
+        self.search_options.node_limit = Some(node_limit);
+    }
+
+    fn toggle_time_mode(&mut self) {
+        if self.search_options.time_budget.take().is_some() {
+            println!("AI: fixed-depth search (depth {})", self.depth);
+        } else {
+            self.search_options.time_budget = Some(self.time_budget);
+            println!("AI: fixed-time search ({:.2?} per move)", self.time_budget);
+        }
+    }
+
+    /// Search for `budget` per move instead of to a fixed depth
+    pub fn set_time_budget(&mut self, budget: Duration) {
+        self.time_budget = budget;
+        self.search_options.time_budget = Some(budget);
+    }
+
+    /// Set the AI depth, evaluation noise and 2nd best move chance of a preset
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.search_options.eval_noise = difficulty.eval_noise();
+        println!("AI: Set Difficulty={:?}", difficulty);
+        self.set_depth(difficulty.depth());
+    }
+
+    /// Set AI search depth, depth 0 play random legal move
+    pub fn set_depth(&mut self, depth: u8) {
+        self.depth = depth;
+        if depth == 0 {
+            println!("AI: Set Depth=0 (random move)");
+        } else {
+            println!("AI: Set Depth={}", self.depth);
+        }
+    }
+
+    /// Pre-populate the transposition table with analysed positions (EPD `ce` score and `acd` depth),
+    /// return number of position inserted
+    pub fn seed_cache(&mut self, records: &[EpdRecord]) -> usize {
+        let mut count = 0;
+        for record in records {
+            match (record.number("ce"), record.number("acd")) {
+                (Some(score), Some(depth)) => {
+                    let entry = TranspositionItem::exact(score, depth);
+                    self.cache.put(BoardHash::new(&record.board), entry);
+                    count += 1;
                 }
+                _ => println!("EPD: {} has no ce/acd, skipped", record.board),
             }
-            Key::S => {
-                self.display_swap_side = !self.display_swap_side;
-                self.mark_dirty();
-            }
-            Key::R => self.reset(),
-            Key::I => self.input_fen(),
-            _ => {}
         }
+        count
     }
 
-    pub fn on_mouse_position(&mut self, mouse_pos: [f64; 2]) {
-        self.mouse_x = mouse_pos[0];
-        self.mouse_y = mouse_pos[1];
+    pub fn set_pondering(&mut self, enable: bool) {
+        self.pondering = enable;
+        if !enable {
+            self.cancel_ponder();
+        }
+        println!("Pondering {}", if enable { "on" } else { "off" });
     }
 
-    pub fn on_resize(&mut self, resize_args: ResizeArgs) {
-        let [w, h] = resize_args.window_size;
-        self.draw_size = [w as _, h as _];
+    pub fn set_stalemate_warning(&mut self, enable: bool) {
+        self.stalemate_warning = enable;
     }
 
-    fn undo(&mut self) {
-        if let Some((last_act, prev_acts)) = self.chess_game.actions().split_last() {
-            println!("Undo Success! ({} undo left)", prev_acts.len());
+    pub fn set_autoplay_speed(&mut self, moves_per_second: f64) {
+        self.autoplay =
+            Autoplay::new(moves_per_second.clamp(MIN_AUTOPLAY_SPEED, MAX_AUTOPLAY_SPEED));
+    }
 
-            let mut game = self.base_game.clone();
+    pub fn set_ai_move_delay(&mut self, delay: Duration) {
+        self.ai_move_delay = delay;
+    }
 
-            prev_acts
-                .iter()
-                .filter_map(|act| {
-                    if let Action::MakeMove(mov) = act {
-                        Some(*mov)
-                    } else {
-                        None
-                    }
-                })
-                .for_each(|mov| {
-                    game.make_move(mov);
-                });
+    pub fn set_save_path(&mut self, path: &str) {
+        self.save_path = path.to_string();
+    }
+
+    pub fn set_pgn_database(&mut self, path: &str) {
+        self.pgn_database = Some(path.to_string());
+    }
+
+    pub fn set_max_game_length(&mut self, max_length: usize) {
+        self.max_game_length = max_length;
+    }
+
+    /// Font used to label the board coordinates
+    pub fn set_glyphs(&mut self, glyphs: Glyphs) {
+        self.glyphs = Some(glyphs);
+        self.mark_dirty();
+    }
+
+    /// Glyph cache of the coordinate labels, its encoder must be flushed after drawing
+    pub fn glyphs_mut(&mut self) -> Option<&mut Glyphs> {
+        self.glyphs.as_mut()
+    }
 
-            self.chess_game = game;
+    pub fn set_book(&mut self, book: OpeningBook) {
+        if book.is_empty() {
+            println!("Warning: opening book is empty");
         } else {
-            println!("Undo queue is empty");
+            println!("Opening book loaded ({} positions)", book.len());
         }
+        self.book = Some(book);
+        self.in_book = true;
     }
 
-    // AI BIND
-    fn ai_play(&mut self, play_2nd_best: bool) {
-        if !self.enable_ai {
-            println!("AI: AI not enable");
+    pub fn set_analysis_lines(&mut self, lines: usize) {
+        self.analysis_lines = lines.clamp(1, MAX_ANALYSIS_LINES);
+    }
+
+    pub fn set_win_probability_scale(&mut self, scale: f64) {
+        self.win_probability_scale = scale;
+    }
+
+    /// Format centipawn score as pawn, e.g. "+0.80" or "+0.80 (61%)" when win probability is shown
+    fn format_score(&self, score: i16) -> String {
+        let pawn = format!("{:+.2}", score as f32 / 100.0);
+        if self.show_win_probability {
+            let win_probability = win_probability(score, self.win_probability_scale);
+            format!("{} ({:.0}%)", pawn, win_probability * 100.0)
+        } else {
+            pawn
+        }
+    }
+
+    fn make_move(&mut self, mov: ChessMove) -> Result<bool, String> {
+        let board = self.chess_game.current_position();
+        let move_result = Self::try_make_move(&mut self.chess_game, &self.repetition, mov)?;
+        if move_result {
+            self.mark_dirty();
+            self.takebacks.clear_redo();
+            self.move_list_scroll = None;
+            self.repetition.push(&board, mov);
+            let score = evaluation_pieces_worth_plus(&self.chess_game.current_position());
+            self.eval_history.push(score);
+
+            if self.eval_trace {
+                let before = evaluation_pieces_worth_plus(&board);
+                let ply = self.repetition.len();
+                println!("{}", Self::eval_trace_line(ply, &board, mov, before, score));
+            }
+
+            if Self::draw_by_max_length(
+                &mut self.chess_game,
+                self.repetition.len(),
+                self.max_game_length,
+            ) {
+                println!(
+                    "Maximum game length ({} ply) reached, game is drawn",
+                    self.max_game_length
+                );
+            }
+
+            self.check_and_report_result();
+            if self.show_book_explorer {
+                self.print_book_explorer();
+            }
+        }
+        Ok(move_result)
+    }
+
+    /// CSV row of the evaluation trace (see `EVAL_TRACE_HEADER`), evaluation are from white perspective
+    fn eval_trace_line(
+        ply: usize,
+        board: &Board,
+        mov: ChessMove,
+        before: i16,
+        after: i16,
+    ) -> String {
+        format!(
+            "{},{:?},{},{},{},{:+}",
+            ply,
+            board.side_to_move(),
+            Self::format_move(&mov),
+            before,
+            after,
+            after - before
+        )
+    }
+
+    /// Single place deciding whether the game is over, report the result the first time
+    /// it is detected and lock move input until reset
+    fn check_and_report_result(&mut self) {
+        if self.game_outcome.is_some() {
             return;
         }
 
-        let ai_result = (if play_2nd_best {
-            Self::run_ai_2nd
-        } else {
-            Self::run_ai
-        })(
-            &self.chess_game.current_position(),
-            &mut self.rng,
-            self.depth,
-            &mut self.cache,
-            &Self::get_potential_repetition(&self.chess_game, &self.base_game)
+        self.game_outcome = game_outcome(&self.chess_game, &self.repetition);
+        if let Some(outcome) = self.game_outcome {
+            println!("Game Over: {}", outcome);
+            self.print_think_time();
+            self.save_to_pgn_database();
+            self.mark_dirty();
+        }
+    }
+
+    fn save_to_pgn_database(&self) {
+        let Some(path) = &self.pgn_database else {
+            return;
+        };
+
+        match append_pgn(path, &self.export_pgn()) {
+            Ok(()) => println!("Game saved to {}", path),
+            Err(e) => println!("Error: can't save game to {}: {}", path, e),
+        }
+    }
+
+    fn print_think_time(&self) {
+        let [white, black] = self.think_time;
+        println!(
+            "AI thinking time - White: {:.2?}, Black: {:.2?}",
+            white, black
         );
+    }
 
-        if let Some((ai_move, expect_score)) = ai_result {
-            println!(
-                "AI ({:?}): Expected Advantage: {:.2} pawn",
-                self.chess_game.current_position().side_to_move(),
-                expect_score as f32 / 100.0
-            );
-            self.make_move_msg(ai_move);
-        } else {
-            println!("AI: Game Ended");
+    /// Convert search score of `side` (side to move when searched) to white perspective
+    fn white_perspective(side: Color, score: i16) -> i16 {
+        match side {
+            Color::White => score,
+            Color::Black => -score,
         }
     }
 
-    // HELPER
-    fn make_move(&mut self, mov: ChessMove) -> Result<bool, String> {
-        match self.chess_game.current_position().status() {
-            BoardStatus::Ongoing => {
-                let move_result = self.chess_game.make_move(mov);
-                Ok(move_result)
+    /// Declare `game` drawn (as agreed draw) once `ply` reach `max_length`
+    fn draw_by_max_length(game: &mut Game, ply: usize, max_length: usize) -> bool {
+        if ply < max_length || game.result().is_some() {
+            return false;
+        }
+
+        let side = game.side_to_move();
+        game.offer_draw(side) && game.accept_draw()
+    }
+
+    /// Apply `mov` to `game` (its moves recorded in `repetition`) unless the game is already over
+    fn try_make_move(
+        game: &mut Game,
+        repetition: &RepetitionTracker,
+        mov: ChessMove,
+    ) -> Result<bool, String> {
+        match game_outcome(game, repetition) {
+            Some(outcome) => Err(outcome.to_string()),
+            None => Ok(game.make_move(mov)),
+        }
+    }
+
+    /// Guard for every move input (human and AI), print message if the game is already over
+    fn check_game_ongoing(&self) -> bool {
+        match self.game_outcome {
+            Some(outcome) => {
+                println!(
+                    "Game is over ({}) - press R to reset and play again",
+                    outcome
+                );
+                false
             }
-            BoardStatus::Stalemate => Err("Stalemated".to_string()),
-            BoardStatus::Checkmate => Err(format!(
-                "{:?} Checkmated",
-                self.chess_game.current_position().side_to_move()
-            )),
+            None => true,
         }
     }
 
     fn make_move_msg(&mut self, mov: ChessMove) -> bool {
+        let board = self.chess_game.current_position();
         match self.make_move(mov) {
             Err(msg) => {
                 println!("Error: {}", msg);
                 false
             }
-            Ok(val) => val,
+            Ok(val) => {
+                if val {
+                    self.play_sound(MoveSound::of(&board, mov));
+                    self.animation.slide = Some((mov.get_source(), mov.get_dest(), 0.0));
+                }
+                val
+            }
+        }
+    }
+
+    fn play_sound(&self, sound: MoveSound) {
+        if !self.sound_enabled {
+            return;
+        }
+        let player = self.sound_player.get_or_init(|| match SoundPlayer::open() {
+            Ok(player) => Some(player),
+            Err(e) => {
+                println!("Sound unavailable: {}", e);
+                None
+            }
+        });
+        if let Some(Err(e)) = player.as_ref().map(|player| player.play(sound)) {
+            println!("Failed to play sound: {}", e);
         }
     }
 
@@ -483,40 +2857,62 @@ impl ChessGraphic {
         board: &Board,
         rng: &mut impl Rng,
         depth: u8,
+        options: &SearchOptions,
         cache: &mut LruCache<BoardHash, TranspositionItem, K>,
-        repetition: &HashSet<BoardHash>
-    ) -> Option<(ChessMove, i16)> {
-        negamax_prelude(board, depth, rng, cache, repetition)
+        repetition: &HashSet<BoardHash>,
+    ) -> (Option<(ChessMove, i16)>, Option<SearchStats>) {
+        let (best, stats) = search_root(board, depth, options, rng, cache, repetition);
+        (best, Some(stats))
     }
 
     fn run_ai_2nd<K: BuildHasher>(
         board: &Board,
         rng: &mut impl Rng,
         depth: u8,
+        options: &SearchOptions, // ranking search every move fully, node limit doesn't apply
         cache: &mut LruCache<BoardHash, TranspositionItem, K>,
-        repetition: &HashSet<BoardHash>
-    ) -> Option<(ChessMove, i16)> {
-        negamax_prelude_2nd(board, depth, rng, cache, repetition)[1]
+        repetition: &HashSet<BoardHash>,
+    ) -> (Option<(ChessMove, i16)>, Option<SearchStats>) {
+        let ranked = negamax_prelude_2nd(board, depth, options, rng, cache, repetition);
+        let (chosen, fallback) = second_best_within(ranked, options.second_best_margin);
+        if fallback {
+            println!(
+                "AI: 2nd best move is more than {} centipawn worse than the best, playing the best move",
+                options.second_best_margin.unwrap_or_default()
+            );
+        }
+        // the ranking search doesn't count its nodes
+        (chosen, None)
     }
 
-    fn get_potential_repetition(game: &Game, base_game: &Game) -> HashSet<BoardHash> {
-        let mut occured = HashSet::with_capacity(game.actions().len());
-        let mut repeated = HashSet::new();
+    /// "N nodes, M nodes/s" of a search that took `elapsed`
+    fn format_search_speed(nodes: u64, elapsed: Duration) -> String {
+        let seconds = elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            let nps = (nodes as f64 / seconds).round() as u64;
+            format!("{} nodes, {} nodes/s", nodes, nps)
+        } else {
+            format!("{} nodes", nodes)
+        }
+    }
 
-        let mut board = base_game.current_position();
+    fn position_after(base: &Board, moves: &[ChessMove]) -> Board {
+        moves
+            .iter()
+            .fold(*base, |board, &mov| board.make_move_new(mov))
+    }
 
-        game.actions().iter()
-            .filter_map(|act| if let Action::MakeMove(mov) = act { Some(*mov) } else { None})
-            .enumerate()
-            .for_each(|(i, mov)| {
-                let hash = BoardHash::new(&board);
-                if !occured.insert(hash) {
-                    repeated.insert(hash);
+    fn game_moves(game: &Game) -> Vec<ChessMove> {
+        game.actions()
+            .iter()
+            .filter_map(|act| {
+                if let Action::MakeMove(mov) = act {
+                    Some(*mov)
+                } else {
+                    None
                 }
-
-                board = board.make_move_new(mov);
-            });
-        repeated
+            })
+            .collect()
     }
 
     fn mark_dirty(&mut self) {
@@ -542,8 +2938,8 @@ impl ChessGraphic {
             }
         };
 
-        let w = f64::from(viewport.window_size[0]);
-        let h = f64::from(viewport.window_size[1]);
+        let w = viewport.window_size[0];
+        let h = viewport.window_size[1];
 
         let x0 = (w * f) / NUM_FILE as f64;
         let y0 = h - ((h * r) / NUM_RANK as f64);
@@ -553,11 +2949,37 @@ impl ChessGraphic {
         rectangle::rectangle_by_corners(x0, y0, x1, y1)
     }
 
-    fn pos_to_square(draw_size: [u32; 2], x: f64, y: f64, swap: bool) -> Square {
-        let [w, h] = draw_size;
+    /// Center of a piece `t` (0 to 1) along its slide between two square rectangles, eased in and out
+    fn slide_position(source: [f64; 4], dest: [f64; 4], t: f64) -> [f64; 2] {
+        let t = t.clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        let center = |[x, y, w, h]: [f64; 4]| [x + w / 2.0, y + h / 2.0];
+        let ([x0, y0], [x1, y1]) = (center(source), center(dest));
+        [x0 + (x1 - x0) * t, y0 + (y1 - y0) * t]
+    }
+
+    /// Orientation to draw and vertical scale of the board `progress` (0 to 1) into flipping to `swap`,
+    /// the old orientation shrink to a line then the new one grow back
+    fn flip_view(swap: bool, progress: Option<f64>) -> (bool, f64) {
+        match progress {
+            None => (swap, 1.0),
+            Some(t) => {
+                let scale = (std::f64::consts::PI * t.clamp(0.0, 1.0)).cos();
+                if scale > 0.0 {
+                    (!swap, scale)
+                } else {
+                    (swap, -scale)
+                }
+            }
+        }
+    }
+
+    /// Square at `[x, y]` relative to the corner of a board of `board_size`
+    fn pos_to_square(board_size: [f64; 2], x: f64, y: f64, swap: bool) -> Square {
+        let [w, h] = board_size;
 
-        let rel_x = x / f64::from(w);
-        let rel_y = y / f64::from(h);
+        let rel_x = x / w;
+        let rel_y = y / h;
 
         let file = File::from_index({
             let file_pos = NUM_FILE as f64 * rel_x;
@@ -589,3 +3011,631 @@ impl ChessGraphic {
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Animation, Autoplay, ChessGraphic, Difficulty, PonderSearch, Takebacks};
+    use crate::chess_minmax::main_evalation::evaluation_pieces_worth_plus;
+    use crate::chess_minmax::{test_rng, RepetitionTracker, SearchOptions, MAX_TIMED_DEPTH};
+    use crate::chess_outcome::game_outcome;
+    use chess::{Board, BoardStatus, ChessMove, Color, Game, Piece, Square};
+    use lru::LruCache;
+    use piston_window::{Key, Viewport};
+    use std::collections::HashSet;
+    use std::str::FromStr;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_no_move_after_checkmate() {
+        // fool's mate
+        let mut game = Game::new();
+        let mut repetition = RepetitionTracker::default();
+        for mov in ["f2f3", "e7e5", "g2g4", "d8h4"].iter() {
+            let mov = ChessMove::from_str(mov).unwrap();
+            let board = game.current_position();
+            assert_eq!(
+                ChessGraphic::try_make_move(&mut game, &repetition, mov),
+                Ok(true)
+            );
+            repetition.push(&board, mov);
+        }
+
+        let actions_count = game.actions().len();
+        let mov = ChessMove::new(Square::E2, Square::E4, None);
+
+        assert!(game_outcome(&game, &repetition).is_some());
+        assert!(ChessGraphic::try_make_move(&mut game, &repetition, mov).is_err());
+        assert_eq!(game.actions().len(), actions_count);
+    }
+
+    #[test]
+    fn test_no_move_after_stalemate() {
+        let mut game = Game::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let repetition = RepetitionTracker::default();
+        let mov = ChessMove::new(Square::H8, Square::G8, None);
+
+        assert!(game_outcome(&game, &repetition).is_some());
+        assert!(ChessGraphic::try_make_move(&mut game, &repetition, mov).is_err());
+        assert!(game.actions().is_empty());
+    }
+
+    #[test]
+    fn test_move_while_ongoing() {
+        let mut game = Game::new();
+        let repetition = RepetitionTracker::default();
+        let mov = ChessMove::new(Square::E2, Square::E4, None);
+
+        assert!(game_outcome(&game, &repetition).is_none());
+        assert_eq!(
+            ChessGraphic::try_make_move(&mut game, &repetition, mov),
+            Ok(true)
+        );
+        assert_eq!(game.actions().len(), 1);
+    }
+
+    #[test]
+    fn test_draw_by_max_length() {
+        let mut game = Game::new();
+        assert!(!ChessGraphic::draw_by_max_length(&mut game, 9, 10));
+        assert!(game.result().is_none());
+
+        assert!(ChessGraphic::draw_by_max_length(&mut game, 10, 10));
+        let repetition = RepetitionTracker::default();
+        assert!(game_outcome(&game, &repetition).is_some());
+
+        let mov = ChessMove::new(Square::E2, Square::E4, None);
+        assert!(ChessGraphic::try_make_move(&mut game, &repetition, mov).is_err());
+    }
+
+    #[test]
+    fn test_move_list_ignore_draw_actions() {
+        let mut game = Game::new();
+        for mov in ["e2e4", "e7e5"].iter() {
+            game.make_move(ChessMove::from_str(mov).unwrap());
+        }
+        assert!(ChessGraphic::draw_by_max_length(&mut game, 2, 2));
+
+        // the draw offer and its acceptance are actions but not moves
+        let move_count = ChessGraphic::game_moves(&game).len();
+        assert_eq!((game.actions().len(), move_count), (4, 2));
+        assert_eq!(
+            ChessGraphic::move_list_index(Color::White, 1, 0, move_count),
+            None
+        );
+        assert_eq!(ChessGraphic::shown_ply(None, move_count), 2);
+        assert_eq!(ChessGraphic::shown_ply(Some(1), move_count), 1);
+        assert_eq!(ChessGraphic::shown_ply(Some(4), move_count), 2);
+    }
+
+    #[test]
+    fn test_score_sign_consistency() {
+        // (fen, side with clear advantage)
+        let positions = [
+            ("k7/8/8/8/8/8/8/KQ6 w - - 0 1", Color::White),
+            ("k7/8/8/8/8/8/8/KQ6 b - - 0 1", Color::White),
+            ("k6r/8/8/8/8/8/8/K7 w - - 0 1", Color::Black),
+            ("k6r/8/8/8/8/8/8/K7 b - - 0 1", Color::Black),
+        ];
+        let rng = &mut test_rng();
+
+        for (fen, winner) in positions.iter() {
+            let board = Board::from_str(fen).unwrap();
+            let side = board.side_to_move();
+            let mut cache = LruCache::new(1024);
+
+            let options = SearchOptions::default();
+            let (_, score) =
+                ChessGraphic::run_ai(&board, rng, 3, &options, &mut cache, &HashSet::new())
+                    .0
+                    .unwrap();
+            // search score is from the side to move perspective
+            assert_eq!(score > 0, side == *winner, "{}", fen);
+
+            // display (and eval graph) is from white perspective, same as static evaluation
+            let white_score = ChessGraphic::white_perspective(side, score);
+            assert_eq!(white_score > 0, *winner == Color::White, "{}", fen);
+            assert_eq!(
+                evaluation_pieces_worth_plus(&board) > 0,
+                *winner == Color::White,
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_autoplay_tick() {
+        let start = Instant::now();
+        let mut autoplay = Autoplay::new(2.0);
+
+        // paused autoplay never step
+        assert!(!autoplay.tick(start + Duration::from_secs(10)));
+
+        autoplay.start(start);
+        assert!(!autoplay.tick(start + Duration::from_millis(400)));
+        assert!(autoplay.tick(start + Duration::from_millis(500)));
+        // timer restart after each step
+        assert!(!autoplay.tick(start + Duration::from_millis(900)));
+        assert!(autoplay.tick(start + Duration::from_millis(1000)));
+
+        autoplay.change_speed(1000.0);
+        assert_eq!(autoplay.moves_per_second, 16.0);
+
+        autoplay.pause();
+        assert!(!autoplay.is_playing());
+    }
+
+    #[test]
+    fn test_position_after() {
+        let mut game = Game::new();
+        let moves = ["e2e4", "e7e5", "g1f3"]
+            .iter()
+            .map(|mov| ChessMove::from_str(mov).unwrap())
+            .collect::<Vec<_>>();
+        for &mov in &moves {
+            game.make_move(mov);
+        }
+
+        let base = Board::default();
+        assert_eq!(ChessGraphic::position_after(&base, &[]), base);
+        assert_eq!(
+            ChessGraphic::position_after(&base, &moves),
+            game.current_position()
+        );
+    }
+
+    #[test]
+    fn test_winning_stalemate_warning() {
+        let board = Board::from_str("7k/8/5K2/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        let stalemate = ChessMove::new(Square::G1, Square::G6, None);
+        let checkmate = ChessMove::new(Square::G1, Square::G7, None);
+        assert!(ChessGraphic::is_winning_stalemate(&board, stalemate));
+        assert!(!ChessGraphic::is_winning_stalemate(&board, checkmate));
+
+        // stalemate with no material advantage isn't throwing anything away
+        let board = Board::from_str("7k/5K2/8/6P1/8/8/8/8 w - - 0 1").unwrap();
+        let stalemate = ChessMove::new(Square::G5, Square::G6, None);
+        assert_eq!(
+            board.make_move_new(stalemate).status(),
+            BoardStatus::Stalemate
+        );
+        assert!(!ChessGraphic::is_winning_stalemate(&board, stalemate));
+    }
+
+    #[test]
+    fn test_eval_trace_line() {
+        let board = Board::default();
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let line = ChessGraphic::eval_trace_line(1, &board, e4, 0, 40);
+
+        assert_eq!(line, "1,White,e2e4,0,40,+40");
+        assert_eq!(
+            line.split(',').count(),
+            super::EVAL_TRACE_HEADER.split(',').count()
+        );
+    }
+
+    #[test]
+    fn test_promotion_picker_placement() {
+        let viewport = Viewport {
+            rect: [0, 0, 800, 800],
+            draw_size: [800, 800],
+            window_size: [800.0, 800.0],
+        };
+        let picker_rects = |dest, side, swap| {
+            ChessGraphic::promotion_picker(dest, side)
+                .iter()
+                .map(|(square, _)| ChessGraphic::square_to_rect(square, &viewport, swap))
+                .collect::<Vec<_>>()
+        };
+
+        // white promoting on e8: queen at the top, picker extends downward
+        let rects = picker_rects(Square::E8, Color::White, false);
+        assert_eq!(rects[0], [400.0, 0.0, 100.0, 100.0]);
+        assert_eq!(rects[3], [400.0, 300.0, 100.0, 100.0]);
+
+        // flipped board: e8 is at the bottom, picker extends upward
+        let rects = picker_rects(Square::E8, Color::White, true);
+        assert_eq!(rects[0], [300.0, 700.0, 100.0, 100.0]);
+        assert_eq!(rects[3], [300.0, 400.0, 100.0, 100.0]);
+
+        // black promoting on d1 is the mirror case
+        let rects = picker_rects(Square::D1, Color::Black, false);
+        assert_eq!(rects[0], [300.0, 700.0, 100.0, 100.0]);
+        let rects = picker_rects(Square::D1, Color::Black, true);
+        assert_eq!(rects[0], [400.0, 0.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn test_promotion_picker_click() {
+        let choice = |x, y, swap| {
+            let clicked = ChessGraphic::pos_to_square([800.0, 800.0], x, y, swap);
+            ChessGraphic::promotion_choice(Square::E8, Color::White, clicked)
+        };
+
+        assert_eq!(choice(450.0, 50.0, false), Some(Piece::Queen));
+        assert_eq!(choice(450.0, 150.0, false), Some(Piece::Rook));
+        assert_eq!(choice(450.0, 350.0, false), Some(Piece::Knight));
+        assert_eq!(choice(450.0, 450.0, false), None);
+        assert_eq!(choice(550.0, 50.0, false), None);
+
+        assert_eq!(choice(350.0, 750.0, true), Some(Piece::Queen));
+        assert_eq!(choice(350.0, 650.0, true), Some(Piece::Rook));
+        assert_eq!(choice(350.0, 450.0, true), Some(Piece::Knight));
+        // same screen position as the unflipped queen is now a different square
+        assert_eq!(choice(450.0, 50.0, true), None);
+    }
+
+    #[test]
+    fn test_ai_reply_due() {
+        let moved = Instant::now();
+        let due = Some(moved + Duration::from_millis(300));
+
+        assert!(!ChessGraphic::is_reply_due(
+            None,
+            moved + Duration::from_secs(10)
+        ));
+        assert!(!ChessGraphic::is_reply_due(due, moved));
+        assert!(!ChessGraphic::is_reply_due(
+            due,
+            moved + Duration::from_millis(299)
+        ));
+        assert!(ChessGraphic::is_reply_due(
+            due,
+            moved + Duration::from_millis(300)
+        ));
+    }
+
+    #[test]
+    fn test_legal_destinations() {
+        // white pawn e5 can take d5 en passant, push to e6 or take the knight on f6
+        let board = Board::from_str("4k3/8/5n2/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+
+        let mut destinations = ChessGraphic::legal_destinations(&board, Square::E5);
+        destinations.sort();
+        assert_eq!(
+            destinations,
+            vec![(Square::D6, true), (Square::E6, false), (Square::F6, true)]
+        );
+
+        // four promotion share one destination
+        let board = Board::from_str("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            ChessGraphic::legal_destinations(&board, Square::B7),
+            vec![(Square::B8, false)]
+        );
+    }
+
+    #[test]
+    fn test_promotion_key() {
+        assert_eq!(ChessGraphic::promotion_key(Key::N), Some(Piece::Knight));
+        assert_eq!(ChessGraphic::promotion_key(Key::Q), Some(Piece::Queen));
+        assert_eq!(ChessGraphic::promotion_key(Key::K), None);
+    }
+
+    #[test]
+    fn test_coordinate_labels() {
+        let viewport = Viewport {
+            rect: [0, 0, 800, 800],
+            draw_size: [800, 800],
+            window_size: [800.0, 800.0],
+        };
+        let label_at = |swap, text| {
+            let labels = ChessGraphic::coordinate_labels(&viewport, swap);
+            let (_, [x, y]) = labels.into_iter().find(|&(t, _)| t == text).unwrap();
+            // square (column, row) from the top left of the window
+            ((x / 100.0) as u32, (y / 100.0) as u32)
+        };
+
+        assert_eq!(ChessGraphic::coordinate_labels(&viewport, false).len(), 16);
+        assert_eq!(label_at(false, 'a'), (0, 7));
+        assert_eq!(label_at(false, 'h'), (7, 7));
+        assert_eq!(label_at(false, '1'), (0, 7));
+        assert_eq!(label_at(false, '8'), (0, 0));
+
+        // mirrored when black is at the bottom
+        assert_eq!(label_at(true, 'a'), (7, 7));
+        assert_eq!(label_at(true, 'h'), (0, 7));
+        assert_eq!(label_at(true, '1'), (0, 0));
+        assert_eq!(label_at(true, '8'), (0, 7));
+    }
+
+    #[test]
+    fn test_difficulty_presets() {
+        let presets = [
+            Difficulty::Beginner,
+            Difficulty::Intermediate,
+            Difficulty::Advanced,
+        ];
+        for pair in presets.windows(2) {
+            let (weaker, stronger) = (pair[0], pair[1]);
+            assert_eq!(weaker.next(), stronger);
+            assert!(weaker.depth() < stronger.depth());
+            assert!(weaker.eval_noise() > stronger.eval_noise());
+            assert!(weaker.second_best_chance() > stronger.second_best_chance());
+        }
+        assert_eq!(Difficulty::Advanced.next(), Difficulty::Beginner);
+
+        // the strongest preset is the unweakened AI
+        assert_eq!(Difficulty::Advanced.eval_noise(), 0);
+        assert_eq!(Difficulty::Advanced.second_best_chance(), 0.0);
+    }
+
+    #[test]
+    fn test_is_draggable() {
+        let board = Board::default();
+        assert!(ChessGraphic::is_draggable(&board, Square::E2));
+        assert!(!ChessGraphic::is_draggable(&board, Square::E7));
+        assert!(!ChessGraphic::is_draggable(&board, Square::E4));
+
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let board = board.make_move_new(e4);
+        assert!(ChessGraphic::is_draggable(&board, Square::E7));
+        assert!(!ChessGraphic::is_draggable(&board, Square::E4));
+    }
+
+    #[test]
+    fn test_format_search_speed() {
+        assert_eq!(
+            ChessGraphic::format_search_speed(150_000, Duration::from_millis(500)),
+            "150000 nodes, 300000 nodes/s"
+        );
+        assert_eq!(
+            ChessGraphic::format_search_speed(0, Duration::ZERO),
+            "0 nodes"
+        );
+    }
+
+    #[test]
+    fn test_animation_advance() {
+        let mut animation = Animation {
+            slide: Some((Square::E2, Square::E4, 0.0)),
+            flip: Some(0.0),
+        };
+        animation.advance(0.1);
+        assert_eq!(animation.slide, Some((Square::E2, Square::E4, 0.5)));
+        assert!(animation.is_running());
+
+        // the slide end before the flip
+        animation.advance(0.1);
+        assert_eq!(animation.slide, None);
+        assert!(animation.flip.is_some());
+        animation.advance(0.1);
+        assert!(!animation.is_running());
+    }
+
+    #[test]
+    fn test_slide_and_flip_view() {
+        let (source, dest) = ([0.0, 0.0, 10.0, 10.0], [100.0, 50.0, 10.0, 10.0]);
+        assert_eq!(ChessGraphic::slide_position(source, dest, 0.0), [5.0, 5.0]);
+        assert_eq!(
+            ChessGraphic::slide_position(source, dest, 0.5),
+            [55.0, 30.0]
+        );
+        assert_eq!(
+            ChessGraphic::slide_position(source, dest, 1.0),
+            [105.0, 55.0]
+        );
+
+        assert_eq!(ChessGraphic::flip_view(true, None), (true, 1.0));
+        // old orientation first, new one once past the middle
+        let (swap, scale) = ChessGraphic::flip_view(true, Some(0.25));
+        assert!(!swap && scale > 0.0 && scale < 1.0);
+        let (swap, scale) = ChessGraphic::flip_view(true, Some(0.75));
+        assert!(swap && scale > 0.0 && scale < 1.0);
+    }
+
+    #[test]
+    fn test_eval_bar() {
+        assert_eq!(ChessGraphic::eval_bar_fraction(0), 0.5);
+        assert_eq!(ChessGraphic::eval_bar_fraction(500), 0.75);
+        assert_eq!(ChessGraphic::eval_bar_fraction(-500), 0.25);
+        // saturate instead of overflowing the window
+        assert_eq!(ChessGraphic::eval_bar_fraction(i16::MAX), 1.0);
+        assert_eq!(ChessGraphic::eval_bar_fraction(-30000), 0.0);
+    }
+
+    #[test]
+    fn test_board_layout() {
+        // wide window, letterboxed left and right
+        let window = [1384.0, 800.0];
+        assert_eq!(ChessGraphic::board_rect(window), [200.0, 0.0, 800.0, 800.0]);
+        assert_eq!(
+            ChessGraphic::eval_bar_rect(window),
+            [1000.0, 0.0, 24.0, 800.0]
+        );
+        assert_eq!(
+            ChessGraphic::move_list_rect(window, false),
+            [1024.0, 0.0, 160.0, 800.0]
+        );
+        // the evaluation graph take the bottom of the move list column
+        assert_eq!(
+            ChessGraphic::move_list_rect(window, true),
+            [1024.0, 0.0, 160.0, 600.0]
+        );
+        assert_eq!(
+            ChessGraphic::eval_graph_rect(window),
+            [1024.0, 600.0, 160.0, 200.0]
+        );
+        // tall window, letterboxed top and bottom
+        assert_eq!(
+            ChessGraphic::board_rect([584.0, 600.0]),
+            [0.0, 100.0, 400.0, 400.0]
+        );
+        assert_eq!(ChessGraphic::board_rect([10.0, 800.0])[2], 0.0);
+
+        // clicks on a resized window land on the square drawn there
+        let viewport = Viewport {
+            rect: [0, 0, 1384, 800],
+            draw_size: [1384, 800],
+            window_size: [800.0, 800.0],
+        };
+        for &swap in &[false, true] {
+            for &square in &[Square::A1, Square::E4, Square::H8] {
+                // drawn relative to the board corner, 200 pixels right of the window's
+                let [x, y, w, h] = ChessGraphic::square_to_rect(&square, &viewport, swap);
+                let (click_x, click_y) = (200.0 + x + w / 2.0, y + h / 2.0);
+                let clicked = ChessGraphic::window_pos_to_square(window, click_x, click_y, swap);
+                assert_eq!(clicked, Some(square));
+            }
+        }
+        // letterbox and evaluation bar
+        assert_eq!(
+            ChessGraphic::window_pos_to_square(window, 100.0, 400.0, false),
+            None
+        );
+        assert_eq!(
+            ChessGraphic::window_pos_to_square(window, 1010.0, 400.0, false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_move_list() {
+        let board =
+            Board::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let moves = ["e7e5", "g1f3", "b8c6"].map(|mov| ChessMove::from_str(mov).unwrap());
+        assert_eq!(
+            ChessGraphic::move_list(&board, &moves),
+            vec!["e5", "Nf3", "Nc6"]
+        );
+
+        // "1. ... e5 2. Nf3 Nc6", set up with Black to move
+        assert_eq!(ChessGraphic::move_list_cell(Color::Black, 0), (0, 1));
+        assert_eq!(ChessGraphic::move_list_cell(Color::Black, 2), (1, 1));
+        assert_eq!(ChessGraphic::move_list_rows(Color::Black, 3), 2);
+        assert_eq!(ChessGraphic::move_list_index(Color::Black, 0, 0, 3), None);
+        assert_eq!(
+            ChessGraphic::move_list_index(Color::Black, 1, 0, 3),
+            Some(1)
+        );
+        assert_eq!(ChessGraphic::move_list_index(Color::White, 1, 1, 3), None);
+        assert_eq!(ChessGraphic::move_list_rows(Color::White, 3), 2);
+
+        // follow the shown move, or stay where scrolled (within the list)
+        assert_eq!(ChessGraphic::move_list_first_row(None, Some(3), 50, 10), 0);
+        assert_eq!(
+            ChessGraphic::move_list_first_row(None, Some(30), 50, 10),
+            21
+        );
+        assert_eq!(
+            ChessGraphic::move_list_first_row(Some(5), Some(30), 50, 10),
+            5
+        );
+        assert_eq!(
+            ChessGraphic::move_list_first_row(Some(99), None, 50, 10),
+            40
+        );
+        assert_eq!(ChessGraphic::move_list_first_row(Some(3), None, 4, 10), 0);
+
+        // rows of 20px below a 36px move number column, two 62px move columns
+        let rect = [1024.0, 0.0, 160.0, 800.0];
+        assert_eq!(
+            ChessGraphic::move_list_hit(rect, 0, 1070.0, 5.0),
+            Some((0, 0))
+        );
+        assert_eq!(
+            ChessGraphic::move_list_hit(rect, 7, 1130.0, 45.0),
+            Some((9, 1))
+        );
+        assert_eq!(ChessGraphic::move_list_hit(rect, 0, 1030.0, 5.0), None);
+        assert_eq!(ChessGraphic::move_list_hit(rect, 0, 1070.0, 805.0), None);
+    }
+
+    #[test]
+    fn test_undo_to_and_redo_order() {
+        let base = Game::new();
+        let moves = ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]
+            .iter()
+            .map(|mov| ChessMove::from_str(mov).unwrap())
+            .collect::<Vec<_>>();
+
+        // keeping the first 2 moves take back the last 3, each ply count as a takeback
+        let scores = [30, 25, 40, 20, 60];
+        let mut takebacks = Takebacks::new(Some(4));
+        let game = takebacks.undo_to(&base, &moves, &scores, 2).unwrap();
+        assert_eq!(
+            game.current_position(),
+            ChessGraphic::position_after(&base.current_position(), &moves[..2])
+        );
+        assert_eq!(takebacks.left(), Some(1));
+        assert_eq!(takebacks.redo_stack, [moves[4], moves[3], moves[2]]);
+
+        // a move and its reply are 2 takebacks, more than left
+        assert!(takebacks
+            .undo_to(&base, &moves[..2], &scores[..2], 0)
+            .is_err());
+        assert_eq!(takebacks.redo_stack.len(), 3);
+        let game = takebacks
+            .undo_to(&base, &moves[..2], &scores[..2], 1)
+            .unwrap();
+        assert_eq!(
+            game.current_position(),
+            ChessGraphic::position_after(&base.current_position(), &moves[..1])
+        );
+        assert_eq!(takebacks.left(), Some(0));
+
+        // the next move to redo is popped first, with the score it had before the undo
+        assert_eq!(takebacks.pop_redo(), Some((moves[1], Some(25))));
+        assert_eq!(takebacks.pop_redo(), Some((moves[2], Some(40))));
+        assert_eq!(takebacks.pop_redo(), Some((moves[3], Some(20))));
+        assert_eq!(takebacks.pop_redo(), Some((moves[4], Some(60))));
+        assert_eq!(takebacks.pop_redo(), None);
+    }
+
+    #[test]
+    fn test_king_in_check() {
+        assert_eq!(ChessGraphic::king_in_check(&Board::default()), None);
+        // Bb5+ in the French
+        let board =
+            Board::from_str("rnbqkbnr/ppp2ppp/4p3/1B1p4/3PP3/8/PPP2PPP/RNBQK1NR b KQkq - 1 3")
+                .unwrap();
+        assert_eq!(ChessGraphic::king_in_check(&board), Some(Square::E8));
+    }
+
+    #[test]
+    fn test_analysis_lines() {
+        // black to move, scores are from black side
+        let board =
+            Board::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let ranked = [("e7e5", 20), ("c7c5", 15), ("g8f6", 10)]
+            .iter()
+            .map(|&(mov, score)| (ChessMove::from_str(mov).unwrap(), score))
+            .collect::<Vec<_>>();
+
+        let lines = ChessGraphic::analysis_lines(&board, &ranked, 2);
+        assert_eq!(
+            lines,
+            vec![("e5".to_string(), -20), ("c5".to_string(), -15)]
+        );
+        assert_eq!(ChessGraphic::analysis_lines(&board, &ranked, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_ponder_search() {
+        // after the expected ...b6, Ra8 is mate
+        let before = Board::from_str("6k1/1p3ppp/8/8/8/8/8/R5K1 b - - 0 1").unwrap();
+        let expected = ChessMove::from_str("b7b6").unwrap();
+        let options = SearchOptions::default();
+        let ponder = PonderSearch::start(before, expected, 3, options, HashSet::new());
+        assert!(ponder.expects(&before));
+        assert!(ponder.expects(&before.make_move_new(expected)));
+        assert!(!ponder.expects(&Board::default()));
+        let (best, _, cache) = ponder.join();
+        assert_eq!(best.unwrap().0, ChessMove::from_str("a1a8").unwrap());
+        assert!(!cache.is_empty());
+
+        // stopping an unbounded search still give the best move found so far
+        let board =
+            Board::from_str("r3k2r/pppq1ppp/2nbbn2/3pp3/3PP3/2NBBN2/PPPQ1PPP/R3K2R b KQkq - 0 1")
+                .unwrap();
+        let expected = ChessMove::from_str("a8b8").unwrap();
+        let start = Instant::now();
+        let ponder = PonderSearch::start(board, expected, MAX_TIMED_DEPTH, options, HashSet::new());
+        thread::sleep(Duration::from_millis(50));
+        let (best, _, _) = ponder.stop();
+        assert!(board.make_move_new(expected).legal(best.unwrap().0));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
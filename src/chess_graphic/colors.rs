@@ -3,12 +3,14 @@ pub const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
 
 pub const MAT_BLUE: [f32; 4] = [0.129, 0.588, 0.953, 1.0];
 pub const MAT_ORANGE: [f32; 4] = [0.953, 0.494, 0.129, 1.0];
+pub const MAT_ORANGE_TRANS: [f32; 4] = [0.953, 0.494, 0.129, 0.5];
 pub const MAT_GREEN: [f32; 4] = [0.29, 0.812, 0.314, 1.0];
 pub const MAT_GREEN_TRANS: [f32; 4] = [0.29, 0.812, 0.314, 0.5];
-pub const MAT_LIME: [f32; 4] = [0.804, 0.863, 0.224, 1.0];
 pub const MAT_LIME_TRANS: [f32; 4] = [0.804, 0.863, 0.224, 0.2];
+pub const MAT_RED_TRANS: [f32; 4] = [0.957, 0.263, 0.212, 0.6];
+
+pub const BLACK_TRANS: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
 
-pub const PAS_GREEN: [f32; 4] = [0.467, 0.867, 0.467, 1.0];
 pub const PAS_WHITE: [f32; 4] = [0.3491, 0.3448, 0.3062, 1.0];
 
 pub const EARTH_GREEN: [f32; 4] = [0.46, 0.55, 0.45, 1.0];
@@ -17,4 +19,39 @@ pub const GRID_COLOR_1: [f32; 4] = PAS_WHITE;
 pub const GRID_COLOR_2: [f32; 4] = EARTH_GREEN;
 pub const GRID_COLOR_MOVED: [f32; 4] = MAT_LIME_TRANS;
 
+// around the board when the window isn't square
+pub const LETTERBOX: [f32; 4] = BLACK;
+
+pub const BOOK_MOVE: [f32; 4] = MAT_BLUE;
+
+pub const EXCHANGE_GOOD: [f32; 4] = MAT_GREEN;
+pub const EXCHANGE_BAD: [f32; 4] = MAT_ORANGE;
+
+pub const PROMOTION_PICKER: [f32; 4] = WHITE;
+
+pub const LEGAL_MOVE: [f32; 4] = MAT_GREEN_TRANS;
+pub const LEGAL_CAPTURE: [f32; 4] = MAT_ORANGE_TRANS;
+
+// light enough to read on both grid colors
+pub const COORDINATE_LABEL: [f32; 4] = WHITE;
+
 pub const COLOR_SELECTED: [f32; 4] = MAT_GREEN_TRANS;
+
+pub const KING_IN_CHECK: [f32; 4] = MAT_RED_TRANS;
+
+pub const GRAPH_BACKGROUND: [f32; 4] = BLACK_TRANS;
+pub const GRAPH_AXIS: [f32; 4] = WHITE;
+pub const GRAPH_LINE: [f32; 4] = MAT_ORANGE;
+
+pub const EVAL_BAR_WHITE: [f32; 4] = [0.93, 0.93, 0.93, 1.0];
+pub const EVAL_BAR_BLACK: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+pub const EVAL_BAR_MIDDLE: [f32; 4] = MAT_ORANGE;
+
+pub const MOVE_LIST_BACKGROUND: [f32; 4] = [0.15, 0.15, 0.15, 1.0];
+pub const MOVE_LIST_TEXT: [f32; 4] = WHITE;
+pub const MOVE_LIST_NUMBER: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+pub const MOVE_LIST_CURRENT: [f32; 4] = MAT_ORANGE_TRANS;
+
+pub const GAME_OVER_OVERLAY: [f32; 4] = [0.0, 0.0, 0.0, 0.3];
+pub const GAME_OVER_BANNER: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+pub const GAME_OVER_TEXT: [f32; 4] = WHITE;
@@ -0,0 +1,54 @@
+use super::colors;
+
+/// Board colors, selected at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub grid_color_1: [f32; 4],
+    pub grid_color_2: [f32; 4],
+    pub grid_color_moved: [f32; 4],
+    pub selected: [f32; 4],
+    pub coordinate_label: [f32; 4],
+}
+
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        name: "Green",
+        grid_color_1: colors::GRID_COLOR_1,
+        grid_color_2: colors::GRID_COLOR_2,
+        grid_color_moved: colors::GRID_COLOR_MOVED,
+        selected: colors::COLOR_SELECTED,
+        coordinate_label: colors::COORDINATE_LABEL,
+    },
+    Theme {
+        name: "Wood",
+        grid_color_1: [0.71, 0.53, 0.39, 1.0],
+        grid_color_2: [0.94, 0.85, 0.71, 1.0],
+        grid_color_moved: [0.95, 0.85, 0.2, 0.35],
+        selected: colors::MAT_ORANGE_TRANS,
+        coordinate_label: [0.25, 0.15, 0.08, 1.0],
+    },
+    Theme {
+        name: "Ice",
+        grid_color_1: [0.45, 0.58, 0.68, 1.0],
+        grid_color_2: [0.87, 0.91, 0.94, 1.0],
+        grid_color_moved: colors::MAT_LIME_TRANS,
+        selected: colors::MAT_GREEN_TRANS,
+        coordinate_label: [0.1, 0.15, 0.25, 1.0],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::THEMES;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_themes_distinct() {
+        assert!(THEMES.iter().map(|theme| theme.name).all_unique());
+        for theme in THEMES.iter() {
+            // both square colors must be told apart
+            assert_ne!(theme.grid_color_1, theme.grid_color_2, "{}", theme.name);
+        }
+    }
+}
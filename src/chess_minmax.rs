@@ -1,21 +1,28 @@
-use crate::chess_minmax::main_evalation::evaluation_pieces_worth_plus;
-use chess::{
-    Board, BoardStatus, ChessMove, Color, MoveGen, Piece, ALL_FILES, ALL_RANKS, EMPTY, NUM_RANKS,
+use crate::chess_minmax::main_evalation::{
+    debug_evaluation, evaluate_stm, evaluate_white_pov, static_exchange_evaluation_move,
+    PIECE_VALUE,
 };
+use chess::{BitBoard, Board, BoardStatus, ChessMove, Color, MoveGen, Piece, EMPTY};
 
 use lru::LruCache;
 
+use rand::seq::IteratorRandom;
 use rand::Rng;
 
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
 use std::hash::{BuildHasher, Hash};
-use std::collections::HashSet;
 use std::option::Option::Some;
+use std::sync::atomic::{self, AtomicBool};
+use std::time::{Duration, Instant};
 
 pub mod main_evalation;
+pub mod tablebase;
 
-// TODO: add transposition table
-// https://en.wikipedia.org/wiki/Negamax#Negamax_with_alpha_beta_pruning_and_transposition_tables
-// TODO: use fast hash for transposition table hashing
+use tablebase::{tablebase, Wdl};
+
+// the search is negamax with alpha-beta pruning, its transposition table store bounded scores
+// as described in https://en.wikipedia.org/wiki/Negamax#Negamax_with_alpha_beta_pruning_and_transposition_tables
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct BoardHash(u64);
@@ -25,50 +32,622 @@ impl BoardHash {
     }
 }
 
+/// Positions of a game that occurred at least twice (candidates for 3-fold repetition)
+/// and the halfmove clock, updated move by move instead of replaying the whole game
+/// before every search
+#[derive(Default, Clone)]
+pub struct RepetitionTracker {
+    // position before each move, in order
+    history: Vec<BoardHash>,
+    counts: HashMap<BoardHash, u16>,
+    repeated: HashSet<BoardHash>,
+    // ply since the last capture or pawn move of the current position
+    halfmove_clock: u16,
+    // `halfmove_clock` before each move, restored by `pop`
+    clocks: Vec<u16>,
+}
+
+impl RepetitionTracker {
+    /// Record `board` as the position `mov` is being played from
+    pub fn push(&mut self, board: &Board, mov: ChessMove) {
+        let hash = BoardHash::new(board);
+        self.history.push(hash);
+        self.clocks.push(self.halfmove_clock);
+        self.halfmove_clock = if resets_halfmove_clock(board, mov) {
+            0
+        } else {
+            self.halfmove_clock.saturating_add(1)
+        };
+
+        let count = self.counts.entry(hash).or_insert(0);
+        *count += 1;
+        if *count >= 2 {
+            self.repeated.insert(hash);
+        }
+    }
+
+    pub fn pop(&mut self) {
+        if let Some(clock) = self.clocks.pop() {
+            self.halfmove_clock = clock;
+        }
+        if let Some(hash) = self.history.pop() {
+            let count = self
+                .counts
+                .get_mut(&hash)
+                .expect("popped position was counted");
+            *count -= 1;
+            match *count {
+                0 => {
+                    self.counts.remove(&hash);
+                }
+                1 => {
+                    self.repeated.remove(&hash);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Keep only the first `len` positions
+    pub fn truncate(&mut self, len: usize) {
+        while self.history.len() > len {
+            self.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.counts.clear();
+        self.repeated.clear();
+        self.halfmove_clock = 0;
+        self.clocks.clear();
+    }
+
+    /// Halfmove clock of the starting position (e.g. from the FEN, see `fen_halfmove_clock`),
+    /// to set before any move is recorded
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u16) {
+        debug_assert!(self.history.is_empty());
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    /// Ply since the last capture or pawn move of the position after the recorded moves
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Number of time `current` (the position after the recorded moves) occurred, itself included
+    pub fn position_count(&self, current: &Board) -> u16 {
+        self.counts
+            .get(&BoardHash::new(current))
+            .copied()
+            .unwrap_or(0)
+            + 1
+    }
+
+    /// number of move recorded
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// number of distinct position recorded
+    #[cfg(test)]
+    pub fn distinct_positions(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn repeated(&self) -> &HashSet<BoardHash> {
+        &self.repeated
+    }
+}
+
+#[derive(Clone)]
 pub enum BoundedScore {
     LowerBound(i16),
     UpperBound(i16),
     Exact(i16),
 }
 
+#[derive(Clone)]
 pub struct TranspositionItem {
     score: BoundedScore,
     depth: u8,
+    /// position the entry was stored for, to catch hash collision (debug build only)
+    #[cfg(debug_assertions)]
+    position: Option<Board>,
 }
 
-fn negamax<K: BuildHasher>(
+impl TranspositionItem {
+    fn new(score: BoundedScore, depth: u8, board: &Board) -> Self {
+        #[cfg(not(debug_assertions))]
+        let _ = board;
+        TranspositionItem {
+            score,
+            depth,
+            #[cfg(debug_assertions)]
+            position: Some(*board),
+        }
+    }
+
+    /// Entry with known exact `score` (side to move perspective) searched to `depth`,
+    /// e.g. from an external analysis
+    pub fn exact(score: i16, depth: u8) -> Self {
+        TranspositionItem {
+            score: BoundedScore::Exact(score),
+            depth,
+            #[cfg(debug_assertions)]
+            position: None,
+        }
+    }
+
+    /// Panic if the entry was stored for another position with the same hash,
+    /// a collision would otherwise silently return the score of the wrong position.
+    /// Only checked in debug build.
+    fn assert_same_position(&self, board: &Board) {
+        #[cfg(debug_assertions)]
+        if let Some(position) = self.position {
+            assert!(
+                position == *board,
+                "transposition table hash collision: entry of {} probed for {}",
+                position,
+                board
+            );
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = board;
+    }
+}
+
+/// State shared by every node of a single search
+struct SearchContext<'a, R, K> {
+    rng: &'a mut R,
+    cache: &'a mut LruCache<BoardHash, TranspositionItem, K>,
+    /// positions that already repeated in the game, reaching them again is a draw
+    repetition: &'a HashSet<BoardHash>,
+    /// positions from the root to the current node
+    ///
+    /// Reaching one of them again means the side to move can't make any progress
+    /// (capture and pawn move are irreversible), see `is_path_draw` for when
+    /// that is scored as a draw (what let the search recognize fortress and perpetual check).
+    path: Vec<BoardHash>,
+    /// number of node visited so far
+    nodes: u64,
+    /// number of transposition table probe that found a deep enough entry
+    tt_hits: u64,
+    /// number of draw scored that depend on how the position was reached (see `path_draw`),
+    /// a node below which one was scored isn't cached
+    path_draws: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    stop: Option<&'a AtomicBool>,
+    /// deadline was found passed (or the search stopped), latched since the clock
+    /// is only read every few nodes
+    past_deadline: bool,
+    /// maximum number of ply of quiescence search below the nominal depth
+    quiescence_depth: u8,
+    /// deepest quiescence ply reached so far
+    quiescence_reached: u8,
+    /// depth of the deepest iterative deepening iteration completed
+    completed_depth: u8,
+    /// side the search is run for, `contempt` is from its perspective
+    root_side: Color,
+    contempt: i16,
+    /// see `SearchOptions::eval_noise`
+    eval_noise: i16,
+    /// ply since the last capture or pawn move at the current node
+    halfmove_clock: u16,
+    /// try null-move pruning (see `null_move_cutoff`)
+    null_move_pruning: bool,
+    /// the current node was reached by a null move, two in a row would search nothing
+    after_null_move: bool,
+    /// search capture first by MVV-LVA (see `mvv_lva`) instead of the move generator order
+    move_ordering: bool,
+    /// search killer moves right after captures (see `store_killer`)
+    killer_moves: bool,
+    /// principal variation search, move after the first are searched with a null window
+    pvs: bool,
+    /// search each iteration with a narrow window around the previous score first
+    /// (see `aspiration_iteration`)
+    aspiration_windows: bool,
+    /// search the root moves in parallel (`parallel` feature), never when node limited
+    parallel_root: bool,
+    /// distance from the root of the current node, null move included
+    ply: usize,
+    /// up to two quiet moves per ply that caused a beta cutoff, most recent first
+    killers: [[Option<ChessMove>; 2]; MAX_KILLER_PLY],
+}
+
+impl<'a, R: Rng, K: BuildHasher> SearchContext<'a, R, K> {
+    fn new(
+        root: &Board,
+        rng: &'a mut R,
+        cache: &'a mut LruCache<BoardHash, TranspositionItem, K>,
+        repetition: &'a HashSet<BoardHash>,
+    ) -> Self {
+        SearchContext {
+            rng,
+            cache,
+            repetition,
+            path: vec![BoardHash::new(root)],
+            nodes: 0,
+            tt_hits: 0,
+            path_draws: 0,
+            node_limit: None,
+            deadline: None,
+            stop: None,
+            past_deadline: false,
+            quiescence_depth: DEFAULT_QUIESCENCE_DEPTH,
+            quiescence_reached: 0,
+            completed_depth: 0,
+            root_side: root.side_to_move(),
+            contempt: 0,
+            eval_noise: 0,
+            halfmove_clock: 0,
+            null_move_pruning: true,
+            after_null_move: false,
+            move_ordering: true,
+            killer_moves: true,
+            pvs: true,
+            aspiration_windows: true,
+            parallel_root: cfg!(feature = "parallel"),
+            ply: 0,
+            killers: [[None; 2]; MAX_KILLER_PLY],
+        }
+    }
+
+    /// Context searching a subtree on another thread, same settings and path
+    /// but its own RNG and cache (see `search_moves_parallel`)
+    #[cfg(feature = "parallel")]
+    fn fork<'b, R2, K2>(
+        &self,
+        rng: &'b mut R2,
+        cache: &'b mut LruCache<BoardHash, TranspositionItem, K2>,
+    ) -> SearchContext<'b, R2, K2>
+    where
+        'a: 'b,
+    {
+        SearchContext {
+            rng,
+            cache,
+            repetition: self.repetition,
+            path: self.path.clone(),
+            nodes: 0,
+            tt_hits: 0,
+            path_draws: 0,
+            node_limit: None,
+            deadline: self.deadline,
+            stop: self.stop,
+            past_deadline: false,
+            quiescence_depth: self.quiescence_depth,
+            quiescence_reached: 0,
+            completed_depth: 0,
+            root_side: self.root_side,
+            contempt: self.contempt,
+            eval_noise: self.eval_noise,
+            halfmove_clock: self.halfmove_clock,
+            null_move_pruning: self.null_move_pruning,
+            after_null_move: self.after_null_move,
+            move_ordering: self.move_ordering,
+            killer_moves: self.killer_moves,
+            pvs: self.pvs,
+            aspiration_windows: self.aspiration_windows,
+            parallel_root: false,
+            ply: self.ply,
+            killers: self.killers,
+        }
+    }
+
+    /// Use the settings of `options` that apply to every node,
+    /// the limits and root filter are left to `iterative_deepening`
+    fn apply_options(&mut self, options: &SearchOptions) {
+        self.quiescence_depth = options.quiescence_depth;
+        self.contempt = options.contempt;
+        self.halfmove_clock = options.halfmove_clock;
+        self.eval_noise = options.eval_noise;
+    }
+
+    /// Score of a draw for the side to move of `board`
+    fn draw_score(&self, board: &Board) -> i16 {
+        if board.side_to_move() == self.root_side {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// `draw_score` of a draw that only hold on the current path: repetition (in the game
+    /// or the searched line) and the fifty-move rule
+    fn path_draw(&mut self, board: &Board) -> i16 {
+        self.path_draws += 1;
+        self.draw_score(board)
+    }
+
+    /// Whether reaching `board` again on the searched line is scored as a draw: only when
+    /// the side ahead made the move back (its line makes no progress) and nothing was
+    /// captured nor any pawn moved for `PATH_DRAW_MIN_PLY`, a single repetition right
+    /// after a capture is more likely a transposition than a fortress
+    fn is_path_draw(&self, board: &Board) -> bool {
+        self.halfmove_clock >= PATH_DRAW_MIN_PLY && evaluate_stm(board) < 0
+    }
+
+    /// `draw_score` of a draw of the position itself (stalemate, no mating material),
+    /// with contempt it still depend on the side searching so it count as `path_draw`
+    fn position_draw(&mut self, board: &Board) -> i16 {
+        if self.contempt != 0 {
+            self.path_draws += 1;
+        }
+        self.draw_score(board)
+    }
+
+    /// Negamax score of playing `mov` on `board`, keeping the halfmove clock up to date
+    fn search_child(&mut self, board: &Board, mov: ChessMove, depth: u8, a: i16, b: i16) -> i16 {
+        let clock = self.halfmove_clock;
+        let after_null_move = self.after_null_move;
+        self.halfmove_clock = if resets_halfmove_clock(board, mov) {
+            0
+        } else {
+            clock.saturating_add(1)
+        };
+        self.after_null_move = false;
+        self.ply += 1;
+        let score = -negamax(&board.make_move_new(mov), depth, -b, -a, self);
+        self.ply -= 1;
+        self.halfmove_clock = clock;
+        self.after_null_move = after_null_move;
+        score
+    }
+
+    /// Null-move pruning: if passing the turn and searching `NULL_MOVE_REDUCTION` ply
+    /// shallower still score at least `b`, a real move would too (having the move is
+    /// almost always an advantage) so the node can be cut without searching its moves.
+    ///
+    /// Not tried in check, right after another null move, or when the side to move has only
+    /// king and pawns (zugzwang, where passing would be the best move, is common there).
+    fn null_move_cutoff(&mut self, board: &Board, depth: u8, b: i16) -> bool {
+        if !self.null_move_pruning
+            || self.after_null_move
+            || depth <= NULL_MOVE_REDUCTION
+            || *board.checkers() != EMPTY
+        {
+            return false;
+        }
+        let own = board.color_combined(board.side_to_move());
+        let pawns_and_king = board.pieces(Piece::Pawn) | board.pieces(Piece::King);
+        if own & !pawns_and_king == EMPTY {
+            return false;
+        }
+        let Some(passed) = board.null_move() else {
+            return false;
+        };
+
+        self.after_null_move = true;
+        self.ply += 1;
+        let score = -negamax(
+            &passed,
+            depth - 1 - NULL_MOVE_REDUCTION,
+            -b,
+            b.saturating_sub(1).saturating_neg(),
+            self,
+        );
+        self.ply -= 1;
+        self.after_null_move = false;
+        score >= b && !self.out_of_budget()
+    }
+
+    /// Ordering key of a quiet move at the current ply, 2 for the most recent killer move,
+    /// 1 for the other and 0 for any other move
+    fn killer_rank(&self, mov: ChessMove) -> u8 {
+        match self.killers.get(self.ply) {
+            Some([first, _]) if *first == Some(mov) => 2,
+            Some([_, second]) if *second == Some(mov) => 1,
+            _ => 0,
+        }
+    }
+
+    /// Remember a quiet move that caused a beta cutoff, a sibling node (same ply)
+    /// is likely to be refuted by the same move so it will be tried early there
+    fn store_killer(&mut self, mov: ChessMove) {
+        if let Some(killers) = self.killers.get_mut(self.ply) {
+            if killers[0] != Some(mov) {
+                killers[1] = killers[0];
+                killers[0] = Some(mov);
+            }
+        }
+    }
+
+    /// Search should be abandoned, score returned afterward are meaningless
+    fn out_of_budget(&self) -> bool {
+        self.past_deadline || self.node_limit.is_some_and(|limit| self.nodes >= limit)
+    }
+
+    /// Read the clock and the stop flag every `DEADLINE_CHECK_INTERVAL` nodes
+    fn check_deadline(&mut self) {
+        if self.nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && (self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+                || self
+                    .stop
+                    .is_some_and(|stop| stop.load(atomic::Ordering::Relaxed)))
+        {
+            self.past_deadline = true;
+        }
+    }
+}
+
+// quiescence search ply cap unless configured
+pub const DEFAULT_QUIESCENCE_DEPTH: u8 = 8;
+
+// number of ply without capture or pawn move for the fifty-move rule
+pub const FIFTY_MOVE_PLY: u16 = 100;
+
+// number of ply without capture or pawn move before a repetition in the searched line
+// is scored as a draw, see `SearchContext::is_path_draw`
+pub const PATH_DRAW_MIN_PLY: u16 = 16;
+
+// depth reduction of the null-move search, see `SearchContext::null_move_cutoff`
+const NULL_MOVE_REDUCTION: u8 = 2;
+
+// half width of the first aspiration window (centipawn), doubled on every re-search
+const ASPIRATION_WINDOW: i16 = 50;
+// aspiration windows are only used around scores below this (mate scores jump between iterations)
+const ASPIRATION_SCORE_LIMIT: i16 = 10_000;
+
+// killer moves are kept for this many ply from the root, deeper nodes don't use them
+const MAX_KILLER_PLY: usize = 64;
+
+// number of node between two read of the clock in time limited search
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+// deepest iteration of a time limited search,
+// deeper mate score could overflow (see `stats_eval_fn`)
+pub const MAX_TIMED_DEPTH: u8 = 16;
+
+// contempt (centipawn) at full press, see `SearchOptions::with_press`
+pub const MAX_PRESS_CONTEMPT: i16 = 50;
+// 2nd best margin (centipawn) at full press
+pub const MAX_PRESS_MARGIN: i16 = 50;
+// chance of asking for the 2nd best move at full press
+pub const MAX_PRESS_SECOND_BEST_CHANCE: f64 = 0.2;
+
+/// Predicate deciding whether a move on the board may be played
+pub type MoveFilter<'a> = dyn Fn(&Board, ChessMove) -> bool + Sync + 'a;
+
+/// Optional constraints on a search, the default is unconstrained
+#[derive(Clone, Copy)]
+pub struct SearchOptions<'a> {
+    /// Stop the search after visiting this many nodes.
+    ///
+    /// The best move of the deepest completed iterative deepening iteration is then returned,
+    /// 1 ply iteration is never cut off so there is always a move.
+    pub node_limit: Option<u64>,
+    /// Stop the search once this much time passed, same as `node_limit` the best move of
+    /// the deepest completed iteration is returned
+    pub time_budget: Option<Duration>,
+    /// Stop the search once set (by another thread), same as `time_budget` the best move of
+    /// the deepest completed iteration is returned
+    pub stop: Option<&'a AtomicBool>,
+    /// Only root moves accepted by this predicate are searched (e.g. to avoid a move),
+    /// if every move is rejected the search return no move
+    pub root_filter: Option<&'a MoveFilter<'a>>,
+    /// Cap on quiescence search ply (see `DEFAULT_QUIESCENCE_DEPTH`),
+    /// at the cap the static evaluation is used as is. 0 disable quiescence search.
+    pub quiescence_depth: u8,
+    /// When asked for the 2nd best move, only play it if it is at most this much centipawn
+    /// worse than the best move (see `second_best_within`), None to always play it
+    pub second_best_margin: Option<i16>,
+    /// Probability for the caller to ask for the 2nd best move instead of the best one,
+    /// 0.0 (default) never
+    pub second_best_chance: f64,
+    /// Centipawn a draw is worth less than an equal position for the side searching,
+    /// positive avoid draws and negative seek them
+    pub contempt: i16,
+    /// Ply since the last capture or pawn move before the root position,
+    /// the search score a draw once it reach `FIFTY_MOVE_PLY`
+    pub halfmove_clock: u16,
+    /// Random centipawn (uniform in `-eval_noise..=eval_noise`) added to every static
+    /// evaluation to weaken the search, 0 (default) keep the search deterministic
+    pub eval_noise: i16,
+}
+
+impl Default for SearchOptions<'_> {
+    fn default() -> Self {
+        SearchOptions {
+            node_limit: None,
+            time_budget: None,
+            stop: None,
+            root_filter: None,
+            quiescence_depth: DEFAULT_QUIESCENCE_DEPTH,
+            second_best_margin: None,
+            second_best_chance: 0.0,
+            contempt: 0,
+            halfmove_clock: 0,
+            eval_noise: 0,
+        }
+    }
+}
+
+impl SearchOptions<'_> {
+    /// Set how hard to press for a win from `press` -1.0 (opponent much stronger, play solid
+    /// and take draws) to 1.0 (opponent much weaker, avoid draws and accept slightly worse
+    /// 2nd best move), 0.0 is neutral.
+    ///
+    /// Set `contempt`, `second_best_margin` and `second_best_chance`.
+    pub fn with_press(mut self, press: f64) -> Self {
+        let press = press.clamp(-1.0, 1.0);
+        self.contempt = (press * f64::from(MAX_PRESS_CONTEMPT)).round() as i16;
+        self.second_best_margin =
+            Some((press.max(0.0) * f64::from(MAX_PRESS_MARGIN)).round() as i16);
+        self.second_best_chance = press.max(0.0) * MAX_PRESS_SECOND_BEST_CHANCE;
+        self
+    }
+}
+
+fn negamax<R: Rng, K: BuildHasher>(
     board: &Board,
     depth: u8,
     mut a: i16,
     mut b: i16,
-    rng: &mut impl Rng,
-    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
-    repetition: &HashSet<BoardHash>
+    ctx: &mut SearchContext<R, K>,
 ) -> i16 {
+    ctx.nodes += 1;
+    ctx.check_deadline();
+    if ctx.out_of_budget() {
+        return 0;
+    }
+
     // var setup
     let a_orig = a;
+    let path_draws = ctx.path_draws;
 
     let color_index = match board.side_to_move() {
         Color::White => 1,
         Color::Black => -1,
     };
 
-    // terminating condition
-    if depth == 0 {
-        return color_index as i16 * evaluation_fn(board, rng);
+    let board_hash = BoardHash::new(board);
+
+    // terminating condition 3 (repetition, either in the game or in the searching line)
+    let on_path = ctx.path.contains(&board_hash);
+    if ctx.repetition.contains(&board_hash) || (on_path && ctx.is_path_draw(board)) {
+        return ctx.path_draw(board);
     }
 
+    // terminating condition 4 (fifty-move rule), unless the last move checkmated
+    if ctx.halfmove_clock >= FIFTY_MOVE_PLY && board.status() != BoardStatus::Checkmate {
+        return ctx.path_draw(board);
+    }
 
-    let board_hash = BoardHash::new(board);
+    // terminating condition 5 (nobody can checkmate)
+    if is_insufficient_material(board) {
+        return ctx.position_draw(board);
+    }
 
-    // terminating condition 3 (repetition)
-    // TODO: sanity check
-    if repetition.contains(&board_hash) {
-        return 0;
+    // terminating condition 6 (endgame tablebase), probed right after a capture or pawn move
+    // only, later the fifty-move rule may change the result
+    if ctx.halfmove_clock == 0 {
+        if let Some(wdl) = tablebase().and_then(|tablebase| tablebase.probe_wdl(board)) {
+            let draw_score = match wdl {
+                Wdl::Draw => ctx.position_draw(board),
+                _ => 0,
+            };
+            return wdl.score(ctx.ply, draw_score);
+        }
     }
 
-    // Cache checking
-    if let Some(tt_entry) = cache.get(&board_hash).filter(|tte| tte.depth >= depth) {
+    // terminating condition
+    if depth == 0 {
+        return quiescence(board, 0, a, b, ctx);
+    }
+
+    // Cache checking, skipped for a repetition not scored as a draw since the entry
+    // may be the score of its own ancestor
+    let cached = ctx
+        .cache
+        .get(&board_hash)
+        .filter(|tte| !on_path && tte.depth >= depth);
+    if let Some(tt_entry) = cached {
+        tt_entry.assert_same_position(board);
+        ctx.tt_hits += 1;
         let entry_val = match tt_entry.score {
             BoundedScore::Exact(ex) => return ex,
             BoundedScore::LowerBound(lb) => {
@@ -86,31 +665,76 @@ fn negamax<K: BuildHasher>(
         }
     }
 
-    // negamax core
-    let child_nodes = MoveGen::new_legal(&board).map(|mov| board.make_move_new(mov));
+    // a position good enough even after passing is cut (score is a lower bound, not cached),
+    // only tried with a null window so the principal variation is never pruned
+    let null_window = a.checked_add(1) == Some(b);
+    if null_window && ctx.null_move_cutoff(board, depth, b) {
+        return b;
+    }
 
+    // negamax core
     let mut value = -i16::MAX;
-    for child in child_nodes {
-        let node_eval = -negamax(&child, depth - 1, -b, -a, rng, cache, repetition);
+    ctx.path.push(board_hash);
+    let mut moves = MoveGen::new_legal(board).collect::<Vec<_>>();
+    if ctx.move_ordering {
+        // stable sort, capture first then killer moves then other quiet moves in generator order
+        let killer_moves = ctx.killer_moves;
+        moves.sort_by_cached_key(|&mov| {
+            let killer_rank = if killer_moves {
+                ctx.killer_rank(mov)
+            } else {
+                0
+            };
+            Reverse((mvv_lva(board, mov), killer_rank))
+        });
+    }
+    for (i, mov) in moves.into_iter().enumerate() {
+        let node_eval = if i == 0 || !ctx.pvs {
+            ctx.search_child(board, mov, depth - 1, a, b)
+        } else {
+            // principal variation search: the first move is expected to be the best, only
+            // prove the others are not better than `a` (null window), re-search if one is
+            let scout = ctx.search_child(board, mov, depth - 1, a, a + 1);
+            if scout > a && scout < b && !ctx.out_of_budget() {
+                ctx.search_child(board, mov, depth - 1, a, b)
+            } else {
+                scout
+            }
+        };
         debug_assert!(node_eval > -i16::MAX);
 
         value = i16::max(value, node_eval);
 
         a = i16::max(a, value);
         if a >= b {
+            if board.piece_on(mov.get_dest()).is_none() {
+                ctx.store_killer(mov);
+            }
+            break;
+        }
+        if ctx.out_of_budget() {
             break;
         }
     }
+    ctx.path.pop();
+
+    // search cut off, don't let incomplete value into the cache
+    if ctx.out_of_budget() {
+        return 0;
+    }
 
     debug_assert_eq!(value == -i16::MAX, board.status() != BoardStatus::Ongoing);
     // terminating condition 2 (no move)
     if value == -i16::MAX {
-        let status = if *board.checkers() == EMPTY {
-            BoardStatus::Stalemate
-        } else {
-            BoardStatus::Checkmate
-        };
-        return color_index as i16 * stats_eval_fn(status, color_index, depth);
+        if *board.checkers() == EMPTY {
+            return ctx.position_draw(board);
+        }
+        return color_index as i16 * stats_eval_fn(BoardStatus::Checkmate, color_index, depth);
+    }
+
+    // a draw only holding on this path was scored below, the value mustn't be reused elsewhere
+    if ctx.path_draws != path_draws {
+        return value;
     }
 
     // Cache store
@@ -121,26 +745,232 @@ fn negamax<K: BuildHasher>(
     } else {
         BoundedScore::Exact(value)
     };
-    let new_entry = TranspositionItem {
-        score: new_entry_score,
-        depth,
-    };
-    cache.put(board_hash, new_entry);
+    let new_entry = TranspositionItem::new(new_entry_score, depth, board);
+    ctx.cache.put(board_hash, new_entry);
 
     // Returning
     value
 }
 
+/// Search only captures (most valuable victim first) until the position is quiet
+/// so the evaluation isn't taken in the middle of an exchange.
+///
+/// The side to move may always stand pat (decline every capture),
+/// at `ctx.quiescence_depth` ply the stand pat score is returned.
+fn quiescence<R: Rng, K: BuildHasher>(
+    board: &Board,
+    ply: u8,
+    mut a: i16,
+    b: i16,
+    ctx: &mut SearchContext<R, K>,
+) -> i16 {
+    ctx.quiescence_reached = ctx.quiescence_reached.max(ply);
+
+    // a capture may have taken the last mating material
+    if is_insufficient_material(board) {
+        return ctx.position_draw(board);
+    }
+
+    let color_index = match board.side_to_move() {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+    let stand_pat = color_index * evaluation_fn(board, ctx.rng, ctx.eval_noise);
+    if ply >= ctx.quiescence_depth || stand_pat >= b {
+        return stand_pat;
+    }
+    a = a.max(stand_pat);
+
+    let mut captures = MoveGen::new_legal(board);
+    captures.set_iterator_mask(*board.color_combined(!board.side_to_move()));
+    // skip capture losing material by static exchange, standing pat is better anyway
+    let mut captures = captures
+        .filter(|&mov| static_exchange_evaluation_move(board, mov).is_none_or(|see| see >= 0))
+        .collect::<Vec<_>>();
+    captures.sort_by_key(|&mov| Reverse(mvv_lva(board, mov)));
+
+    for mov in captures {
+        ctx.nodes += 1;
+        ctx.check_deadline();
+        if ctx.out_of_budget() {
+            break;
+        }
+
+        let score = -quiescence(&board.make_move_new(mov), ply + 1, -b, -a, ctx);
+        if score >= b {
+            return score;
+        }
+        a = a.max(score);
+    }
+    a
+}
+
 pub fn negamax_prelude<K: BuildHasher>(
     board: &Board,
     depth: u8,
     rng: &mut impl Rng,
     cache: &mut LruCache<BoardHash, TranspositionItem, K>,
-    repetition: &HashSet<BoardHash>
+    repetition: &HashSet<BoardHash>,
+) -> Option<(ChessMove, i16)> {
+    negamax_prelude_with(
+        board,
+        depth,
+        &SearchOptions::default(),
+        rng,
+        cache,
+        repetition,
+    )
+}
+
+/// Same as `negamax_prelude` but constrained by `options`
+pub fn negamax_prelude_with<K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    rng: &mut impl Rng,
+    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
+    repetition: &HashSet<BoardHash>,
 ) -> Option<(ChessMove, i16)> {
-    // var initialization
-    let mut a = -i16::MAX; // don't use i16::MIN! it will overflow on negation
-    let b = i16::MAX;
+    let (best, _stats) = search_root(board, depth, options, rng, cache, repetition);
+    best
+}
+
+/// Counters of a single search
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    /// number of node visited
+    pub nodes: u64,
+    /// number of transposition table probe that cut the search or narrowed the window
+    pub tt_hits: u64,
+    /// depth the returned move was searched to (see `mate_distance`)
+    pub depth: u8,
+}
+
+/// Best move and its score and the search counters
+pub fn search_root<K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    rng: &mut impl Rng,
+    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
+    repetition: &HashSet<BoardHash>,
+) -> (Option<(ChessMove, i16)>, SearchStats) {
+    // endgame tablebase already know the best move (a filtered move may be rejected, search then)
+    let probed = tablebase()
+        .filter(|_| options.root_filter.is_none())
+        .and_then(|tablebase| tablebase.best_move(board, options.halfmove_clock));
+    if let Some((mov, wdl)) = probed {
+        let score = wdl.score(0, -options.contempt);
+        return (Some((mov, score)), SearchStats::default());
+    }
+
+    let mut ctx = SearchContext::new(board, rng, cache, repetition);
+    ctx.apply_options(options);
+    let best = iterative_deepening(board, depth, options, &mut ctx);
+
+    let stats = SearchStats {
+        nodes: ctx.nodes,
+        tt_hits: ctx.tt_hits,
+        depth: ctx.completed_depth,
+    };
+    (best, stats)
+}
+
+/// Search `board` one more ply per iteration up to `depth`, the best move of the deepest
+/// completed iteration.
+///
+/// Each iteration fill the cache and search the previous best move first, which make the
+/// deeper iteration prune more (and give a move if cut off).
+fn iterative_deepening<'a, R: Rng, K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions<'a>,
+    ctx: &mut SearchContext<'a, R, K>,
+) -> Option<(ChessMove, i16)> {
+    // root need at least 1 ply to pick a move, child is searched at depth - 1
+    let depth = depth.max(1);
+    let start_depth = 1;
+
+    let deadline = options.time_budget.map(|budget| Instant::now() + budget);
+    let mut best: Option<(ChessMove, i16)> = None;
+    for iter_depth in start_depth..=depth {
+        let first_iteration = iter_depth == start_depth;
+        ctx.node_limit = options.node_limit.filter(|_| !first_iteration);
+        ctx.deadline = deadline.filter(|_| !first_iteration);
+        ctx.stop = options.stop.filter(|_| !first_iteration);
+
+        let previous_best = best.map(|(mov, _)| mov);
+        let iteration = match best {
+            Some((_, score)) if ctx.aspiration_windows && score.abs() < ASPIRATION_SCORE_LIMIT => {
+                aspiration_iteration(board, iter_depth, options, previous_best, score, ctx)
+            }
+            _ => root_iteration(board, iter_depth, options, previous_best, ctx),
+        };
+        match iteration {
+            Some(result) => {
+                best = result;
+                ctx.completed_depth = iter_depth;
+            }
+            None => break,
+        }
+    }
+    best
+}
+
+/// Search every root move to `depth` (`first` before the others),
+/// None if the search was cut off before finishing
+fn root_iteration<R: Rng, K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    first: Option<ChessMove>,
+    ctx: &mut SearchContext<R, K>,
+) -> Option<Option<(ChessMove, i16)>> {
+    // don't use i16::MIN! it will overflow on negation
+    root_window(board, depth, options, first, -i16::MAX, i16::MAX, ctx)
+}
+
+/// Same as `root_iteration` but first search a narrow window around `guess`
+/// (the previous iteration score), which prune more when the score is stable.
+///
+/// A score outside the window is only a bound, the window is then widened and the
+/// iteration searched again until the score fall inside (at worst at full width).
+fn aspiration_iteration<R: Rng, K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    mut first: Option<ChessMove>,
+    guess: i16,
+    ctx: &mut SearchContext<R, K>,
+) -> Option<Option<(ChessMove, i16)>> {
+    let mut delta = ASPIRATION_WINDOW;
+    loop {
+        let a = guess.saturating_sub(delta).max(-i16::MAX);
+        let b = guess.saturating_add(delta);
+        match root_window(board, depth, options, first, a, b, ctx)? {
+            // fail low: every move is at most `a`, the move returned mean nothing
+            // so the previous best move stay first
+            Some((_, score)) if score <= a && a > -i16::MAX => {}
+            // fail high: this move refute the window, search it first
+            Some((mov, score)) if score >= b && b < i16::MAX => first = Some(mov),
+            result => return Some(result),
+        }
+        delta = delta.saturating_mul(2);
+    }
+}
+
+/// Search every root move within the window `(a, b)`, a score outside the window is a bound
+fn root_window<R: Rng, K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    first: Option<ChessMove>,
+    mut a: i16,
+    b: i16,
+    ctx: &mut SearchContext<R, K>,
+) -> Option<Option<(ChessMove, i16)>> {
+    let a_orig = a;
+    let path_draws = ctx.path_draws;
 
     // cache check doesn't provide move so it's unusable here
 
@@ -152,13 +982,29 @@ pub fn negamax_prelude<K: BuildHasher>(
     // side benefit: state <-> undo system
 
     // negamax
-    let child_nodes = MoveGen::new_legal(&board).map(|mov| (mov, board.make_move_new(mov)));
-
+    let mut root_moves = MoveGen::new_legal(board)
+        .filter(|&mov| options.root_filter.is_none_or(|allow| allow(board, mov)))
+        .collect::<Vec<_>>();
+    if let Some(index) = root_moves.iter().position(|&mov| Some(mov) == first) {
+        root_moves[..=index].rotate_right(1);
+    }
     let mut value = -i16::MAX;
     let mut best_mov = None;
 
-    for (mov, child) in child_nodes {
-        let node_eval = -negamax(&child, depth - 1, -b, -a, rng, cache, repetition);
+    // with the `parallel` feature only the first (expected best) move is searched here,
+    // the others are searched at once with its score as lower bound.
+    // Node limited search stay sequential so it is reproducible.
+    let sequential_count = if ctx.parallel_root && ctx.node_limit.is_none() {
+        1
+    } else {
+        root_moves.len()
+    };
+
+    for &mov in root_moves.iter().take(sequential_count) {
+        let node_eval = ctx.search_child(board, mov, depth - 1, a, b);
+        if ctx.out_of_budget() {
+            return None;
+        }
 
         if node_eval > value {
             value = node_eval;
@@ -171,176 +1017,532 @@ pub fn negamax_prelude<K: BuildHasher>(
         }
     }
 
-    // Cache store (will be exact since this is top layer)
-    let new_entry = TranspositionItem {
-        score: BoundedScore::Exact(value),
-        depth,
-    };
-    cache.put(BoardHash::new(board), new_entry);
+    #[cfg(feature = "parallel")]
+    if a < b && root_moves.len() > sequential_count {
+        let others = &root_moves[sequential_count..];
+        for (mov, node_eval) in search_moves_parallel(board, depth, others, a, b, ctx)? {
+            if node_eval > value {
+                value = node_eval;
+                best_mov = Some(mov);
+            }
+        }
+    }
 
-    // Returning
-    if best_mov.is_none() {
-        println!("\nNone End: {}", board);
+    // Cache store (exact unless the score fell outside the window), a filtered root only
+    // scored some of its moves and a root without any searched move has no score at all
+    let complete =
+        options.root_filter.is_none() && best_mov.is_some() && ctx.path_draws == path_draws;
+    if complete && (value > a_orig && value < b || a_orig == -i16::MAX && b == i16::MAX) {
+        let new_entry = TranspositionItem::new(BoundedScore::Exact(value), depth, board);
+        ctx.cache.put(BoardHash::new(board), new_entry);
+    }
+
+    Some(best_mov.map(|mov| (mov, value)))
+}
+
+/// Search root `moves` in parallel (one rayon task per move) within the window `(a, b)`,
+/// None if the search was cut off before finishing.
+///
+/// Each task get its own copy of `ctx.cache` (bounded the same), afterward an even share of
+/// their most recent entries is merged back into `ctx.cache` (keeping the deeper entry when
+/// both have one), and its own RNG seeded from `ctx.rng`.
+#[cfg(feature = "parallel")]
+fn search_moves_parallel<R: Rng, K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    moves: &[ChessMove],
+    a: i16,
+    b: i16,
+    ctx: &mut SearchContext<R, K>,
+) -> Option<Vec<(ChessMove, i16)>> {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rayon::prelude::*;
+
+    let mut rngs = moves
+        .iter()
+        .map(|_| StdRng::seed_from_u64(ctx.rng.gen()))
+        .collect::<Vec<_>>();
+    // resized rather than `LruCache::new`, which would allocate the whole capacity for every move
+    let mut caches = moves
+        .iter()
+        .map(|_| {
+            let mut cache = LruCache::unbounded();
+            cache.resize(ctx.cache.cap());
+            // least recent first so the copy keep the same order
+            for (hash, item) in ctx.cache.iter().rev() {
+                cache.put(*hash, item.clone());
+            }
+            cache
+        })
+        .collect::<Vec<_>>();
+    let workers = rngs
+        .iter_mut()
+        .zip(caches.iter_mut())
+        .map(|(rng, cache)| ctx.fork(rng, cache))
+        .collect::<Vec<_>>();
+
+    let results = workers
+        .into_par_iter()
+        .zip(moves.par_iter())
+        .map(|(mut worker, &mov)| {
+            let score = worker.search_child(board, mov, depth - 1, a, b);
+            let stats = SearchStats {
+                nodes: worker.nodes,
+                tt_hits: worker.tt_hits,
+                ..Default::default()
+            };
+            (
+                mov,
+                score,
+                stats,
+                worker.path_draws,
+                worker.quiescence_reached,
+                worker.past_deadline,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // every worker may have filled a whole cache, merged in full they would only evict each other
+    let share = (ctx.cache.cap() / moves.len()).max(1);
+    for mut cache in caches {
+        cache.resize(share);
+        merge_cache(ctx.cache, cache);
     }
 
-    best_mov.map(|mov| (mov, value))
+    let mut scores = Vec::with_capacity(results.len());
+    for (mov, score, stats, path_draws, quiescence_reached, past_deadline) in results {
+        ctx.nodes += stats.nodes;
+        ctx.tt_hits += stats.tt_hits;
+        ctx.path_draws += path_draws;
+        ctx.quiescence_reached = ctx.quiescence_reached.max(quiescence_reached);
+        ctx.past_deadline |= past_deadline;
+        scores.push((mov, score));
+    }
+    if ctx.out_of_budget() {
+        return None;
+    }
+    Some(scores)
 }
 
+/// Move every entry of `from` into `into`, the deeper entry win when both have the position
+pub fn merge_cache<K: BuildHasher, K2: BuildHasher>(
+    into: &mut LruCache<BoardHash, TranspositionItem, K>,
+    mut from: LruCache<BoardHash, TranspositionItem, K2>,
+) {
+    while let Some((hash, item)) = from.pop_lru() {
+        if into.peek(&hash).is_none_or(|old| old.depth <= item.depth) {
+            into.put(hash, item);
+        }
+    }
+}
+
+/// Best and 2nd best move of `ranked_moves`
 pub fn negamax_prelude_2nd<K: BuildHasher>(
     board: &Board,
     depth: u8,
+    options: &SearchOptions,
     rng: &mut impl Rng,
     cache: &mut LruCache<BoardHash, TranspositionItem, K>,
-    repetition: &HashSet<BoardHash>
+    repetition: &HashSet<BoardHash>,
 ) -> [Option<(ChessMove, i16)>; 2] {
-    // var initialization
-    let mut a = -i16::MAX; // don't use i16::MIN! it will overflow on negation
-    let b = i16::MAX;
+    let mut ranked = ranked_moves(board, depth, options, rng, cache, repetition).into_iter();
+    [ranked.next(), ranked.next()]
+}
 
-    // cache check doesn't provide move so it's unusable here
+/// Choose between the best and 2nd best move of `negamax_prelude_2nd`: the 2nd best move unless
+/// it is more than `margin` centipawn worse than the best move (or there is none).
+/// The flag is true when falling back to the best move because of the margin.
+pub fn second_best_within(
+    ranked: [Option<(ChessMove, i16)>; 2],
+    margin: Option<i16>,
+) -> (Option<(ChessMove, i16)>, bool) {
+    match ranked {
+        [Some(best), Some(second)]
+            if margin.is_some_and(|margin| best.1.saturating_sub(second.1) > margin) =>
+        {
+            (Some(best), true)
+        }
+        [best, None] => (best, false),
+        [_, second] => (second, false),
+    }
+}
 
-    // negamax
-    let child_nodes = MoveGen::new_legal(&board).map(|mov| (mov, board.make_move_new(mov)));
+/// Search every legal move with full window (so every score is exact, not just a bound)
+/// and return them from best to worst, see `cmp_ranked_move` for the ordering.
+///
+/// Only the per node settings of `options` apply (see `SearchContext::apply_options`),
+/// every move is searched to the end.
+pub fn ranked_moves<K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    rng: &mut impl Rng,
+    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
+    repetition: &HashSet<BoardHash>,
+) -> Vec<(ChessMove, i16)> {
+    let depth = depth.max(1);
+    let mut ctx = SearchContext::new(board, rng, cache, repetition);
+    ctx.apply_options(options);
+    let mut ranked = MoveGen::new_legal(board)
+        .map(|mov| {
+            let node_eval = ctx.search_child(board, mov, depth - 1, -i16::MAX, i16::MAX);
+            (mov, node_eval)
+        })
+        .collect::<Vec<_>>();
 
-    let mut value = -i16::MAX;
-    let mut best_mov = None;
+    ranked.sort_by(|x, y| cmp_ranked_move(board, x, y));
+    ranked
+}
 
-    let mut value_2nd = -i16::MAX;
-    let mut best_mov_2nd = None;
+/// Uniformly random legal move, ignoring search entirely (weakest difficulty)
+pub fn random_move(board: &Board, rng: &mut impl Rng) -> Option<ChessMove> {
+    MoveGen::new_legal(board).choose(rng)
+}
 
-    for (mov, child) in child_nodes {
-        let node_eval = -negamax(&child, depth - 1, -b, -a, rng, cache, repetition);
+/// Search score of playing `mov` on `board` from the perspective of the side making the move,
+/// the resulting position is searched to `depth - 1`.
+///
+/// Return None if the move is illegal.
+pub fn evaluate_move<K: BuildHasher>(
+    board: &Board,
+    mov: ChessMove,
+    depth: u8,
+    rng: &mut impl Rng,
+    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
+    repetition: &HashSet<BoardHash>,
+) -> Option<i16> {
+    if !board.legal(mov) {
+        return None;
+    }
 
-        if node_eval > value {
-            value = node_eval;
-            best_mov = Some(mov);
-        } else if node_eval == value {
-            value_2nd = value;
-            best_mov_2nd = Some(mov);
-        }
+    let mut ctx = SearchContext::new(board, rng, cache, repetition);
+    Some(ctx.search_child(board, mov, depth.saturating_sub(1), -i16::MAX, i16::MAX))
+}
 
-        a = i16::max(a, value);
+/// Total ordering of searched moves, better move come first.
+///
+/// Primary key is the score, equal score are ordered by MVV-LVA then by
+/// source square, destination square and promotion piece,
+/// so the order is reproducible as long as the search is deterministic.
+pub fn cmp_ranked_move(board: &Board, x: &(ChessMove, i16), y: &(ChessMove, i16)) -> Ordering {
+    let (x_mov, x_score) = x;
+    let (y_mov, y_score) = y;
+
+    y_score
+        .cmp(x_score)
+        .then_with(|| mvv_lva(board, *y_mov).cmp(&mvv_lva(board, *x_mov)))
+        .then_with(|| x_mov.cmp(y_mov))
+}
 
-        if a >= b {
-            break;
-        }
-    }
+/// Most Valuable Victim - Least Valuable Aggressor, a greater key is a more promising capture
+/// (victim value first, then the cheaper aggressor), None for non-capture move
+fn mvv_lva(board: &Board, mov: ChessMove) -> Option<(i16, Reverse<i16>)> {
+    let victim = board.piece_on(mov.get_dest())?;
+    let aggressor = board.piece_on(mov.get_source())?;
+    Some((
+        PIECE_VALUE[victim.to_index()],
+        Reverse(PIECE_VALUE[aggressor.to_index()]),
+    ))
+}
 
-    // Cache store (will be exact since this is top layer)
-    let new_entry = TranspositionItem {
-        score: BoundedScore::Exact(value),
-        depth,
-    };
-    cache.put(BoardHash::new(board), new_entry);
+/// Move in a game which lose a lot of advantage compared to the best move available
+pub struct Blunder {
+    /// number of move played before the blunder
+    pub ply: usize,
+    pub mov: ChessMove,
+    pub best_mov: ChessMove,
+    /// centipawn lost from the blundering side perspective
+    pub loss: i16,
+}
 
-    // Returning
-    if best_mov.is_none() {
-        println!("\nNone End: {}", board);
+/// Search every position of a game and report moves that are worse than the best move
+/// by at least `threshold` centipawn
+pub fn find_blunders<K: BuildHasher>(
+    base_board: &Board,
+    moves: &[ChessMove],
+    depth: u8,
+    threshold: i16,
+    rng: &mut impl Rng,
+    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
+) -> Vec<Blunder> {
+    let depth = depth.max(1);
+    let no_repetition = HashSet::new();
+
+    let mut blunders = Vec::new();
+    let mut board = *base_board;
+
+    for (ply, &mov) in moves.iter().enumerate() {
+        let next_board = board.make_move_new(mov);
+
+        if let Some((best_mov, best_score)) =
+            negamax_prelude(&board, depth, rng, cache, &no_repetition)
+        {
+            let played_score = evaluate_move(&board, mov, depth, rng, cache, &no_repetition)
+                .expect("game move should be legal");
+
+            let loss = best_score.saturating_sub(played_score);
+            if loss >= threshold {
+                blunders.push(Blunder {
+                    ply,
+                    mov,
+                    best_mov,
+                    loss,
+                });
+            }
+        }
+
+        board = next_board;
     }
 
-    [
-        best_mov.map(|mov| (mov, value)),
-        best_mov_2nd.map(|mov| (mov, value_2nd)),
-    ]
+    blunders
 }
 
-fn stats_eval_fn(stats: BoardStatus, color_index: i8, depth: u8) -> i16 {
-    const CHECKMATE_SCORE: i16 = 20000; // base score when checkmated
-                                        // additional score for each depth when checkmated to encourage faster checkmate
-                                        //
-                                        // This should be large enough to compensate pieces value
-                                        // else AI won't do sacrifice for checkmate and AI will prefer eating all the enemy pieces than fast win
-                                        // which presumably we don't want
-                                        // beware of limit of i16 (max 32767)
-                                        // DEPTH * CHECKMATE_DEPTH_SCORE shall never exceed 8000
-                                        // else overflow will happen
-    const CHECKMATE_DEPTH_SCORE: i16 = 500; // approximately rook
+/// Outcome of a standalone search, see `analyze`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: ChessMove,
+    /// side to move perspective
+    pub score: i16,
+    /// principal variation, starting with `best_move`
+    pub pv: Vec<ChessMove>,
+    /// number of node visited by the search (PV extraction not included)
+    pub nodes: u64,
+    /// number of transposition table hit during the search
+    pub tt_hits: u64,
+}
 
-    match stats {
-        BoardStatus::Ongoing => {
-            unreachable!("Ongoing game shouldn't be able to call this function")
-        }
-        BoardStatus::Stalemate => 0,
-        BoardStatus::Checkmate => {
-            color_index as i16 * -(CHECKMATE_SCORE + CHECKMATE_DEPTH_SCORE * depth as i16)
+/// Search `board` (reached after `halfmove_clock` ply without capture or pawn move) to `depth`
+/// with a fresh cache and fixed seed so the result is reproducible, None if there is no legal move
+pub fn analyze(board: &Board, halfmove_clock: u16, depth: u8) -> Option<SearchResult> {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let rng = &mut StdRng::seed_from_u64(0);
+    let mut cache = LruCache::new(crate::CACHE_SIZE);
+    let repetition = HashSet::new();
+    let options = SearchOptions {
+        halfmove_clock,
+        ..Default::default()
+    };
+
+    let (best, stats) = search_root(board, depth, &options, rng, &mut cache, &repetition);
+    let (best_move, score) = best?;
+    let pv = principal_variation(board, depth, &options, rng, &mut cache, &repetition)
+        .into_iter()
+        .map(|(mov, _)| mov)
+        .collect();
+
+    Some(SearchResult {
+        best_move,
+        score,
+        pv,
+        nodes: stats.nodes,
+        tt_hits: stats.tt_hits,
+    })
+}
+
+/// Principal variation of a `depth` ply search: best move of `board`, best reply to it and so on,
+/// each with the search score (side to move perspective) of the position it is played from.
+///
+/// The cache doesn't keep moves so every position along the line is searched again
+/// (cheap since the transposition table is already filled by the previous search).
+pub fn principal_variation<K: BuildHasher>(
+    board: &Board,
+    depth: u8,
+    options: &SearchOptions,
+    rng: &mut impl Rng,
+    cache: &mut LruCache<BoardHash, TranspositionItem, K>,
+    repetition: &HashSet<BoardHash>,
+) -> Vec<(ChessMove, i16)> {
+    let mut pv = Vec::new();
+    let mut board = *board;
+
+    for remaining in (1..=depth.max(1)).rev() {
+        match search_root(&board, remaining, options, rng, cache, repetition).0 {
+            Some((mov, score)) => {
+                pv.push((mov, score));
+                board = board.make_move_new(mov);
+            }
+            None => break,
         }
     }
+
+    pv
 }
 
-fn evaluation_fn(board: &Board, _rng: &mut impl Rng) -> i16 {
-    // this function is call after move simulation so board.side_to_move() == enemy side
-    // higher = better for white
+/// Table of the search score and static evaluation (both white perspective) of every position
+/// along `pv` played from `board`, with the evaluation components that changed since the
+/// previous position. Where the two scores diverge is where the line has tactical content.
+pub fn format_pv_trace(board: &Board, pv: &[(ChessMove, i16)]) -> String {
+    let mut text = format!(
+        "{:<4}{:<8}{:>8}{:>8}{:>8}  changed components\n",
+        "ply", "move", "search", "static", "diff"
+    );
+
+    let mut board = *board;
+    let mut prev_components = debug_evaluation(&board);
+    let rows = pv
+        .iter()
+        .map(|&(mov, score)| (Some(mov), Some(score)))
+        .chain(std::iter::once((None, None)));
+
+    for (ply, (mov, score)) in rows.enumerate() {
+        let static_eval = evaluate_white_pov(&board);
+        let search = score.map(|score| match board.side_to_move() {
+            Color::White => score,
+            Color::Black => -score,
+        });
+
+        let components = debug_evaluation(&board);
+        let changed = components
+            .iter()
+            .zip(&prev_components)
+            .map(|(now, prev)| {
+                (
+                    now.name,
+                    (now.white - now.black) - (prev.white - prev.black),
+                )
+            })
+            .filter(|&(_, delta)| delta != 0)
+            .map(|(name, delta)| format!("{}:{:+}", name, delta))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        text += &format!(
+            "{:<4}{:<8}{:>8}{:>8}{:>8}  {}\n",
+            ply,
+            mov.map_or("-".to_string(), |mov| mov.to_string()),
+            search.map_or("-".to_string(), |s| s.to_string()),
+            static_eval,
+            search.map_or("-".to_string(), |s| format!("{:+}", s - static_eval)),
+            changed
+        );
+
+        if let Some(mov) = mov {
+            board = board.make_move_new(mov);
+        }
+        prev_components = components;
+    }
 
-    // let tiny_noise = rng.gen_range(-1, 2);
-    evaluation_pieces_worth_plus(board)
+    text
 }
 
-fn evaluation_count_pieces(board: &Board) -> f64 {
-    let white_pieces_count = f64::from(board.color_combined(Color::White).popcnt());
-    let black_pieces_count = f64::from(board.color_combined(Color::Black).popcnt());
-    white_pieces_count - black_pieces_count
+/// Estimate chance of winning (0.0 to 1.0) from centipawn score using logistic curve,
+/// `scale` is the score at which the winning chance is 10 to 1
+pub fn win_probability(score: i16, scale: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-f64::from(score) / scale))
 }
 
-fn evaluation_reverse_count_pieces(board: &Board) -> f64 {
-    let white_pieces_count = f64::from(board.color_combined(Color::White).popcnt());
-    let black_pieces_count = f64::from(board.color_combined(Color::Black).popcnt());
-    black_pieces_count - white_pieces_count
+/// Capture and pawn move can't be undone, they reset the fifty-move rule count
+pub fn resets_halfmove_clock(board: &Board, mov: ChessMove) -> bool {
+    board.piece_on(mov.get_source()) == Some(Piece::Pawn)
+        || board.piece_on(mov.get_dest()).is_some()
 }
 
-fn evaluation_pawn_motion(board: &Board) -> f64 {
-    let white = board.color_combined(Color::White);
-    let black = board.color_combined(Color::Black);
-    let pawn = board.pieces(Piece::Pawn);
+/// Neither side can possibly checkmate: K vs K, K + minor piece vs K
+/// and K + B vs K + B with bishops on the same square color
+pub fn is_insufficient_material(board: &Board) -> bool {
+    const DARK_SQUARES: BitBoard = BitBoard(0xAA55_AA55_AA55_AA55);
 
-    let white_pwn = (pawn & white)
-        .map(|sq| sq.get_rank().to_index())
-        .sum::<usize>() as f64;
-    let black_pwn = (pawn & black)
-        .map(|sq| sq.get_rank().to_index())
-        .sum::<usize>() as f64;
+    let pawn_or_heavy =
+        board.pieces(Piece::Pawn) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
+    if pawn_or_heavy != EMPTY {
+        return false;
+    }
 
-    white_pwn - black_pwn
+    let knight = *board.pieces(Piece::Knight);
+    let bishop = *board.pieces(Piece::Bishop);
+    match (knight | bishop).popcnt() {
+        0 | 1 => true,
+        2 => {
+            let one_bishop_each =
+                knight == EMPTY && (bishop & board.color_combined(Color::White)).popcnt() == 1;
+            let dark_bishop = (bishop & DARK_SQUARES).popcnt();
+            one_bishop_each && dark_bishop != 1
+        }
+        _ => false,
+    }
 }
 
-fn evaluation_freedom(board: &Board) -> f64 {
-    MoveGen::new_legal(&board)
-        .map(|mov| {
-            let moving_color = board.color_on(mov.get_source()).unwrap();
-            match moving_color {
-                Color::White => 1.0,
-                Color::Black => -1.0,
-            }
-        })
-        .sum()
+// base score when checkmated
+const CHECKMATE_SCORE: i16 = 20000;
+// additional score for each depth when checkmated to encourage faster checkmate
+//
+// This should be large enough to compensate pieces value
+// else AI won't do sacrifice for checkmate and AI will prefer eating all the enemy pieces than fast win
+// which presumably we don't want
+// beware of limit of i16 (max 32767)
+// DEPTH * CHECKMATE_DEPTH_SCORE shall never exceed 8000
+// else overflow will happen
+const CHECKMATE_DEPTH_SCORE: i16 = 500; // approximately rook
+
+/// Number of moves until checkmate of `score`, the score of a search to `depth`:
+/// positive when the side the score is for mates, negative when it gets mated,
+/// None when the score isn't a checkmate
+pub fn mate_distance(score: i16, depth: u8) -> Option<i16> {
+    if score.abs() < CHECKMATE_SCORE {
+        return None;
+    }
+    // the mated side scored `CHECKMATE_DEPTH_SCORE` for each depth left
+    let depth_left = (score.abs() - CHECKMATE_SCORE) / CHECKMATE_DEPTH_SCORE;
+    // a deeper cached score may claim more depth left than searched
+    let ply = (i16::from(depth) - depth_left).max(1);
+    Some(score.signum() * ((ply + 1) / 2))
 }
 
-fn evaluation_pieces_worth(board: &Board) -> f64 {
-    let white = board.color_combined(Color::White);
-    let black = board.color_combined(Color::Black);
+fn stats_eval_fn(stats: BoardStatus, color_index: i8, depth: u8) -> i16 {
+    match stats {
+        BoardStatus::Ongoing => {
+            unreachable!("Ongoing game shouldn't be able to call this function")
+        }
+        BoardStatus::Stalemate => 0,
+        BoardStatus::Checkmate => {
+            color_index as i16 * -(CHECKMATE_SCORE + CHECKMATE_DEPTH_SCORE * depth as i16)
+        }
+    }
+}
 
-    let pawn = board.pieces(Piece::Pawn);
-    let bishop = board.pieces(Piece::Bishop);
-    let rook = board.pieces(Piece::Rook);
-    let knight = board.pieces(Piece::Knight);
-    let queen = board.pieces(Piece::Queen);
+fn evaluation_fn<R: Rng>(board: &Board, rng: &mut R, noise: i16) -> i16 {
+    // this function is call after move simulation so board.side_to_move() == enemy side
+    // higher = better for white
 
-    let delta_pawn = f64::from((pawn & white).popcnt()) - f64::from((pawn & black).popcnt());
-    let delta_rook = f64::from((rook & white).popcnt()) - f64::from((rook & black).popcnt());
-    let delta_queen = f64::from((queen & white).popcnt()) - f64::from((queen & black).popcnt());
-    let delta_bishop = f64::from((bishop & white).popcnt()) - f64::from((bishop & black).popcnt());
-    let delta_knight = f64::from((knight & white).popcnt()) - f64::from((knight & black).popcnt());
+    let tiny_noise = if noise > 0 {
+        rng.gen_range(-noise..=noise)
+    } else {
+        0
+    };
+    evaluate_white_pov(board).saturating_add(tiny_noise)
+}
 
-    9.0 * delta_queen + 5.0 * delta_rook + 3.0 * (delta_bishop + delta_knight) + 1.0 * delta_pawn
+/// Seeded RNG so search tests are reproducible (no `thread_rng` in tests)
+#[cfg(test)]
+pub(crate) fn test_rng() -> rand::rngs::StdRng {
+    use rand::SeedableRng;
+    rand::rngs::StdRng::seed_from_u64(0x5EED)
 }
-/*
+
 #[cfg(test)]
 mod tests {
 
-    use super::negamax_prelude;
-    use chess::{Board, ChessMove, Color, File, Rank, Square};
+    use super::main_evalation::{evaluate_stm, evaluate_white_pov};
+    use super::{
+        analyze, aspiration_iteration, evaluate_move, evaluation_fn, find_blunders,
+        format_pv_trace, is_insufficient_material, iterative_deepening, mvv_lva, negamax,
+        negamax_prelude, negamax_prelude_2nd, negamax_prelude_with, principal_variation,
+        quiescence, random_move, ranked_moves, root_iteration, search_root, second_best_within,
+        test_rng, win_probability, BoardHash, RepetitionTracker, SearchContext, SearchOptions,
+        TranspositionItem, DEFAULT_QUIESCENCE_DEPTH, MAX_PRESS_SECOND_BEST_CHANCE, MAX_TIMED_DEPTH,
+    };
+    use crate::chess_notation::fen_halfmove_clock;
+    use chess::{Board, BoardStatus, ChessMove, Color, File, MoveGen, Rank, Square};
     use lru::LruCache;
-    use rand::thread_rng;
+    use rand::Rng;
+    use std::cmp::Reverse;
+    use std::collections::{HashMap, HashSet};
     use std::str::FromStr;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
 
     fn build_move(file1: File, rank1: Rank, file2: File, rank2: Rank) -> ChessMove {
         ChessMove::new(
@@ -350,6 +1552,26 @@ mod tests {
         )
     }
 
+    type TestContext<'a> = SearchContext<'a, rand::rngs::StdRng, lru::DefaultHasher>;
+
+    /// Best move and score of an iterative deepening search of `board` to `depth` with a fresh
+    /// cache and the root searched sequentially, and the number of nodes it visited.
+    /// `setup` turn a search feature off (or on) first.
+    fn nodes_with(
+        board: &Board,
+        depth: u8,
+        setup: impl FnOnce(&mut TestContext),
+    ) -> ((ChessMove, i16), u64) {
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(100_000);
+        let repetition = HashSet::new();
+        let mut ctx = SearchContext::new(board, rng, &mut cache, &repetition);
+        ctx.parallel_root = false;
+        setup(&mut ctx);
+        let best = iterative_deepening(board, depth, &SearchOptions::default(), &mut ctx);
+        (best.unwrap(), ctx.nodes)
+    }
+
     #[test]
     fn test_who_good() {
         let question = [
@@ -363,17 +1585,1060 @@ mod tests {
             ),
         ];
 
-        let rng = &mut thread_rng();
+        let rng = &mut test_rng();
 
         for (fen, answer) in question.iter() {
             let board = Board::from_str(fen).unwrap();
             let player = board.side_to_move();
             let mut cache = LruCache::new(64);
-            let (_, score) = negamax_prelude(&board, 5, rng, &mut cache).unwrap();
+            // the answers are what the search see without quiescence search,
+            // with it the first position is still better for White at this depth
+            let options = SearchOptions {
+                quiescence_depth: 0,
+                ..Default::default()
+            };
+            let (_, score) =
+                negamax_prelude_with(&board, 5, &options, rng, &mut cache, &HashSet::new())
+                    .unwrap();
 
             let guess = if score > 0 { player } else { !player };
 
             assert_eq!(guess, *answer);
         }
     }
-}*/
+
+    #[test]
+    fn test_seeded_entry_is_hit() {
+        let board = Board::default();
+        let e4 = build_move(File::E, Rank::Second, File::E, Rank::Fourth);
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+
+        // absurd score no real search would return, from black (side to move) perspective
+        let after_e4 = board.make_move_new(e4);
+        cache.put(
+            BoardHash::new(&after_e4),
+            TranspositionItem::exact(-5000, 20),
+        );
+
+        let score = evaluate_move(&board, e4, 3, rng, &mut cache, &HashSet::new()).unwrap();
+        assert_eq!(score, 5000);
+    }
+
+    #[test]
+    fn test_random_move() {
+        let rng = &mut test_rng();
+
+        let board = Board::default();
+        let moves = (0..200)
+            .map(|_| random_move(&board, rng).unwrap())
+            .collect::<HashSet<_>>();
+        assert!(moves.iter().all(|mov| board.legal(*mov)));
+        // 20 legal moves, 200 draws should hit most of them
+        assert!(moves.len() > 10);
+
+        let checkmated = Board::from_str("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(random_move(&checkmated, rng), None);
+    }
+
+    #[test]
+    fn test_node_limit() {
+        // lots of legal moves for both side
+        let board =
+            Board::from_str("r3k2r/pppq1ppp/2nbbn2/3pp3/3PP3/2NBBN2/PPPQ1PPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let options = SearchOptions {
+            node_limit: Some(5_000),
+            ..Default::default()
+        };
+
+        let (best, stats) = search_root(&board, 20, &options, rng, &mut cache, &HashSet::new());
+
+        assert!(board.legal(best.unwrap().0));
+        assert!(stats.nodes <= 5_000);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let board =
+            Board::from_str("r3k2r/pppq1ppp/2nbbn2/3pp3/3PP3/2NBBN2/PPPQ1PPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let options = SearchOptions {
+            time_budget: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let (best, _) = search_root(
+            &board,
+            MAX_TIMED_DEPTH,
+            &options,
+            rng,
+            &mut cache,
+            &HashSet::new(),
+        );
+
+        assert!(board.legal(best.unwrap().0));
+        // far from the time a full depth 16 search would take
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_stop_flag() {
+        let board =
+            Board::from_str("r3k2r/pppq1ppp/2nbbn2/3pp3/3PP3/2NBBN2/PPPQ1PPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let stop = AtomicBool::new(true);
+        let options = SearchOptions {
+            stop: Some(&stop),
+            ..Default::default()
+        };
+
+        // already stopped, only the first iteration is searched
+        let (best, stats) = search_root(
+            &board,
+            MAX_TIMED_DEPTH,
+            &options,
+            rng,
+            &mut cache,
+            &HashSet::new(),
+        );
+        assert!(board.legal(best.unwrap().0));
+        assert!(stats.nodes < 5_000);
+    }
+
+    #[test]
+    fn test_node_limit_smaller_than_first_iteration() {
+        let board = Board::default();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let options = SearchOptions {
+            node_limit: Some(1),
+            ..Default::default()
+        };
+
+        let (best, _) = search_root(&board, 5, &options, rng, &mut cache, &HashSet::new());
+        assert!(board.legal(best.unwrap().0));
+    }
+
+    #[test]
+    fn test_root_filter() {
+        // taking the undefended queen is clearly best
+        let board = Board::from_str("k7/8/8/3q4/8/8/8/K2R4 w - - 0 1").unwrap();
+        let take_queen = build_move(File::D, Rank::First, File::D, Rank::Fifth);
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+
+        let (best, _) = search_root(
+            &board,
+            3,
+            &SearchOptions::default(),
+            rng,
+            &mut cache,
+            &HashSet::new(),
+        );
+        assert_eq!(best.unwrap().0, take_queen);
+
+        let avoid_take = |_: &Board, mov: ChessMove| mov != take_queen;
+        let options = SearchOptions {
+            root_filter: Some(&avoid_take),
+            ..Default::default()
+        };
+        let mut cache = LruCache::new(1024);
+        let (best, _) = search_root(&board, 3, &options, rng, &mut cache, &HashSet::new());
+        let (mov, score) = best.unwrap();
+        assert_ne!(mov, take_queen);
+        assert!(board.legal(mov));
+        assert!(score < 0);
+
+        let reject_all = |_: &Board, _: ChessMove| false;
+        let options = SearchOptions {
+            root_filter: Some(&reject_all),
+            ..Default::default()
+        };
+        let (best, _) = search_root(&board, 3, &options, rng, &mut cache, &HashSet::new());
+        assert!(best.is_none());
+        // neither restricted score may be read back by an unfiltered search
+        assert!(cache.peek(&BoardHash::new(&board)).is_none());
+    }
+
+    #[test]
+    fn test_depth_zero_at_root() {
+        let board = Board::default();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let repetition = HashSet::new();
+
+        let (mov, _) = negamax_prelude(&board, 0, rng, &mut cache, &repetition).unwrap();
+        assert!(board.legal(mov));
+
+        let ranked = ranked_moves(
+            &board,
+            0,
+            &SearchOptions::default(),
+            rng,
+            &mut cache,
+            &repetition,
+        );
+        assert_eq!(ranked.len(), 20);
+    }
+
+    #[test]
+    fn test_find_blunders() {
+        let board = Board::from_str("k7/8/8/3p4/7Q/8/8/K7 w - - 0 1").unwrap();
+        // queen walk into pawn capture, pawn take it
+        let moves = [
+            build_move(File::H, Rank::Fourth, File::E, Rank::Fourth),
+            build_move(File::D, Rank::Fifth, File::E, Rank::Fourth),
+        ];
+
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let blunders = find_blunders(&board, &moves, 2, 150, rng, &mut cache);
+
+        assert_eq!(blunders.len(), 1);
+        assert_eq!(blunders[0].ply, 0);
+        assert_eq!(blunders[0].mov, moves[0]);
+        assert!(blunders[0].loss >= 800);
+    }
+
+    #[test]
+    fn test_evaluate_move() {
+        // rook can take the undefended queen or be left hanging to it
+        let board = Board::from_str("k7/8/8/3q4/8/8/8/K2R4 w - - 0 1").unwrap();
+        let take_queen = build_move(File::D, Rank::First, File::D, Rank::Fifth);
+        let rook_aside = build_move(File::D, Rank::First, File::H, Rank::First);
+        let illegal = build_move(File::D, Rank::First, File::E, Rank::Second);
+
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let repetition = HashSet::new();
+        let mut evaluate = |mov| evaluate_move(&board, mov, 3, rng, &mut cache, &repetition);
+
+        let take_score = evaluate(take_queen).unwrap();
+        let aside_score = evaluate(rook_aside).unwrap();
+        assert!(take_score > 0);
+        assert!(take_score - aside_score >= 800);
+        assert_eq!(evaluate(illegal), None);
+    }
+
+    #[test]
+    fn test_ranked_moves_deterministic() {
+        // many equal score moves for both king and rook
+        let board = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let rng = &mut test_rng();
+
+        let mut search = || {
+            let mut cache = LruCache::new(1024);
+            ranked_moves(
+                &board,
+                2,
+                &SearchOptions::default(),
+                rng,
+                &mut cache,
+                &HashSet::new(),
+            )
+        };
+
+        let first = search();
+        assert!(first.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        for _ in 0..3 {
+            assert_eq!(search(), first);
+        }
+    }
+
+    #[test]
+    fn test_ranked_moves_options() {
+        // rook up, but the next move complete the fifty moves
+        let board = Board::from_str("8/8/8/4k3/8/8/8/R3K3 w - - 99 80").unwrap();
+        let rng = &mut test_rng();
+        let mut rank = |options: &SearchOptions| {
+            ranked_moves(
+                &board,
+                2,
+                options,
+                rng,
+                &mut LruCache::new(1024),
+                &HashSet::new(),
+            )
+        };
+
+        assert!(rank(&SearchOptions::default())[0].1 > 300);
+        let options = SearchOptions {
+            halfmove_clock: 99,
+            contempt: 20,
+            ..Default::default()
+        };
+        // every move is a draw, worth less than equal with contempt
+        assert!(rank(&options).iter().all(|&(_, score)| score == -20));
+    }
+
+    #[test]
+    fn test_win_probability() {
+        assert!((win_probability(0, 400.0) - 0.5).abs() < 1e-9);
+        assert!((win_probability(400, 400.0) - 10.0 / 11.0).abs() < 1e-9);
+        assert!((win_probability(-250, 400.0) + win_probability(250, 400.0) - 1.0).abs() < 1e-9);
+        // smaller scale, more confident
+        assert!(win_probability(100, 200.0) > win_probability(100, 400.0));
+    }
+
+    #[test]
+    fn test_repetition_in_search_is_draw() {
+        // black is up material, but white can force perpetual check with Qe8+ Kh7 Qh5+ Kg8
+        // nothing was captured nor any pawn moved for a while
+        let fen = "6k1/6p1/ppp5/7Q/8/rr6/5PPP/n5K1 w - - 20 40";
+        let board = Board::from_str(fen).unwrap();
+
+        let result = analyze(&board, fen_halfmove_clock(fen), 5).unwrap();
+
+        assert_eq!(
+            result.best_move,
+            build_move(File::H, Rank::Fifth, File::E, Rank::Eighth)
+        );
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn test_fortress_is_draw() {
+        // white is two (doubled) pawns up but every pawn is blocked, both bishops are
+        // trapped behind their own pawns and both kings can only shuffle between two squares
+        let fen = "k1b5/1p1p4/1P1P4/1P1P4/8/4p1p1/4P1P1/5B1K w - - 30 60";
+        let board = Board::from_str(fen).unwrap();
+
+        // right after a pawn move a repetition isn't trusted, the static advantage stays
+        assert!(analyze(&board, 0, 6).unwrap().score > 100);
+        // after many ply without progress shuffling back is scored as a draw
+        assert_eq!(
+            analyze(&board, fen_halfmove_clock(fen), 6).unwrap().score,
+            0
+        );
+    }
+
+    #[test]
+    fn test_game_repetition_saves_lost_position() {
+        // same lost position as above, but the check already happened twice in the game
+        // so repeating it is a threefold draw, seen even by a 1 ply search
+        let board = Board::from_str("6k1/6p1/ppp5/7Q/8/rr6/5PPP/n5K1 w - - 0 1").unwrap();
+        let check = build_move(File::H, Rank::Fifth, File::E, Rank::Eighth);
+        let mut repetition = HashSet::new();
+        repetition.insert(BoardHash::new(&board.make_move_new(check)));
+
+        let rng = &mut test_rng();
+        let (_, lost_score) =
+            negamax_prelude(&board, 1, rng, &mut LruCache::new(1024), &HashSet::new()).unwrap();
+        assert!(lost_score < -300);
+
+        let (mov, score) =
+            negamax_prelude(&board, 1, rng, &mut LruCache::new(1024), &repetition).unwrap();
+        assert_eq!(mov, check);
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let insufficient = |fen: &str| is_insufficient_material(&Board::from_str(fen).unwrap());
+
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/8/3KB3/8/8 w - - 0 1"));
+        assert!(insufficient("8/8/4kn2/8/8/3K4/8/8 w - - 0 1"));
+        // bishops on c1 and f4 are both on dark squares
+        assert!(insufficient("8/8/4k3/8/5b2/3K4/8/2B5 w - - 0 1"));
+
+        assert!(!insufficient("8/8/4k3/8/4b3/3K4/8/2B5 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3KNN2/8/8 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1"));
+        assert!(!insufficient("8/8/4kn2/8/8/3KB3/8/8 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_insufficient_material_is_draw_in_search() {
+        // a bishop up, but nothing left that can checkmate
+        let board = Board::from_str("8/8/4k3/8/8/3KB3/8/8 w - - 0 1").unwrap();
+        let (_, score) = negamax_prelude(
+            &board,
+            3,
+            &mut test_rng(),
+            &mut LruCache::new(1024),
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(score, 0);
+
+        // black save the game by taking the last pawn
+        let board = Board::from_str("8/8/8/4k3/3P4/8/8/K6B b - - 0 1").unwrap();
+        let (mov, score) = negamax_prelude(
+            &board,
+            2,
+            &mut test_rng(),
+            &mut LruCache::new(1024),
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(mov, ChessMove::new(Square::E5, Square::D4, None));
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn test_repetition_tracker_halfmove_clock() {
+        let mut tracker = RepetitionTracker::default();
+        tracker.set_halfmove_clock(10);
+        let mut board = Board::default();
+        for mov in ["g1f3", "g8f6", "e2e4"].iter() {
+            let mov = ChessMove::from_str(mov).unwrap();
+            tracker.push(&board, mov);
+            board = board.make_move_new(mov);
+            if tracker.len() == 2 {
+                assert_eq!(tracker.halfmove_clock(), 12);
+            }
+        }
+        // pawn move reset it
+        assert_eq!(tracker.halfmove_clock(), 0);
+
+        // taking back restore the clock down to the starting one
+        tracker.pop();
+        assert_eq!(tracker.halfmove_clock(), 12);
+        tracker.truncate(0);
+        assert_eq!(tracker.halfmove_clock(), 10);
+    }
+
+    #[test]
+    fn test_repetition_tracker_long_shuffling_game() {
+        // both side shuffle their knight back and forth
+        let shuffle = [
+            build_move(File::G, Rank::First, File::F, Rank::Third),
+            build_move(File::G, Rank::Eighth, File::F, Rank::Sixth),
+            build_move(File::F, Rank::Third, File::G, Rank::First),
+            build_move(File::F, Rank::Sixth, File::G, Rank::Eighth),
+        ];
+
+        let mut tracker = RepetitionTracker::default();
+        let mut board = Board::default();
+        for mov in shuffle.iter().cycle().take(10_000) {
+            tracker.push(&board, *mov);
+            board = board.make_move_new(*mov);
+        }
+
+        assert_eq!(tracker.len(), 10_000);
+        assert_eq!(tracker.distinct_positions(), 4);
+        assert_eq!(tracker.repeated().len(), 4);
+
+        // undo back to before anything repeated
+        tracker.truncate(4);
+        assert_eq!(tracker.distinct_positions(), 4);
+        assert!(tracker.repeated().is_empty());
+
+        tracker.truncate(0);
+        assert_eq!(tracker.len(), 0);
+        assert_eq!(tracker.distinct_positions(), 0);
+    }
+
+    #[test]
+    fn test_quiescence_depth_cap() {
+        // every pawn can capture, both side can keep capturing for a long time
+        let board = Board::from_str("7k/8/8/1p1p1p1p/P1P1P1P1/8/8/K7 w - - 0 1").unwrap();
+        let repetition = HashSet::new();
+
+        for &cap in &[0, 2, 4] {
+            let rng = &mut test_rng();
+            let mut cache = LruCache::new(1024);
+            let mut ctx = SearchContext::new(&board, rng, &mut cache, &repetition);
+            ctx.quiescence_depth = cap;
+
+            quiescence(&board, 0, -i16::MAX, i16::MAX, &mut ctx);
+            assert_eq!(ctx.quiescence_reached, cap);
+        }
+    }
+
+    #[test]
+    fn test_quiescence_see_recapture() {
+        // Qxd5 win a pawn but the queen is lost to cxd5 right after
+        let board = Board::from_str("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let qxd5 = ChessMove::new(Square::D1, Square::D5, None);
+        let best_move = |quiescence_depth| {
+            let options = SearchOptions {
+                quiescence_depth,
+                ..Default::default()
+            };
+            let mut cache = LruCache::new(1024);
+            let (best, _) = search_root(
+                &board,
+                1,
+                &options,
+                &mut test_rng(),
+                &mut cache,
+                &HashSet::new(),
+            );
+            best.unwrap().0
+        };
+
+        assert_eq!(best_move(0), qxd5);
+        assert_ne!(best_move(DEFAULT_QUIESCENCE_DEPTH), qxd5);
+    }
+
+    #[test]
+    fn test_quiescence_hanging_recapture() {
+        // after Qxd5 white look a queen up statically, but cxd5 take the queen back
+        let board = Board::from_str("4k3/8/2p5/3Q4/8/8/8/4K3 b - - 0 1").unwrap();
+        let repetition = HashSet::new();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let mut ctx = SearchContext::new(&board, rng, &mut cache, &repetition);
+        ctx.quiescence_depth = DEFAULT_QUIESCENCE_DEPTH;
+
+        let static_score = evaluate_stm(&board);
+        let quiet_score = quiescence(&board, 0, -i16::MAX, i16::MAX, &mut ctx);
+
+        assert!(static_score < -500, "{}", static_score);
+        assert!(quiet_score > -200, "{}", quiet_score);
+    }
+
+    #[test]
+    fn test_principal_variation_trace() {
+        // Rxd8+ Kxd8 Rxe7, the static evaluation swing by a rook along the line
+        let board = Board::from_str("3rk3/4p3/8/8/8/8/5PPP/3RR1K1 w - - 0 1").unwrap();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(4096);
+        let options = SearchOptions::default();
+
+        let best = search_root(&board, 3, &options, rng, &mut cache, &HashSet::new()).0;
+        let pv = principal_variation(&board, 3, &options, rng, &mut cache, &HashSet::new());
+
+        assert_eq!(pv.len(), 3);
+        assert_eq!(pv.first().copied(), best);
+        let mut line = board;
+        for &(mov, _) in &pv {
+            assert!(line.legal(mov));
+            line = line.make_move_new(mov);
+        }
+
+        let trace = format_pv_trace(&board, &pv);
+        let rows = trace.lines().collect::<Vec<_>>();
+        // header, one row per move and the final position
+        assert_eq!(rows.len(), pv.len() + 2);
+        assert!(rows[1].starts_with(&format!("0   {}", pv[0].0)));
+        assert!(rows[pv.len() + 1].contains(" - "));
+    }
+
+    #[test]
+    fn test_second_best_margin() {
+        // Rxd5 win a free queen, every other move is far worse
+        let board = Board::from_str("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let rxd5 = ChessMove::new(Square::D1, Square::D5, None);
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+
+        let ranked = negamax_prelude_2nd(
+            &board,
+            2,
+            &SearchOptions::default(),
+            rng,
+            &mut cache,
+            &HashSet::new(),
+        );
+        assert_eq!(ranked[0].unwrap().0, rxd5);
+
+        let (chosen, fallback) = second_best_within(ranked, Some(100));
+        assert_eq!(chosen, ranked[0]);
+        assert!(fallback);
+
+        let (chosen, fallback) = second_best_within(ranked, None);
+        assert_eq!(chosen, ranked[1]);
+        assert!(!fallback);
+
+        // only one legal move, nothing to fall back from
+        let (chosen, fallback) = second_best_within([ranked[0], None], Some(100));
+        assert_eq!(chosen, ranked[0]);
+        assert!(!fallback);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "hash collision")]
+    fn test_hash_collision_detected() {
+        let board = Board::default();
+        let e4 = ChessMove::new(Square::E2, Square::E4, None);
+        let after_e4 = board.make_move_new(e4);
+        let other = Board::from_str("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+
+        // force an entry of another position under the hash of the searched one
+        cache.put(
+            BoardHash::new(&after_e4),
+            TranspositionItem::new(super::BoundedScore::Exact(0), 20, &other),
+        );
+
+        evaluate_move(&board, e4, 3, rng, &mut cache, &HashSet::new());
+    }
+
+    #[test]
+    fn test_analyze() {
+        // back rank mate in one
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = analyze(&board, 0, 3).unwrap();
+
+        assert_eq!(
+            result.best_move,
+            ChessMove::new(Square::A1, Square::A8, None)
+        );
+        assert_eq!(result.pv.first(), Some(&result.best_move));
+        assert!(result.score > 1000);
+        assert!(result.nodes > 0);
+        assert_eq!(analyze(&board, 0, 3), Some(result));
+
+        let checkmated = Board::from_str("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(analyze(&checkmated, 0, 3), None);
+    }
+
+    #[test]
+    fn test_fifty_move_rule_draw() {
+        // rook up, but the next move without capture or pawn move complete the fifty moves
+        let fen = "8/8/8/4k3/8/8/8/R3K3 w - - 99 80";
+        let board = Board::from_str(fen).unwrap();
+        let clock = fen_halfmove_clock(fen);
+        assert_eq!(clock, 99);
+
+        assert!(analyze(&board, 0, 3).unwrap().score > 300);
+        assert_eq!(analyze(&board, clock, 3).unwrap().score, 0);
+
+        // checkmate on the 100th ply still win
+        let mate = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80").unwrap();
+        assert!(analyze(&mate, 99, 3).unwrap().score > 1000);
+    }
+
+    /// Random walk forward and backward (via board clones), checking the incrementally updated
+    /// board hash and repetition tracker against recomputation from scratch at every step
+    fn check_incremental_tracking(start: &str, steps: usize) -> usize {
+        let rng = &mut test_rng();
+        let mut tracker = RepetitionTracker::default();
+        let mut path = Vec::new();
+        let mut board = Board::from_str(start).unwrap();
+        let mut most_repeated = 0;
+
+        for step in 0..steps {
+            let backtrack = !path.is_empty() && rng.gen_bool(0.3);
+            match random_move(&board, rng) {
+                Some(mov) if !backtrack => {
+                    tracker.push(&board, mov);
+                    path.push(board);
+                    board = board.make_move_new(mov);
+                }
+                _ => match path.pop() {
+                    Some(previous) => {
+                        tracker.pop();
+                        board = previous;
+                    }
+                    None => break,
+                },
+            }
+
+            // hash updated by make_move against hash of the same position parsed from FEN
+            let fresh = Board::from_str(&board.to_string()).unwrap();
+            assert_eq!(
+                board.get_hash(),
+                fresh.get_hash(),
+                "step {}: {}",
+                step,
+                board
+            );
+
+            let mut counts = HashMap::new();
+            for previous in &path {
+                *counts.entry(BoardHash::new(previous)).or_insert(0) += 1;
+            }
+            let repeated = counts
+                .iter()
+                .filter(|(_, &count)| count >= 2)
+                .map(|(&hash, _)| hash)
+                .collect::<HashSet<_>>();
+            assert_eq!(tracker.len(), path.len(), "step {}", step);
+            assert_eq!(tracker.repeated(), &repeated, "step {}", step);
+            most_repeated = most_repeated.max(repeated.len());
+        }
+
+        most_repeated
+    }
+
+    #[test]
+    fn test_incremental_tracking_random_walk() {
+        // castling both side, en passant and promotion (with capture) available
+        check_incremental_tracking("r3k2r/pPp5/8/3pP3/8/8/P5pP/R3K2R w KQkq d6 0 1", 2000);
+
+        // few pieces so the walk revisit positions
+        let most_repeated = check_incremental_tracking("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", 2000);
+        assert!(most_repeated > 0);
+    }
+
+    #[test]
+    fn test_press_contempt() {
+        // after 1.Nf3 Nf6, 2.Ng1 return to a position that already repeated (a draw)
+        let start = Board::default();
+        let board = ["g1f3", "g8f6"].iter().fold(start, |board, mov| {
+            board.make_move_new(ChessMove::from_str(mov).unwrap())
+        });
+        let retreat = ChessMove::from_str("f3g1").unwrap();
+        let mut repetition = HashSet::new();
+        repetition.insert(BoardHash::new(&board.make_move_new(retreat)));
+
+        let best_with_press = |press| {
+            let options = SearchOptions::default().with_press(press);
+            let (best, _) = search_root(
+                &board,
+                2,
+                &options,
+                &mut test_rng(),
+                &mut LruCache::new(1024),
+                &repetition,
+            );
+            best.unwrap().0
+        };
+
+        assert_ne!(best_with_press(1.0), retreat);
+        assert_eq!(best_with_press(-1.0), retreat);
+
+        let neutral = SearchOptions::default().with_press(0.0);
+        assert_eq!((neutral.contempt, neutral.second_best_margin), (0, Some(0)));
+        assert_eq!(neutral.second_best_chance, 0.0);
+        // pressing play the near best move sometimes, playing solid never
+        let pressing = SearchOptions::default().with_press(1.0);
+        assert_eq!(pressing.second_best_chance, MAX_PRESS_SECOND_BEST_CHANCE);
+        assert_eq!(
+            SearchOptions::default().with_press(-1.0).second_best_chance,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_contempt() {
+        // equal position, 2.Ng1 repeat a position (a draw)
+        let board = ["g1f3", "g8f6"]
+            .iter()
+            .fold(Board::default(), |board, mov| {
+                board.make_move_new(ChessMove::from_str(mov).unwrap())
+            });
+        let retreat = ChessMove::from_str("f3g1").unwrap();
+        let mut repetition = HashSet::new();
+        repetition.insert(BoardHash::new(&board.make_move_new(retreat)));
+
+        let best_with_contempt = |contempt| {
+            let options = SearchOptions {
+                contempt,
+                ..Default::default()
+            };
+            let (best, _) = search_root(
+                &board,
+                2,
+                &options,
+                &mut test_rng(),
+                &mut LruCache::new(1024),
+                &repetition,
+            );
+            best.unwrap()
+        };
+
+        // the draw is worth -contempt to the side searching
+        assert_ne!(best_with_contempt(100).0, retreat);
+        assert_eq!(best_with_contempt(-100), (retreat, 100));
+
+        // a stalemate is scored as a draw too, whoever is stalemated
+        let stalemate = Board::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(stalemate.status(), BoardStatus::Stalemate);
+        let white = Board::from_str("7k/8/6K1/8/8/8/5Q2/8 w - - 0 1").unwrap();
+        for (root, score) in [(&stalemate, -30), (&white, 30)] {
+            let rng = &mut test_rng();
+            let mut cache = LruCache::new(1024);
+            let mut ctx = SearchContext::new(root, rng, &mut cache, &repetition);
+            ctx.contempt = 30;
+            assert_eq!(negamax(&stalemate, 2, -i16::MAX, i16::MAX, &mut ctx), score);
+        }
+    }
+
+    #[test]
+    fn test_transposition_table_hit() {
+        let board =
+            Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let options = SearchOptions::default();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(100_000);
+
+        let (first_best, first) =
+            search_root(&board, 4, &options, rng, &mut cache, &HashSet::new());
+        let (second_best, second) =
+            search_root(&board, 4, &options, rng, &mut cache, &HashSet::new());
+
+        // every child of the root is already in the cache at the needed depth
+        assert!(second.tt_hits > 0);
+        assert!(second.nodes < first.nodes);
+        assert_eq!(first_best.unwrap().1, second_best.unwrap().1);
+
+        // a draw by repetition only hold in this game, no score depending on it is cached
+        let board = ["g1f3", "g8f6"]
+            .iter()
+            .fold(Board::default(), |board, mov| {
+                board.make_move_new(ChessMove::from_str(mov).unwrap())
+            });
+        let retreat = ChessMove::from_str("f3g1").unwrap();
+        let mut repetition = HashSet::new();
+        repetition.insert(BoardHash::new(&board.make_move_new(retreat)));
+        let seek_draw = SearchOptions {
+            contempt: -100,
+            ..Default::default()
+        };
+        let mut cache = LruCache::new(100_000);
+        let (drawn, _) = search_root(&board, 2, &seek_draw, rng, &mut cache, &repetition);
+        assert_eq!(drawn, Some((retreat, 100)));
+        assert!(cache.peek(&BoardHash::new(&board)).is_none());
+
+        // same result with or without the cache once the repetition is gone
+        let fresh = search_root(
+            &board,
+            2,
+            &options,
+            rng,
+            &mut LruCache::new(100_000),
+            &HashSet::new(),
+        );
+        let warm = search_root(&board, 2, &options, rng, &mut cache, &HashSet::new());
+        assert_eq!(fresh.0.unwrap().1, warm.0.unwrap().1);
+    }
+
+    #[test]
+    fn test_iterative_deepening_same_score() {
+        let board =
+            Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let options = SearchOptions::default();
+        let repetition = HashSet::new();
+
+        let (deepened, _) = search_root(
+            &board,
+            4,
+            &options,
+            &mut test_rng(),
+            &mut LruCache::new(100_000),
+            &repetition,
+        );
+
+        // single iteration straight to depth 4
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(100_000);
+        let mut ctx = SearchContext::new(&board, rng, &mut cache, &repetition);
+        let single = root_iteration(&board, 4, &options, None, &mut ctx).unwrap();
+
+        assert_eq!(deepened.unwrap().1, single.unwrap().1);
+    }
+
+    #[test]
+    fn test_null_move_pruning_reduce_nodes() {
+        let board =
+            Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let repetition = HashSet::new();
+
+        let ((pruned_move, _), pruned_nodes) = nodes_with(&board, 5, |_| {});
+        let ((full_move, _), full_nodes) = nodes_with(&board, 5, |ctx| {
+            ctx.null_move_pruning = false;
+        });
+        assert!(pruned_nodes < full_nodes);
+        assert!(board.legal(pruned_move) && board.legal(full_move));
+
+        // passing while in check would be illegal, however good the position
+        let check = Board::from_str("4k3/8/8/8/8/8/4r3/QQQ1K3 w - - 0 1").unwrap();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(1024);
+        let mut ctx = SearchContext::new(&check, rng, &mut cache, &repetition);
+        assert!(!ctx.null_move_cutoff(&check, 5, -1000));
+        assert_eq!(ctx.nodes, 0);
+    }
+
+    #[test]
+    fn test_mvv_lva_order() {
+        // white pawn, knight and queen can all capture the black queen on d5, knight, rook and queen can take a pawn
+        let board = Board::from_str("4k3/8/8/3q4/p3P3/2N5/8/R2QK3 w - - 0 1").unwrap();
+        let mut moves = MoveGen::new_legal(&board).collect::<Vec<_>>();
+        moves.sort_by_key(|&mov| Reverse(mvv_lva(&board, mov)));
+
+        let text = moves
+            .iter()
+            .take(6)
+            .map(|mov| mov.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(text, ["e4d5", "c3d5", "d1d5", "c3a4", "a1a4", "d1a4"]);
+        assert_eq!(mvv_lva(&board, moves[6]), None);
+    }
+
+    #[test]
+    fn test_move_ordering_reduce_nodes() {
+        // white wins the queen with Nxd5 / exd5, a tactical position where captures matter
+        let board =
+            Board::from_str("r1b1kb1r/ppp2ppp/2n5/3qp3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 0 5")
+                .unwrap();
+        let ((ordered_move, _), ordered_nodes) = nodes_with(&board, 3, |_| {});
+        let ((unordered_move, _), unordered_nodes) = nodes_with(&board, 3, |ctx| {
+            ctx.move_ordering = false;
+        });
+        assert!(ordered_nodes < unordered_nodes);
+        assert_eq!(ordered_move, unordered_move);
+    }
+
+    #[test]
+    fn test_killer_moves() {
+        let board = Board::default();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(16);
+        let repetition = HashSet::new();
+        let mut ctx = SearchContext::new(&board, rng, &mut cache, &repetition);
+
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+        let d4 = ChessMove::from_str("d2d4").unwrap();
+        let nf3 = ChessMove::from_str("g1f3").unwrap();
+        ctx.store_killer(e4);
+        ctx.store_killer(d4);
+        ctx.store_killer(d4);
+        assert_eq!(ctx.killer_rank(d4), 2);
+        assert_eq!(ctx.killer_rank(e4), 1);
+        assert_eq!(ctx.killer_rank(nf3), 0);
+
+        // killers are per ply
+        ctx.ply = 1;
+        assert_eq!(ctx.killer_rank(d4), 0);
+        ctx.store_killer(nf3);
+        ctx.store_killer(e4);
+        ctx.store_killer(d4);
+        assert_eq!(ctx.killers[1], [Some(d4), Some(e4)]);
+    }
+
+    #[test]
+    fn test_killer_moves_reduce_nodes() {
+        // quiet middlegame, few captures to order so killer moves matter
+        let board =
+            Board::from_str("r1bq1rk1/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R1BQ1RK1 w - - 0 8")
+                .unwrap();
+        let ((killer_move, _), killer_nodes) = nodes_with(&board, 5, |_| {});
+        let ((plain_move, _), plain_nodes) = nodes_with(&board, 5, |ctx| {
+            ctx.killer_moves = false;
+        });
+        assert!(killer_nodes < plain_nodes);
+        assert_eq!(killer_move, plain_move);
+    }
+
+    #[test]
+    fn test_evaluation_noise() {
+        let board =
+            Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let rng = &mut test_rng();
+        let exact = evaluate_white_pov(&board);
+
+        assert!((0..20).all(|_| evaluation_fn(&board, rng, 0) == exact));
+
+        let noisy = (0..20)
+            .map(|_| evaluation_fn(&board, rng, 50))
+            .collect::<HashSet<_>>();
+        assert!(noisy.len() > 1);
+        assert!(noisy.iter().all(|eval| (eval - exact).abs() <= 50));
+    }
+
+    #[test]
+    fn test_aspiration_window_reduce_nodes() {
+        // quiet position, the score barely move between iterations
+        let board = Board::from_str(
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQK2R b KQkq - 0 5",
+        )
+        .unwrap();
+
+        let (aspiration_best, aspiration_nodes) = nodes_with(&board, 5, |_| {});
+        let (full_best, full_nodes) = nodes_with(&board, 5, |ctx| {
+            ctx.aspiration_windows = false;
+        });
+        assert!(aspiration_nodes < full_nodes);
+        assert_eq!(aspiration_best, full_best);
+    }
+
+    #[test]
+    fn test_aspiration_window_research() {
+        // white win the queen, a guess far below the real score fail high and is re-searched
+        let board = Board::from_str("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let repetition = HashSet::new();
+        let options = SearchOptions::default();
+        let rng = &mut test_rng();
+        let mut cache = LruCache::new(100_000);
+        let mut ctx = SearchContext::new(&board, rng, &mut cache, &repetition);
+
+        let best = aspiration_iteration(&board, 2, &options, None, 0, &mut ctx).unwrap();
+        let full = root_iteration(&board, 2, &options, None, &mut ctx).unwrap();
+        assert_eq!(best.unwrap().0, ChessMove::from_str("e4d5").unwrap());
+        assert_eq!(best.unwrap().1, full.unwrap().1);
+    }
+
+    #[test]
+    fn test_principal_variation_search() {
+        let fens = [
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "r1b1kb1r/ppp2ppp/2n5/3qp3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 0 5",
+            "r1bq1rk1/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R1BQ1RK1 w - - 0 8",
+            "8/5pk1/6p1/3R4/8/6P1/5PK1/1r6 b - - 0 1",
+        ];
+
+        let (mut pvs_total, mut plain_total) = (0, 0);
+        for fen in fens {
+            let board = Board::from_str(fen).unwrap();
+            // null-move cutoffs and aspiration re-searches depend on the window, keep both
+            // searches comparable
+            let (pvs_best, pvs_nodes) = nodes_with(&board, 5, |ctx| {
+                ctx.null_move_pruning = false;
+                ctx.aspiration_windows = false;
+            });
+            let (plain_best, plain_nodes) = nodes_with(&board, 5, |ctx| {
+                ctx.null_move_pruning = false;
+                ctx.aspiration_windows = false;
+                ctx.pvs = false;
+            });
+            assert_eq!(pvs_best, plain_best, "{}", fen);
+            pvs_total += pvs_nodes;
+            plain_total += plain_nodes;
+        }
+        assert!(pvs_total < plain_total);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_root_search() {
+        // white win the queen with exd5
+        let board = Board::from_str("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let rng = &mut test_rng();
+        let parallel = SearchOptions::default();
+        // node limited search stay sequential
+        let sequential = SearchOptions {
+            node_limit: Some(u64::MAX),
+            ..Default::default()
+        };
+
+        let mut cache = LruCache::new(100_000);
+        let (parallel_best, stats) =
+            search_root(&board, 4, &parallel, rng, &mut cache, &HashSet::new());
+        assert!(stats.nodes > 0);
+        assert!(!cache.is_empty());
+
+        let mut cache = LruCache::new(100_000);
+        let (sequential_best, _) =
+            search_root(&board, 4, &sequential, rng, &mut cache, &HashSet::new());
+
+        let exd5 = ChessMove::from_str("e4d5").unwrap();
+        assert_eq!(parallel_best.unwrap().0, exd5);
+        assert_eq!(sequential_best.unwrap().0, exd5);
+
+        // the workers' caches are bounded like the shared one
+        let mut cache = LruCache::new(64);
+        let (small_best, _) = search_root(&board, 4, &parallel, rng, &mut cache, &HashSet::new());
+        assert_eq!(small_best.unwrap().0, exd5);
+        assert!(cache.len() <= 64);
+    }
+}
@@ -1,38 +1,1072 @@
-use chess::{BitBoard, Board, Color, Piece, ALL_FILES, ALL_RANKS, NUM_RANKS};
+use chess::{
+    between, get_adjacent_files, get_bishop_moves, get_file, get_king_moves, get_knight_moves,
+    get_pawn_attacks, get_rank, get_rook_moves, BitBoard, Board, ChessMove, Color, File, Piece,
+    Square, ALL_FILES, EMPTY,
+};
 
+pub mod eval_params;
 pub mod piece_square_tables;
+use eval_params::{eval_params, EvalParams};
 use piece_square_tables::*;
 
+// bonus for each rook on the relative 7th rank
+pub const ROOK_ON_SEVENTH_BONUS: i16 = 20;
+// additional bonus when two heavy pieces (at least one of them rook) are doubled on the 7th rank
+pub const DOUBLED_ON_SEVENTH_BONUS: i16 = 50;
+// bonus in the middlegame for two rooks defending each other along a rank or file
+pub const CONNECTED_ROOKS_BONUS: i16 = 15;
+// closedness at which knight and bishop are consider equally good
+pub const NEUTRAL_CLOSEDNESS: i16 = 12;
+// knight gain (and bishop lose) this much per point of closedness above neutral
+pub const MINOR_CLOSEDNESS_WEIGHT: i16 = 2;
+// game phase of the starting position, see `game_phase`
+pub const OPENING_PHASE: u8 = 24;
+// game phase at or below which endgame heuristics are used
+pub const ENDGAME_PHASE_THRESHOLD: u8 = 10;
+// game phase at or above which opening heuristics are used
+pub const OPENING_PHASE_THRESHOLD: u8 = 20;
+// bonus for each knight or bishop that left its starting square (opening only)
+pub const DEVELOPED_MINOR_BONUS: i16 = 10;
+// penalty for each knight or bishop still on its starting square (opening only)
+pub const UNDEVELOPED_MINOR_PENALTY: i16 = 15;
+// penalty for each knight or bishop still undeveloped while the queen left its starting square
+// (opening only)
+pub const EARLY_QUEEN_PENALTY: i16 = 10;
+// bonus per square of the enemy king zone attacked, indexed by `Piece::to_index`
+pub const KING_ZONE_ATTACK_WEIGHT: [i16; 6] = [0, 6, 6, 10, 20, 0];
+// percentage of the zone attack bonus used depending on number of attacker,
+// a lone attacker is rarely dangerous
+pub const KING_ATTACKER_SCALE: [i16; 8] = [0, 25, 60, 80, 90, 95, 100, 100];
+// side up the exchange (rook for minor piece) gain this much per point of closedness
+// below neutral (and lose as much above it), rook need open file
+pub const EXCHANGE_OPENNESS_WEIGHT: i16 = 2;
+// bonus for the side to move
+pub const TEMPO_BONUS: i16 = 10;
+// additional tempo bonus per capture or check available to the side to move
+pub const SHARP_TEMPO_WEIGHT: i16 = 4;
+// captures and checks counted at most this many time for the tempo bonus
+pub const MAX_SHARP_MOVES: i16 = 8;
+// bonus for having two or more bishops
+pub const BISHOP_PAIR_BONUS: i16 = 30;
+// bonus per square a knight, bishop, rook or queen can move to, in the middlegame
+pub const MOBILITY_WEIGHT_MIDDLE: i16 = 4;
+// same as `MOBILITY_WEIGHT_MIDDLE` once only kings and pawns are left, tapered by `game_phase`
+pub const MOBILITY_WEIGHT_END: i16 = 2;
+// penalty for each pawn beyond the first on a file
+pub const DOUBLED_PAWN_PENALTY: i16 = 15;
+// penalty for each pawn without friendly pawn on the adjacent files
+pub const ISOLATED_PAWN_PENALTY: i16 = 15;
+// bonus for a pawn with no enemy pawn ahead on its file or the adjacent files,
+// indexed by its rank from its own side (0 = back rank)
+pub const PASSED_PAWN_BONUS: [i16; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
+// bonus for a pawn-defended knight in the enemy half that no enemy pawn can ever attack
+pub const KNIGHT_OUTPOST_BONUS: i16 = 25;
+// piece value in centipawn, indexed by `Piece::to_index`, the default of `EvalParams::material`
+// (the evaluation count material from there), also used by static exchange evaluation and move ordering
+pub const PIECE_VALUE: [i16; 6] = [100, 320, 330, 500, 900, 20000];
+
 pub fn evaluation_pieces_worth_plus(board: &Board) -> i16 {
+    let mut score = 0;
+    evaluation_terms(board, |_, white, black| score += white - black);
+    score
+}
+
+/// Static evaluation in centipawn, positive is good for White whoever is to move
+pub fn evaluate_white_pov(board: &Board) -> i16 {
+    evaluation_pieces_worth_plus(board)
+}
+
+/// Static evaluation in centipawn, positive is good for the side to move
+/// (the convention of the search score)
+pub fn evaluate_stm(board: &Board) -> i16 {
+    match board.side_to_move() {
+        Color::White => evaluate_white_pov(board),
+        Color::Black => -evaluate_white_pov(board),
+    }
+}
+
+/// Contribution of a single evaluation term, each side from its own perspective
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalComponent {
+    pub name: &'static str,
+    pub white: i16,
+    pub black: i16,
+}
+
+/// Breakdown of `evaluation_pieces_worth_plus` into its terms,
+/// sum of `white - black` over every component equal the evaluation
+pub fn debug_evaluation(board: &Board) -> Vec<EvalComponent> {
+    let mut components = Vec::new();
+    evaluation_terms(board, |name, white, black| {
+        components.push(EvalComponent { name, white, black })
+    });
+    components
+}
+
+/// Readable table of `debug_evaluation`, centipawn of each component for White and Black
+pub fn format_debug_evaluation(board: &Board) -> String {
+    let components = debug_evaluation(board);
+
+    let mut text = format!("Evaluation of {}\n", board);
+    text += &format!(
+        "{:<16}{:>8}{:>8}{:>8}\n",
+        "component", "white", "black", "delta"
+    );
+    for c in &components {
+        text += &format!(
+            "{:<16}{:>8}{:>8}{:>+8}\n",
+            c.name,
+            c.white,
+            c.black,
+            c.white - c.black
+        );
+    }
+    text += &format!(
+        "{:<32}{:>+8} (white perspective)\n",
+        "total",
+        evaluation_pieces_worth_plus(board)
+    );
+    text
+}
+
+/// Call `term(name, white, black)` for every evaluation term,
+/// the evaluation (from white perspective) is the sum of `white - black`
+fn evaluation_terms(board: &Board, mut term: impl FnMut(&'static str, i16, i16)) {
+    let params = eval_params();
     let white = board.color_combined(Color::White);
     let black = board.color_combined(Color::Black);
 
+    // piece value plus its piece-square table, black read the table mirrored
+    let mut piece_table = |name, piece: Piece, table: &[i16; 64]| {
+        let piece_bb = board.pieces(piece);
+        let material = |bb: BitBoard| bb.popcnt() as i16 * params.material[piece.to_index()];
+        term(
+            name,
+            material(piece_bb & white) + weighted_sum(piece_bb & white, table),
+            material(piece_bb & black) + weighted_sum_mirrored(piece_bb & black, table),
+        )
+    };
+
+    piece_table("pawn", Piece::Pawn, &params.pawn_table);
+    piece_table("knight", Piece::Knight, &params.knight_table);
+    piece_table("bishop", Piece::Bishop, &params.bishop_table);
+    piece_table("rook", Piece::Rook, &params.rook_table);
+    piece_table("queen", Piece::Queen, &params.queen_table);
+    if is_endgame(board) {
+        piece_table("king", Piece::King, &params.king_endgame_table);
+    } else {
+        piece_table("king", Piece::King, &params.king_middle_table);
+    }
+
+    term(
+        "rook_activity",
+        rook_activity(board, Color::White, params),
+        rook_activity(board, Color::Black, params),
+    );
+    term(
+        "king_attack",
+        king_attack(board, Color::White, params),
+        king_attack(board, Color::Black, params),
+    );
+    term(
+        "development",
+        development(board, Color::White, params),
+        development(board, Color::Black, params),
+    );
+    term(
+        "minor_openness",
+        minor_piece_openness(board, Color::White, params),
+        minor_piece_openness(board, Color::Black, params),
+    );
+    term(
+        "exchange",
+        exchange_imbalance(board, Color::White, params),
+        exchange_imbalance(board, Color::Black, params),
+    );
+    term(
+        "bishop_pair",
+        bishop_pair(board, Color::White, params),
+        bishop_pair(board, Color::Black, params),
+    );
+    term(
+        "pawn_structure",
+        pawn_structure(board, Color::White, params),
+        pawn_structure(board, Color::Black, params),
+    );
+    term(
+        "knight_outpost",
+        knight_outposts(board, Color::White, params),
+        knight_outposts(board, Color::Black, params),
+    );
+    term(
+        "mobility",
+        mobility(board, Color::White, params),
+        mobility(board, Color::Black, params),
+    );
+
+    let tempo = tempo(board, params);
+    match board.side_to_move() {
+        Color::White => term("tempo", tempo, 0),
+        Color::Black => term("tempo", 0, tempo),
+    }
+}
+
+/// Amount of non-pawn material left, from `OPENING_PHASE` (all pieces on board) down to 0 (only kings and pawns).
+///
+/// Minor piece count 1, rook 2 and queen 4, capped at `OPENING_PHASE` for promoted pieces.
+pub fn game_phase(board: &Board) -> u8 {
+    let count = |piece| board.pieces(piece).popcnt() as u8;
+
+    let phase = count(Piece::Knight)
+        + count(Piece::Bishop)
+        + 2 * count(Piece::Rook)
+        + 4 * count(Piece::Queen);
+    phase.min(OPENING_PHASE)
+}
+
+pub fn is_endgame(board: &Board) -> bool {
+    game_phase(board) <= ENDGAME_PHASE_THRESHOLD
+}
+
+pub fn is_opening(board: &Board) -> bool {
+    game_phase(board) >= OPENING_PHASE_THRESHOLD
+}
+
+/// Squares attacked by the piece on `square` (pinned or not)
+pub fn piece_attacks(board: &Board, square: Square) -> BitBoard {
+    let blockers = *board.combined();
+    match (board.piece_on(square), board.color_on(square)) {
+        (Some(Piece::Pawn), Some(color)) => get_pawn_attacks(square, color, !EMPTY),
+        (Some(Piece::Knight), _) => get_knight_moves(square),
+        (Some(Piece::Bishop), _) => get_bishop_moves(square, blockers),
+        (Some(Piece::Rook), _) => get_rook_moves(square, blockers),
+        (Some(Piece::Queen), _) => {
+            get_bishop_moves(square, blockers) | get_rook_moves(square, blockers)
+        }
+        (Some(Piece::King), _) => get_king_moves(square),
+        _ => EMPTY,
+    }
+}
+
+/// Every piece of both side within `occupied` attacking `square`
+fn attackers_to(board: &Board, square: Square, occupied: BitBoard) -> BitBoard {
+    let pieces = |piece| *board.pieces(piece);
+    let diagonal = pieces(Piece::Bishop) | pieces(Piece::Queen);
+    let straight = pieces(Piece::Rook) | pieces(Piece::Queen);
+
+    let pawns = (get_pawn_attacks(square, Color::White, !EMPTY)
+        & pieces(Piece::Pawn)
+        & board.color_combined(Color::Black))
+        | (get_pawn_attacks(square, Color::Black, !EMPTY)
+            & pieces(Piece::Pawn)
+            & board.color_combined(Color::White));
+
+    let attackers = pawns
+        | (get_knight_moves(square) & pieces(Piece::Knight))
+        | (get_bishop_moves(square, occupied) & diagonal)
+        | (get_rook_moves(square, occupied) & straight)
+        | (get_king_moves(square) & pieces(Piece::King));
+    attackers & occupied
+}
+
+/// Square and kind of the cheapest piece of `color` within `occupied` attacking `square`
+fn least_valuable_attacker(
+    board: &Board,
+    square: Square,
+    occupied: BitBoard,
+    color: Color,
+) -> Option<(Square, Piece)> {
+    let attackers = attackers_to(board, square, occupied) & board.color_combined(color);
+    chess::ALL_PIECES.iter().find_map(|&piece| {
+        (attackers & board.pieces(piece))
+            .next()
+            .map(|from| (from, piece))
+    })
+}
+
+/// Static exchange evaluation: material won (centipawn, side to move perspective)
+/// by capturing on `square` then both side keep recapturing with their cheapest piece
+/// as long as it is profitable, None if the side to move can't capture there.
+///
+/// Pin and check are ignored except that a king never capture into a defended square.
+pub fn static_exchange_evaluation(board: &Board, square: Square) -> Option<i16> {
+    let side = board.side_to_move();
+    if board.color_on(square) != Some(!side) {
+        return None;
+    }
+    let (from, piece) = least_valuable_attacker(board, square, *board.combined(), side)?;
+    exchange_gain(board, square, from, piece)
+}
+
+/// Same as `static_exchange_evaluation` but the exchange start with `mov` instead of the
+/// cheapest attacker, None if `mov` doesn't capture (en passant included).
+///
+/// A promoting capture count as a pawn capturing.
+pub fn static_exchange_evaluation_move(board: &Board, mov: ChessMove) -> Option<i16> {
+    let square = mov.get_dest();
+    if board.color_on(square) != Some(!board.side_to_move()) {
+        return None;
+    }
+    let piece = board.piece_on(mov.get_source())?;
+    exchange_gain(board, square, mov.get_source(), piece)
+}
+
+/// Static exchange on `square` starting with `piece` on `from` capturing,
+/// see `static_exchange_evaluation`
+fn exchange_gain(board: &Board, square: Square, from: Square, piece: Piece) -> Option<i16> {
+    let side = board.side_to_move();
+    let target = board.piece_on(square)?;
+
+    let mut occupied = *board.combined();
+    let (mut from, mut piece) = (from, piece);
+    let mut capturing_side = side;
+    // gain[d]: material of the side making the d-th capture if the exchange stop there
+    let mut gain = vec![PIECE_VALUE[target.to_index()]];
+
+    loop {
+        occupied ^= BitBoard::from_square(from);
+        capturing_side = !capturing_side;
+
+        match least_valuable_attacker(board, square, occupied, capturing_side) {
+            None => break,
+            Some(_) if piece == Piece::King => {
+                // the king just captured into a defended square, that capture is illegal
+                gain.pop();
+                break;
+            }
+            Some(next) => {
+                gain.push(PIECE_VALUE[piece.to_index()] - gain[gain.len() - 1]);
+                (from, piece) = next;
+            }
+        }
+    }
+
+    if gain.is_empty() {
+        return None;
+    }
+    // each side may stop recapturing when it doesn't gain anything
+    for d in (1..gain.len()).rev() {
+        gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+    }
+    Some(gain[0])
+}
+
+/// Reward piece attacking squares around the enemy king (middlegame only)
+fn king_attack(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    if is_endgame(board) {
+        return 0;
+    }
+
+    let enemy_king = board.king_square(!color);
+    let king_zone = get_king_moves(enemy_king) | BitBoard::from_square(enemy_king);
+
+    let mut attacker_count = 0;
+    let mut zone_attack = 0;
+    for square in *board.color_combined(color) & !board.pieces(Piece::Pawn) {
+        let piece = board.piece_on(square).expect("square is occupied");
+        let attacked = (piece_attacks(board, square) & king_zone).popcnt() as i16;
+        let weight = params.king_zone_attack_weight[piece.to_index()];
+
+        if attacked > 0 && weight > 0 {
+            attacker_count += 1;
+            zone_attack += attacked * weight;
+        }
+    }
+
+    let scale =
+        params.king_attacker_scale[attacker_count.min(params.king_attacker_scale.len() - 1)];
+    zone_attack * scale / 100
+}
+
+/// Reward knight and bishop that left the back rank during the opening,
+/// penalize bringing the queen out before them
+fn development(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    if !is_opening(board) {
+        return 0;
+    }
+
+    let minor_files = get_file(File::B) | get_file(File::C) | get_file(File::F) | get_file(File::G);
+    let starting_squares = get_rank(color.to_my_backrank()) & minor_files;
+
+    let minor =
+        (board.pieces(Piece::Knight) | board.pieces(Piece::Bishop)) & board.color_combined(color);
+    let undeveloped = (minor & starting_squares).popcnt() as i16;
+    let developed = minor.popcnt() as i16 - undeveloped;
+
+    let queen_start = BitBoard::from_square(Square::make_square(color.to_my_backrank(), File::D));
+    let queen = board.pieces(Piece::Queen) & board.color_combined(color);
+    let early_queen = queen != EMPTY && queen & queen_start == EMPTY;
+    let early_queen_penalty = if early_queen {
+        undeveloped * params.early_queen_penalty
+    } else {
+        0
+    };
+
+    developed * params.developed_minor_bonus
+        - undeveloped * params.undeveloped_minor_penalty
+        - early_queen_penalty
+}
+
+fn rook_activity(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let connected_bonus = if !is_endgame(board) && has_connected_rooks(board, color) {
+        params.connected_rooks_bonus
+    } else {
+        0
+    };
+    connected_bonus + rook_on_seventh(board, color, params)
+}
+
+/// Two rooks of `color` sharing a rank or file with nothing in between
+fn has_connected_rooks(board: &Board, color: Color) -> bool {
+    let rooks = board.pieces(Piece::Rook) & board.color_combined(color);
+    let mut pairs = rooks.flat_map(|a| rooks.map(move |b| (a, b)));
+    pairs.any(|(a, b)| {
+        a != b
+            && (a.get_rank() == b.get_rank() || a.get_file() == b.get_file())
+            && between(a, b) & board.combined() == EMPTY
+    })
+}
+
+fn rook_on_seventh(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let own = board.color_combined(color);
+    let enemy = board.color_combined(!color);
+    let seventh = get_rank(color.to_seventh_rank());
+    let enemy_backrank = get_rank(color.to_their_backrank());
+
+    // 7th rank is only worth occupying when there is pawn to eat or king to confine
+    let enemy_pawn_on_seventh = board.pieces(Piece::Pawn) & enemy & seventh;
+    let enemy_king_on_backrank = board.pieces(Piece::King) & enemy & enemy_backrank;
+    if enemy_pawn_on_seventh == EMPTY && enemy_king_on_backrank == EMPTY {
+        return 0;
+    }
+
+    let rook_count = (board.pieces(Piece::Rook) & own & seventh).popcnt() as i16;
+    let queen_count = (board.pieces(Piece::Queen) & own & seventh).popcnt() as i16;
+
+    let mut bonus = rook_count * params.rook_on_seventh_bonus;
+    if rook_count >= 1 && rook_count + queen_count >= 2 {
+        bonus += params.doubled_on_seventh_bonus;
+    }
+    bonus
+}
+
+/// How blocked the position is, count every pawn plus extra for each pawn locked head-on
+fn closedness(board: &Board) -> i16 {
     let pawn = board.pieces(Piece::Pawn);
-    let bishop = board.pieces(Piece::Bishop);
-    let rook = board.pieces(Piece::Rook);
-    let knight = board.pieces(Piece::Knight);
-    let queen = board.pieces(Piece::Queen);
-    let king = board.pieces(Piece::King);
-
-    let delta_piece_table = |piece_bb: &BitBoard, w_table: &[i16; 64], b_table: &[i16; 64]| {
-        weighted_sum(piece_bb & white, w_table) - weighted_sum(piece_bb & black, b_table)
+    let white_pawn = pawn & board.color_combined(Color::White);
+    let black_pawn = pawn & board.color_combined(Color::Black);
+
+    // black pawn directly in front of white pawn (from white perspective)
+    let locked_count = white_pawn
+        .filter(|sq| {
+            sq.up()
+                .is_some_and(|front| black_pawn & BitBoard::from_square(front) != EMPTY)
+        })
+        .count() as i16;
+
+    pawn.popcnt() as i16 + 2 * locked_count
+}
+
+/// Knight prefer closed position, bishop prefer open one
+fn minor_piece_openness(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let own = board.color_combined(color);
+    let knight_count = (board.pieces(Piece::Knight) & own).popcnt() as i16;
+    let bishop_count = (board.pieces(Piece::Bishop) & own).popcnt() as i16;
+
+    let knight_advantage =
+        (closedness(board) - params.neutral_closedness) * params.minor_closedness_weight;
+    (knight_count - bishop_count) * knight_advantage
+}
+
+/// Number of capture and direct check of the side to move, counted from attack bitboards
+/// (pseudo-legal, pawn push and discovered check aren't counted) to keep it cheap
+fn sharp_move_count(board: &Board) -> i16 {
+    let color = board.side_to_move();
+    let own = board.color_combined(color);
+    let enemy = board.color_combined(!color);
+    let enemy_king = board.king_square(!color);
+    let occupied = *board.combined();
+
+    // squares from which each piece kind would attack the enemy king
+    let diagonal = get_bishop_moves(enemy_king, occupied);
+    let straight = get_rook_moves(enemy_king, occupied);
+    let check_squares = |piece| match piece {
+        Piece::Knight => get_knight_moves(enemy_king),
+        Piece::Bishop => diagonal,
+        Piece::Rook => straight,
+        Piece::Queen => diagonal | straight,
+        Piece::Pawn | Piece::King => EMPTY,
     };
 
-    let delta_pawn_p = delta_piece_table(pawn, &WHITE_PAWN, &BLACK_PAWN);
-    let delta_rook_p = delta_piece_table(rook, &WHITE_ROOK, &BLACK_ROOK);
-    let delta_bishop_p = delta_piece_table(bishop, &WHITE_BISHOP, &BLACK_BISHOP);
-    let delta_knight_p = delta_piece_table(knight, &WHITE_KNIGHT, &BLACK_KNIGHT);
+    own.map(|square| {
+        let piece = board.piece_on(square).expect("own square has a piece");
+        let targets = piece_attacks(board, square) & !own;
+        (targets & (enemy | check_squares(piece))).popcnt() as i16
+    })
+    .sum()
+}
+
+/// Bonus for having the move, worth more when there are captures and checks to play
+fn tempo(board: &Board, params: &EvalParams) -> i16 {
+    params.tempo_bonus
+        + sharp_move_count(board).min(params.max_sharp_moves) * params.sharp_tempo_weight
+}
+
+/// Number of squares (empty or enemy) the knights, bishops, rooks and queens of `color`
+/// attack, counted from attack bitboards so both side are counted without a null move
+/// (pseudo-legal, pins are ignored)
+fn mobility_count(board: &Board, color: Color) -> i16 {
+    let own = board.color_combined(color);
+    let pieces = board.pieces(Piece::Knight)
+        | board.pieces(Piece::Bishop)
+        | board.pieces(Piece::Rook)
+        | board.pieces(Piece::Queen);
+
+    (pieces & own)
+        .map(|square| (piece_attacks(board, square) & !own).popcnt() as i16)
+        .sum()
+}
 
-    // explicit calculation so we can use result to compute is_end_game
-    let white_queen_p = weighted_sum(queen & white, &WHITE_QUEEN);
-    let black_queen_p = weighted_sum(queen & black, &BLACK_QUEEN);
-    let delta_queen_p = white_queen_p - black_queen_p;
+/// Bonus for piece activity, weight tapered from `MOBILITY_WEIGHT_MIDDLE` to `MOBILITY_WEIGHT_END`
+fn mobility(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let phase = i16::from(game_phase(board));
+    let opening = i16::from(OPENING_PHASE);
+    let weight =
+        params.mobility_weight_middle * phase + params.mobility_weight_end * (opening - phase);
+    mobility_count(board, color) * weight / opening
+}
 
-    let delta_king_p = if white_queen_p == 0 && black_queen_p == 0 {
-        delta_piece_table(king, &WHITE_KING_ENDGAME, &BLACK_KING_ENDGAME)
+/// Bonus if `color` has the bishop pair
+fn bishop_pair(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    if (board.pieces(Piece::Bishop) & board.color_combined(color)).popcnt() >= 2 {
+        params.bishop_pair_bonus
     } else {
-        delta_piece_table(king, &WHITE_KING_MIDDLE, &BLACK_KING_MIDDLE)
+        0
+    }
+}
+
+/// Doubled and isolated pawn penalty and passed pawn bonus of `color`
+fn pawn_structure(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let pawns = *board.pieces(Piece::Pawn);
+    let own = pawns & board.color_combined(color);
+    let enemy = pawns & board.color_combined(!color);
+
+    let mut score = 0;
+    for &file in ALL_FILES.iter() {
+        let count = (own & get_file(file)).popcnt() as i16;
+        if count > 1 {
+            score -= (count - 1) * params.doubled_pawn_penalty;
+        }
+        if own & get_adjacent_files(file) == EMPTY {
+            score -= count * params.isolated_pawn_penalty;
+        }
+    }
+
+    for square in own {
+        let front_span = ranks_ahead(square, color)
+            & (get_file(square.get_file()) | get_adjacent_files(square.get_file()));
+        if enemy & front_span == EMPTY {
+            score += params.passed_pawn_bonus[relative_rank(square, color)];
+        }
+    }
+    score
+}
+
+/// Ranks ahead of `square` from `color` point of view
+fn ranks_ahead(square: Square, color: Color) -> BitBoard {
+    let rank = square.get_rank().to_index();
+    match color {
+        Color::White => BitBoard(!0 << (8 * rank)) & !get_rank(square.get_rank()),
+        Color::Black => BitBoard((1 << (8 * rank)) - 1),
+    }
+}
+
+/// Rank index of `square` counted from `color` back rank
+fn relative_rank(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => square.get_rank().to_index(),
+        Color::Black => 7 - square.get_rank().to_index(),
+    }
+}
+
+/// Bonus for each `color` knight on an outpost: in the enemy half, defended by a friendly pawn
+/// and out of reach of every enemy pawn (none left ahead on the adjacent files)
+fn knight_outposts(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let pawns = *board.pieces(Piece::Pawn);
+    let own_pawns = pawns & board.color_combined(color);
+    let enemy_pawns = pawns & board.color_combined(!color);
+    let knights = board.pieces(Piece::Knight) & board.color_combined(color);
+
+    let outposts = knights.filter(|&square| {
+        // own pawns attacking the square are the ones an enemy pawn there would attack
+        let defended = get_pawn_attacks(square, !color, own_pawns) != EMPTY;
+        let attackable =
+            enemy_pawns & ranks_ahead(square, color) & get_adjacent_files(square.get_file())
+                != EMPTY;
+        relative_rank(square, color) >= 4 && defended && !attackable
+    });
+    outposts.count() as i16 * params.knight_outpost_bonus
+}
+
+/// Adjust the worth of being up the exchange (more rook, opponent has more minor piece)
+/// to how open the position is, on top of the plain piece values
+fn exchange_imbalance(board: &Board, color: Color, params: &EvalParams) -> i16 {
+    let count = |piece, color| (board.pieces(piece) & board.color_combined(color)).popcnt() as i16;
+    let minor = |color| count(Piece::Knight, color) + count(Piece::Bishop, color);
+
+    let extra_rook = count(Piece::Rook, color) - count(Piece::Rook, !color);
+    let missing_minor = minor(!color) - minor(color);
+    let exchange_up = extra_rook.min(missing_minor);
+    if exchange_up <= 0 {
+        return 0;
+    }
+
+    exchange_up * (params.neutral_closedness - closedness(board)) * params.exchange_openness_weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_params::eval_params;
+    use super::{
+        bishop_pair, closedness, debug_evaluation, development, evaluate_stm, evaluate_white_pov,
+        evaluation_pieces_worth_plus, exchange_imbalance, format_debug_evaluation, game_phase,
+        is_endgame, king_attack, knight_outposts, minor_piece_openness, mobility, mobility_count,
+        pawn_structure, rook_activity, sharp_move_count, static_exchange_evaluation,
+        static_exchange_evaluation_move, tempo, BISHOP_PAIR_BONUS, CONNECTED_ROOKS_BONUS,
+        DEVELOPED_MINOR_BONUS, DOUBLED_ON_SEVENTH_BONUS, DOUBLED_PAWN_PENALTY, EARLY_QUEEN_PENALTY,
+        ISOLATED_PAWN_PENALTY, KNIGHT_OUTPOST_BONUS, MAX_SHARP_MOVES, MOBILITY_WEIGHT_MIDDLE,
+        NEUTRAL_CLOSEDNESS, OPENING_PHASE, PASSED_PAWN_BONUS, ROOK_ON_SEVENTH_BONUS,
+        SHARP_TEMPO_WEIGHT, TEMPO_BONUS, UNDEVELOPED_MINOR_PENALTY,
     };
+    use chess::{Board, ChessMove, Color, Square};
+    use std::str::FromStr;
+
+    fn eval_fen(fen: &str) -> i16 {
+        evaluation_pieces_worth_plus(&Board::from_str(fen).unwrap())
+    }
+
+    #[test]
+    fn test_doubled_rooks_on_seventh() {
+        let doubled = eval_fen("6k1/RR3ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let single = eval_fen("6k1/R4ppp/8/8/8/1R6/5PPP/6K1 w - - 0 1");
+        let passive = eval_fen("6k1/5ppp/8/8/8/RR6/5PPP/6K1 w - - 0 1");
+
+        assert!(doubled - single >= DOUBLED_ON_SEVENTH_BONUS);
+        assert!(doubled - passive >= 2 * ROOK_ON_SEVENTH_BONUS + DOUBLED_ON_SEVENTH_BONUS);
+    }
+
+    #[test]
+    fn test_rook_queen_on_seventh_is_symmetric() {
+        let white = eval_fen("6k1/QR3ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let black = eval_fen("6k1/5ppp/8/8/8/8/qr3PPP/6K1 b - - 0 1");
+
+        assert_eq!(white, -black);
+    }
+
+    #[test]
+    fn test_minor_piece_openness() {
+        // locked pawn chain, white knight vs black bishop
+        let closed_knight =
+            Board::from_str("4kb2/p7/1p1p4/1PpPp1p1/2P1P1P1/5N2/8/4K3 w - - 0 1").unwrap();
+        // same structure with the minor pieces swapped
+        let closed_bishop =
+            Board::from_str("4kn2/p7/1p1p4/1PpPp1p1/2P1P1P1/5B2/8/4K3 w - - 0 1").unwrap();
+        // few pawn, nothing locked
+        let open_knight = Board::from_str("4kb2/p7/8/8/8/5N2/6P1/4K3 w - - 0 1").unwrap();
+        let open_bishop = Board::from_str("4kn2/p7/8/8/8/5B2/6P1/4K3 w - - 0 1").unwrap();
+
+        assert!(closedness(&closed_knight) > NEUTRAL_CLOSEDNESS);
+        assert!(closedness(&open_knight) < NEUTRAL_CLOSEDNESS);
+
+        let delta_openness = |board: &Board| {
+            minor_piece_openness(board, Color::White, eval_params())
+                - minor_piece_openness(board, Color::Black, eval_params())
+        };
+        assert!(delta_openness(&closed_knight) > delta_openness(&closed_bishop));
+        assert!(delta_openness(&open_knight) < delta_openness(&open_bishop));
+    }
+
+    #[test]
+    fn test_game_phase() {
+        let phase_of = |fen| game_phase(&Board::from_str(fen).unwrap());
+        let endgame = |fen| is_endgame(&Board::from_str(fen).unwrap());
+
+        // (fen, phase, is endgame)
+        let positions = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                OPENING_PHASE,
+                false,
+            ),
+            // queens traded but everything else still on board
+            (
+                "r1b1kb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1B1KB1R w KQkq - 0 1",
+                16,
+                false,
+            ),
+            // rook and minor each
+            ("2r3k1/5ppp/4b3/8/8/4N3/5PPP/3R2K1 w - - 0 1", 6, true),
+            // queen against rook, queen still on board but little material left
+            ("6k1/5ppp/8/8/8/8/r4PPP/3Q2K1 w - - 0 1", 6, true),
+            ("8/5k2/8/3p4/3P4/8/5K2/8 w - - 0 1", 0, true),
+            // promoted queens doesn't push phase over the starting position
+            (
+                "QQQQkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQk - 0 1",
+                OPENING_PHASE,
+                false,
+            ),
+        ];
+
+        for (fen, phase, is_end) in positions.iter() {
+            assert_eq!(phase_of(fen), *phase, "{}", fen);
+            assert_eq!(endgame(fen), *is_end, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_debug_evaluation_sum_to_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "6k1/QR3ppp/8/8/8/8/5PPP/6K1 w - - 0 1",
+            "4kb2/p7/1p1p4/1PpPp1p1/2P1P1P1/5N2/8/4K3 w - - 0 1",
+        ];
+
+        for fen in fens.iter() {
+            let board = Board::from_str(fen).unwrap();
+            let components = debug_evaluation(&board);
+            let sum: i16 = components.iter().map(|c| c.white - c.black).sum();
+
+            assert_eq!(sum, evaluation_pieces_worth_plus(&board), "{}", fen);
+        }
+
+        // only the side to move get the tempo
+        let start = debug_evaluation(&Board::default());
+        assert!(start
+            .iter()
+            .filter(|c| c.name != "tempo")
+            .all(|c| c.white == c.black));
+    }
+
+    #[test]
+    fn test_development() {
+        let start = Board::default();
+        // 1. Nf3 played, black to move
+        let knight_out =
+            Board::from_str("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1").unwrap();
+
+        assert_eq!(
+            development(&knight_out, Color::White, eval_params())
+                - development(&start, Color::White, eval_params()),
+            DEVELOPED_MINOR_BONUS + UNDEVELOPED_MINOR_PENALTY
+        );
+        assert!(evaluation_pieces_worth_plus(&knight_out) > evaluation_pieces_worth_plus(&start));
+
+        // no development term once most piece are traded
+        let endgame = Board::from_str("1n2k3/4p3/8/8/8/8/4P3/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(development(&endgame, Color::White, eval_params()), 0);
+    }
+
+    #[test]
+    fn test_format_debug_evaluation() {
+        let board = Board::from_str("6k1/QR3ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let text = format_debug_evaluation(&board);
+
+        for component in debug_evaluation(&board) {
+            assert!(text.contains(component.name));
+        }
+        let total = format!("{:+}", evaluation_pieces_worth_plus(&board));
+        assert!(text.lines().last().unwrap().contains(&total));
+    }
+
+    #[test]
+    fn test_king_attack() {
+        // castled black king, white knight on b1 far away from it
+        let quiet =
+            Board::from_str("r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/3P4/PPP2PPP/RNBQK2R w KQ - 0 1")
+                .unwrap();
+        // knight on g5 eye f7 and h7
+        let knight_g5 =
+            Board::from_str("r1bq1rk1/pppp1ppp/2n2n2/2b1p1N1/2B1P3/3P4/PPP2PPP/R1BQK2R b KQ - 0 1")
+                .unwrap();
+        // queen join the knight from h5
+        let with_queen =
+            Board::from_str("r1bq1rk1/pppp1ppp/2n5/2b1p1NQ/2B1P3/3P4/PPP2PPP/R1B1K2R b KQ - 0 1")
+                .unwrap();
+
+        let quiet_attack = king_attack(&quiet, Color::White, eval_params());
+        let knight_attack = king_attack(&knight_g5, Color::White, eval_params());
+        let queen_attack = king_attack(&with_queen, Color::White, eval_params());
+
+        assert!(knight_attack > quiet_attack);
+        assert!(queen_attack > knight_attack);
+        assert!(evaluation_pieces_worth_plus(&with_queen) > evaluation_pieces_worth_plus(&quiet));
+
+        // no king attack bonus in the endgame
+        let endgame = Board::from_str("6k1/5ppp/8/6N1/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_attack(&endgame, Color::White, eval_params()), 0);
+    }
+
+    #[test]
+    fn test_static_exchange_evaluation() {
+        let see =
+            |fen: &str, square| static_exchange_evaluation(&Board::from_str(fen).unwrap(), square);
+
+        // free pawn
+        assert_eq!(
+            see("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", Square::D5),
+            Some(100)
+        );
+        // pawn defended by pawn, knight takes
+        assert_eq!(
+            see("4k3/8/4p3/3p4/8/4N3/8/4K3 w - - 0 1", Square::D5),
+            Some(-220)
+        );
+        // rook backed by rook behind (x-ray) win the pawn defended by a single rook
+        assert_eq!(
+            see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", Square::D5),
+            Some(100)
+        );
+        // king can't capture a defended piece
+        assert_eq!(see("4k3/8/8/8/8/2b5/3n4/4K3 w - - 0 1", Square::D2), None);
+        assert_eq!(
+            see("4k3/8/8/8/8/8/3n4/4K3 w - - 0 1", Square::D2),
+            Some(320)
+        );
+
+        // nothing to capture
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1", Square::D5), None);
+        assert_eq!(see("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", Square::D1), None);
+    }
+
+    #[test]
+    fn test_static_exchange_evaluation_move() {
+        // pawn defended by pawn and backed by the queen: the pawn taking first win a pawn,
+        // the queen taking first lose itself for two pawns
+        let board = Board::from_str("4k3/8/4p3/3p4/2P5/8/8/3QK3 w - - 0 1").unwrap();
+        let see =
+            |mov: &str| static_exchange_evaluation_move(&board, ChessMove::from_str(mov).unwrap());
+
+        assert_eq!(static_exchange_evaluation(&board, Square::D5), Some(100));
+        assert_eq!(see("c4d5"), Some(100));
+        assert_eq!(see("d1d5"), Some(-700));
+        // not a capture
+        assert_eq!(see("c4c5"), None);
+    }
+
+    #[test]
+    fn test_early_queen_penalty() {
+        // 1. e4 e5 2. Nf3 against 2. Qh5, same material
+        let knight_out =
+            Board::from_str("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+                .unwrap();
+        let queen_out =
+            Board::from_str("rnbqkbnr/pppp1ppp/8/4p3/4P2Q/8/PPPP1PPP/RNB1KBNR b KQkq - 1 2")
+                .unwrap();
+
+        assert_eq!(
+            development(&queen_out, Color::White, eval_params()),
+            -4 * (UNDEVELOPED_MINOR_PENALTY + EARLY_QUEEN_PENALTY)
+        );
+        assert!(
+            evaluation_pieces_worth_plus(&knight_out) > evaluation_pieces_worth_plus(&queen_out)
+        );
+    }
+
+    #[test]
+    fn test_exchange_imbalance() {
+        // white has rook against black knight, few pawns
+        let open = Board::from_str("4k3/pp3n2/8/8/8/8/PP3R2/4K3 w - - 0 1").unwrap();
+        // same material but a locked pawn chain
+        let closed =
+            Board::from_str("4k3/5n2/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/5R2/4K3 w - - 0 1").unwrap();
+
+        assert!(exchange_imbalance(&open, Color::White, eval_params()) > 0);
+        assert!(exchange_imbalance(&closed, Color::White, eval_params()) < 0);
+        assert_eq!(exchange_imbalance(&open, Color::Black, eval_params()), 0);
+
+        // equal material, no exchange imbalance
+        assert_eq!(
+            exchange_imbalance(&Board::default(), Color::White, eval_params()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_sharp_tempo() {
+        // same material, white queen and rook eyeing black's loose pieces against quiet setup
+        let sharp = Board::from_str("1k6/p1p2r2/8/1n1Q1b2/8/8/PP6/1K2R3 w - - 0 1").unwrap();
+        let quiet = Board::from_str("1k6/p1p2r2/1n3b2/8/8/8/PP1Q4/1K2R3 w - - 0 1").unwrap();
+
+        assert!(sharp_move_count(&sharp) > sharp_move_count(&quiet));
+        assert!(tempo(&sharp, eval_params()) > tempo(&quiet, eval_params()));
+        assert!(tempo(&sharp, eval_params()) <= TEMPO_BONUS + MAX_SHARP_MOVES * SHARP_TEMPO_WEIGHT);
+
+        // tempo only go to the side to move
+        let tempo_of = |board: &Board| {
+            debug_evaluation(board)
+                .into_iter()
+                .find(|c| c.name == "tempo")
+                .map(|c| (c.white, c.black))
+                .unwrap()
+        };
+        assert_eq!(tempo_of(&sharp), (tempo(&sharp, eval_params()), 0));
+        let black_to_move =
+            Board::default().make_move_new(ChessMove::new(Square::E2, Square::E4, None));
+        assert_eq!(tempo_of(&black_to_move).0, 0);
+    }
+
+    #[test]
+    fn test_connected_rooks() {
+        // castled but queen and bishop still stand between the rooks
+        let blocked = Board::from_str(
+            "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQ1RK1 w - - 0 1",
+        )
+        .unwrap();
+        let connected =
+            Board::from_str("r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N1BN2/PPPPQPPP/R4RK1 w - - 0 1")
+                .unwrap();
+
+        assert_eq!(rook_activity(&blocked, Color::White, eval_params()), 0);
+        assert_eq!(
+            rook_activity(&connected, Color::White, eval_params()),
+            CONNECTED_ROOKS_BONUS
+        );
+        assert_eq!(rook_activity(&connected, Color::Black, eval_params()), 0);
+    }
+
+    #[test]
+    fn test_evaluation_sign_convention() {
+        // white is a queen up, black to move
+        let board = Board::from_str("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+
+        assert!(evaluate_white_pov(&board) > 0);
+        assert_eq!(evaluate_stm(&board), -evaluate_white_pov(&board));
+
+        let white_to_move = board.null_move().unwrap();
+        assert!(evaluate_stm(&white_to_move) > 0);
+        assert_eq!(
+            evaluate_stm(&white_to_move),
+            evaluate_white_pov(&white_to_move)
+        );
+    }
+
+    #[test]
+    fn test_mobility() {
+        // bishop buried behind its own pawns against the same material with an open diagonal
+        let cramped = Board::from_str("4k3/pp6/8/8/8/8/PP1P4/2B1K3 w - - 0 1").unwrap();
+        let open = Board::from_str("4k3/pp6/8/8/8/2P5/PP6/2B1K3 w - - 0 1").unwrap();
+
+        assert_eq!(mobility_count(&cramped, Color::White), 0);
+        assert_eq!(mobility_count(&open, Color::White), 5);
+        assert!(
+            mobility(&open, Color::White, eval_params())
+                > mobility(&cramped, Color::White, eval_params())
+        );
+        assert!(evaluate_white_pov(&open) > evaluate_white_pov(&cramped));
+
+        // start position: only the knights can move
+        let start = Board::default();
+        assert_eq!(mobility_count(&start, Color::White), 4);
+        assert_eq!(
+            mobility(&start, Color::White, eval_params()),
+            4 * MOBILITY_WEIGHT_MIDDLE
+        );
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        let structure =
+            |fen: &str, color| pawn_structure(&Board::from_str(fen).unwrap(), color, eval_params());
+
+        // pawns blocked by each other and by a pawn on each adjacent files, nothing to count
+        let start = Board::default();
+        assert_eq!(pawn_structure(&start, Color::White, eval_params()), 0);
+        assert_eq!(pawn_structure(&start, Color::Black, eval_params()), 0);
+
+        // doubled: c3 and c4 (supported by b and d pawns, all blocked)
+        let doubled = "4k3/1ppp4/8/1ppp4/1PP5/2PP4/8/4K3 w - - 0 1";
+        assert_eq!(structure(doubled, Color::White), -DOUBLED_PAWN_PENALTY);
+
+        // isolated: a4 (h pawns keep each other company and are blocked)
+        let isolated = "4k3/p5pp/8/8/P7/6PP/8/4K3 w - - 0 1";
+        assert_eq!(structure(isolated, Color::White), -ISOLATED_PAWN_PENALTY);
+        assert_eq!(structure(isolated, Color::Black), -ISOLATED_PAWN_PENALTY);
+
+        // passed: white d6 and black e3 (each with a friendly pawn beside it)
+        let passed = "4k3/8/3P4/2P5/5p2/4p3/8/4K3 w - - 0 1";
+        assert_eq!(
+            structure(passed, Color::White),
+            PASSED_PAWN_BONUS[5] + PASSED_PAWN_BONUS[4]
+        );
+        assert_eq!(
+            structure(passed, Color::Black),
+            PASSED_PAWN_BONUS[5] + PASSED_PAWN_BONUS[4]
+        );
+        // more advanced passed pawn is worth more
+        assert!(PASSED_PAWN_BONUS[6] > PASSED_PAWN_BONUS[5]);
+    }
+
+    #[test]
+    fn test_bishop_pair() {
+        // white bishop pair against black bishop and knight, same pawns
+        let board = Board::from_str("4k3/pppp4/2n2b2/8/8/2B2B2/PPPP4/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            bishop_pair(&board, Color::White, eval_params()),
+            BISHOP_PAIR_BONUS
+        );
+        assert_eq!(bishop_pair(&board, Color::Black, eval_params()), 0);
+        assert!(evaluate_white_pov(&board) > 0);
+
+        // mirrored, black has the pair now
+        let mirrored = Board::from_str("4k3/pppp4/2b2b2/8/8/2N2B2/PPPP4/4K3 b - - 0 1").unwrap();
+        assert!(evaluate_stm(&mirrored) > 0);
+    }
+
+    #[test]
+    fn test_tempo_favors_side_to_move() {
+        // symmetric position with either side to move, only the tempo isn't balanced
+        let white_to_move =
+            Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4")
+                .unwrap();
+        let black_to_move =
+            Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4")
+                .unwrap();
+
+        let bonus = tempo(&white_to_move, eval_params());
+        assert!(bonus >= TEMPO_BONUS);
+        assert_eq!(tempo(&black_to_move, eval_params()), bonus);
+        assert_eq!(evaluate_white_pov(&white_to_move), bonus);
+        assert_eq!(evaluate_white_pov(&black_to_move), -bonus);
+        // the side to move is slightly ahead, whichever it is
+        assert_eq!(evaluate_stm(&white_to_move), bonus);
+        assert_eq!(evaluate_stm(&black_to_move), bonus);
+    }
+
+    #[test]
+    fn test_knight_outpost() {
+        // Nd5 defended by e4, black has no c pawn left behind to chase it (it is already on c5)
+        let outpost = "4k3/pp3ppp/3p4/2pNp3/4P3/8/PPP2PPP/4K3 w - - 0 1";
+        // same material but c7-c6 can still kick the knight
+        let contestable = "4k3/ppp2ppp/3p4/3Np3/4P3/8/PPP2PPP/4K3 w - - 0 1";
+
+        let board = Board::from_str(outpost).unwrap();
+        assert_eq!(
+            knight_outposts(&board, Color::White, eval_params()),
+            KNIGHT_OUTPOST_BONUS
+        );
+        let board = Board::from_str(contestable).unwrap();
+        assert_eq!(knight_outposts(&board, Color::White, eval_params()), 0);
+        assert!(eval_fen(outpost) > eval_fen(contestable));
 
-    delta_queen_p + delta_rook_p + delta_bishop_p + delta_knight_p + delta_pawn_p + delta_king_p
+        // mirrored for black, Nd4 defended by e5
+        let black = Board::from_str("4k3/ppp2ppp/8/4p3/2Pn4/3P4/PP3PPP/4K3 b - - 0 1").unwrap();
+        assert_eq!(
+            knight_outposts(&black, Color::Black, eval_params()),
+            KNIGHT_OUTPOST_BONUS
+        );
+        // an undefended knight isn't on an outpost
+        let undefended = Board::from_str("4k3/pp3ppp/3p4/2pN4/8/8/PPP2PPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(knight_outposts(&undefended, Color::White, eval_params()), 0);
+    }
 }
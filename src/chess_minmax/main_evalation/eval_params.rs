@@ -0,0 +1,298 @@
+use super::piece_square_tables::*;
+use super::*;
+
+use std::sync::OnceLock;
+
+static EVAL_PARAMS: OnceLock<EvalParams> = OnceLock::new();
+
+/// Weights of every evaluation term, the defaults are the constants of `main_evalation`
+/// and the tables of `piece_square_tables`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    /// value of each piece, indexed by `Piece::to_index` (king is never traded, counted as 0)
+    pub material: [i16; 6],
+    pub pawn_table: [i16; 64],
+    pub knight_table: [i16; 64],
+    pub bishop_table: [i16; 64],
+    pub rook_table: [i16; 64],
+    pub queen_table: [i16; 64],
+    pub king_middle_table: [i16; 64],
+    pub king_endgame_table: [i16; 64],
+    pub rook_on_seventh_bonus: i16,
+    pub doubled_on_seventh_bonus: i16,
+    pub connected_rooks_bonus: i16,
+    pub neutral_closedness: i16,
+    pub minor_closedness_weight: i16,
+    pub developed_minor_bonus: i16,
+    pub undeveloped_minor_penalty: i16,
+    pub early_queen_penalty: i16,
+    pub king_zone_attack_weight: [i16; 6],
+    pub king_attacker_scale: [i16; 8],
+    pub exchange_openness_weight: i16,
+    pub tempo_bonus: i16,
+    pub sharp_tempo_weight: i16,
+    pub max_sharp_moves: i16,
+    pub bishop_pair_bonus: i16,
+    pub mobility_weight_middle: i16,
+    pub mobility_weight_end: i16,
+    pub doubled_pawn_penalty: i16,
+    pub isolated_pawn_penalty: i16,
+    pub passed_pawn_bonus: [i16; 8],
+    pub knight_outpost_bonus: i16,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        let mut material = PIECE_VALUE;
+        material[Piece::King.to_index()] = 0;
+
+        EvalParams {
+            material,
+            pawn_table: PAWN_TABLE,
+            knight_table: KNIGHT_TABLE,
+            bishop_table: BISHOP_TABLE,
+            rook_table: ROOK_TABLE,
+            queen_table: QUEEN_TABLE,
+            king_middle_table: KING_MIDDLE_TABLE,
+            king_endgame_table: KING_ENDGAME_TABLE,
+            rook_on_seventh_bonus: ROOK_ON_SEVENTH_BONUS,
+            doubled_on_seventh_bonus: DOUBLED_ON_SEVENTH_BONUS,
+            connected_rooks_bonus: CONNECTED_ROOKS_BONUS,
+            neutral_closedness: NEUTRAL_CLOSEDNESS,
+            minor_closedness_weight: MINOR_CLOSEDNESS_WEIGHT,
+            developed_minor_bonus: DEVELOPED_MINOR_BONUS,
+            undeveloped_minor_penalty: UNDEVELOPED_MINOR_PENALTY,
+            early_queen_penalty: EARLY_QUEEN_PENALTY,
+            king_zone_attack_weight: KING_ZONE_ATTACK_WEIGHT,
+            king_attacker_scale: KING_ATTACKER_SCALE,
+            exchange_openness_weight: EXCHANGE_OPENNESS_WEIGHT,
+            tempo_bonus: TEMPO_BONUS,
+            sharp_tempo_weight: SHARP_TEMPO_WEIGHT,
+            max_sharp_moves: MAX_SHARP_MOVES,
+            bishop_pair_bonus: BISHOP_PAIR_BONUS,
+            mobility_weight_middle: MOBILITY_WEIGHT_MIDDLE,
+            mobility_weight_end: MOBILITY_WEIGHT_END,
+            doubled_pawn_penalty: DOUBLED_PAWN_PENALTY,
+            isolated_pawn_penalty: ISOLATED_PAWN_PENALTY,
+            passed_pawn_bonus: PASSED_PAWN_BONUS,
+            knight_outpost_bonus: KNIGHT_OUTPOST_BONUS,
+        }
+    }
+}
+
+impl EvalParams {
+    /// Every parameter by name, a single value is a slice of length 1
+    fn fields_mut(&mut self) -> Vec<(&'static str, &mut [i16])> {
+        use std::slice::from_mut;
+        vec![
+            ("material", &mut self.material[..]),
+            ("pawn_table", &mut self.pawn_table[..]),
+            ("knight_table", &mut self.knight_table[..]),
+            ("bishop_table", &mut self.bishop_table[..]),
+            ("rook_table", &mut self.rook_table[..]),
+            ("queen_table", &mut self.queen_table[..]),
+            ("king_middle_table", &mut self.king_middle_table[..]),
+            ("king_endgame_table", &mut self.king_endgame_table[..]),
+            (
+                "rook_on_seventh_bonus",
+                from_mut(&mut self.rook_on_seventh_bonus),
+            ),
+            (
+                "doubled_on_seventh_bonus",
+                from_mut(&mut self.doubled_on_seventh_bonus),
+            ),
+            (
+                "connected_rooks_bonus",
+                from_mut(&mut self.connected_rooks_bonus),
+            ),
+            ("neutral_closedness", from_mut(&mut self.neutral_closedness)),
+            (
+                "minor_closedness_weight",
+                from_mut(&mut self.minor_closedness_weight),
+            ),
+            (
+                "developed_minor_bonus",
+                from_mut(&mut self.developed_minor_bonus),
+            ),
+            (
+                "undeveloped_minor_penalty",
+                from_mut(&mut self.undeveloped_minor_penalty),
+            ),
+            (
+                "early_queen_penalty",
+                from_mut(&mut self.early_queen_penalty),
+            ),
+            (
+                "king_zone_attack_weight",
+                &mut self.king_zone_attack_weight[..],
+            ),
+            ("king_attacker_scale", &mut self.king_attacker_scale[..]),
+            (
+                "exchange_openness_weight",
+                from_mut(&mut self.exchange_openness_weight),
+            ),
+            ("tempo_bonus", from_mut(&mut self.tempo_bonus)),
+            ("sharp_tempo_weight", from_mut(&mut self.sharp_tempo_weight)),
+            ("max_sharp_moves", from_mut(&mut self.max_sharp_moves)),
+            ("bishop_pair_bonus", from_mut(&mut self.bishop_pair_bonus)),
+            (
+                "mobility_weight_middle",
+                from_mut(&mut self.mobility_weight_middle),
+            ),
+            (
+                "mobility_weight_end",
+                from_mut(&mut self.mobility_weight_end),
+            ),
+            (
+                "doubled_pawn_penalty",
+                from_mut(&mut self.doubled_pawn_penalty),
+            ),
+            (
+                "isolated_pawn_penalty",
+                from_mut(&mut self.isolated_pawn_penalty),
+            ),
+            ("passed_pawn_bonus", &mut self.passed_pawn_bonus[..]),
+            (
+                "knight_outpost_bonus",
+                from_mut(&mut self.knight_outpost_bonus),
+            ),
+        ]
+    }
+
+    /// Parse `name = value` or `name = [value, ...]` lines (a subset of TOML),
+    /// parameters not in the text keep their default value.
+    ///
+    /// Blank line and `#` comment are skipped, an array may span several lines.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut params = EvalParams::default();
+        let mut fields = params.fields_mut();
+
+        // join the lines of multi-line arrays into a single entry
+        let mut entries: Vec<(usize, String)> = Vec::new();
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            match entries.last_mut() {
+                Some((_, entry)) if is_open_array(entry) => {
+                    entry.push(' ');
+                    entry.push_str(line);
+                }
+                _ => entries.push((line_num + 1, line.to_string())),
+            }
+        }
+
+        for (line_num, entry) in entries {
+            let (name, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected <name> = <value>", line_num))?;
+            let name = name.trim();
+            let field = fields
+                .iter_mut()
+                .find(|(field_name, _)| *field_name == name)
+                .map(|(_, field)| field)
+                .ok_or_else(|| format!("line {}: unknown parameter \"{}\"", line_num, name))?;
+
+            let value = value.trim();
+            let values = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(array) => array
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .collect::<Vec<_>>(),
+                None => vec![value],
+            };
+            if values.len() != field.len() {
+                return Err(format!(
+                    "line {}: {} expect {} value(s), got {}",
+                    line_num,
+                    name,
+                    field.len(),
+                    values.len()
+                ));
+            }
+            for (slot, text) in field.iter_mut().zip(values) {
+                *slot = text
+                    .parse()
+                    .map_err(|e| format!("line {}: {} \"{}\": {}", line_num, name, text, e))?;
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// Every parameter in the format read by `parse`, the tables one rank per line from rank 1
+    pub fn to_toml(&self) -> String {
+        let mut params = self.clone();
+        let mut text = String::new();
+        for (name, values) in params.fields_mut() {
+            let values = values.iter().map(i16::to_string).collect::<Vec<_>>();
+            match values.len() {
+                1 => text += &format!("{} = {}\n", name, values[0]),
+                64 => {
+                    text += &format!("{} = [\n", name);
+                    for rank in values.chunks(8) {
+                        text += &format!("    {},\n", rank.join(", "));
+                    }
+                    text += "]\n";
+                }
+                _ => text += &format!("{} = [{}]\n", name, values.join(", ")),
+            }
+        }
+        text
+    }
+}
+
+fn is_open_array(entry: &str) -> bool {
+    entry.contains('[') && !entry.contains(']')
+}
+
+/// Parameters used by the evaluation, the defaults unless `set_eval_params` was called first
+pub fn eval_params() -> &'static EvalParams {
+    EVAL_PARAMS.get_or_init(EvalParams::default)
+}
+
+/// Use `params` for the rest of the program, fail if the evaluation already ran
+pub fn set_eval_params(params: EvalParams) -> Result<(), String> {
+    EVAL_PARAMS
+        .set(params)
+        .map_err(|_| "evaluation parameters are already in use".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvalParams;
+
+    #[test]
+    fn test_parse_eval_params() {
+        let text = "
+            # comment
+            bishop_pair_bonus = 45
+            passed_pawn_bonus = [0, 1, 2, 3,
+                                 4, 5, 6, 0]  # trailing comment
+        ";
+        let params = EvalParams::parse(text).unwrap();
+        assert_eq!(params.bishop_pair_bonus, 45);
+        assert_eq!(params.passed_pawn_bonus, [0, 1, 2, 3, 4, 5, 6, 0]);
+        // untouched parameters keep their default
+        assert_eq!(params.material, EvalParams::default().material);
+
+        assert!(EvalParams::parse("no_such_weight = 1").is_err());
+        assert!(EvalParams::parse("material = [1, 2]").is_err());
+        assert!(EvalParams::parse("tempo_bonus = ten").is_err());
+        assert!(EvalParams::parse("tempo_bonus").is_err());
+    }
+
+    #[test]
+    fn test_eval_params_round_trip() {
+        let mut params = EvalParams::default();
+        params.knight_table[27] = 99;
+        params.tempo_bonus = -3;
+        assert_eq!(EvalParams::parse(&params.to_toml()), Ok(params));
+    }
+}
@@ -0,0 +1,187 @@
+use chess::{Board, CastleRights, ChessMove, Color};
+
+use std::sync::OnceLock;
+
+static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+
+/// Score of a tablebase win, below any checkmate score so a mate the search found is still
+/// preferred (and well above any evaluation)
+pub const TABLEBASE_WIN_SCORE: i16 = 15000;
+
+/// Result of a tablebase position for the side to move, with best play.
+///
+/// Cursed win and blessed loss (only decided after the fifty-move rule draw) count as draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "syzygy"), allow(dead_code))]
+pub enum Wdl {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Wdl {
+    /// Search score `ply` ply from the root, a win sooner (and a loss later) is better
+    pub fn score(self, ply: usize, draw_score: i16) -> i16 {
+        let win = TABLEBASE_WIN_SCORE - ply.min(1000) as i16;
+        match self {
+            Wdl::Win => win,
+            Wdl::Draw => draw_score,
+            Wdl::Loss => -win,
+        }
+    }
+}
+
+/// Syzygy endgame tablebases, needs the `syzygy` feature
+#[cfg(feature = "syzygy")]
+pub struct Tablebase {
+    tables: shakmaty_syzygy::Tablebase<shakmaty::Chess>,
+}
+
+#[cfg(feature = "syzygy")]
+impl Tablebase {
+    /// Open every table of `dir`, fail if there is none
+    pub fn open(dir: &str) -> Result<Self, String> {
+        let mut tables = shakmaty_syzygy::Tablebase::new();
+        let count = tables
+            .add_directory(dir)
+            .map_err(|e| format!("{}: {}", dir, e))?;
+        if count == 0 {
+            return Err(format!("{}: no Syzygy table found", dir));
+        }
+        Ok(Tablebase { tables })
+    }
+
+    pub fn max_pieces(&self) -> u32 {
+        self.tables.max_pieces() as u32
+    }
+
+    /// Result of `board`, which must have been reached by a capture or pawn move
+    /// (otherwise the fifty-move rule may change it). None when the table is missing.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        let position = to_position(board, 0)?;
+        let wdl = self.tables.probe_wdl_after_zeroing(&position).ok()?;
+        Some(wdl.into())
+    }
+
+    /// Move keeping the best result of `board` (quickest to a capture or pawn move when winning)
+    /// and that result, None when the table is missing
+    pub fn best_move(&self, board: &Board, halfmove_clock: u16) -> Option<(ChessMove, Wdl)> {
+        if !self.covers(board) {
+            return None;
+        }
+        let position = to_position(board, halfmove_clock)?;
+        let wdl = self.tables.probe_wdl(&position).ok()?.after_zeroing();
+        let (mov, _) = self.tables.best_move(&position).ok()??;
+        let mov = mov.to_uci(shakmaty::CastlingMode::Standard).to_string();
+        Some((mov.parse().ok()?, wdl.into()))
+    }
+}
+
+#[cfg(feature = "syzygy")]
+impl From<shakmaty_syzygy::Wdl> for Wdl {
+    fn from(wdl: shakmaty_syzygy::Wdl) -> Self {
+        use shakmaty_syzygy::Wdl as Syzygy;
+        match wdl {
+            Syzygy::Loss => Wdl::Loss,
+            Syzygy::BlessedLoss | Syzygy::Draw | Syzygy::CursedWin => Wdl::Draw,
+            Syzygy::Win => Wdl::Win,
+        }
+    }
+}
+
+/// `board` as a shakmaty position (through FEN), with the given halfmove clock
+#[cfg(feature = "syzygy")]
+fn to_position(board: &Board, halfmove_clock: u16) -> Option<shakmaty::Chess> {
+    // the `chess` FEN always end with "0 1"
+    let placement = board
+        .to_string()
+        .split(' ')
+        .take(4)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let fen: shakmaty::fen::Fen = format!("{} {} 1", placement, halfmove_clock).parse().ok()?;
+    fen.into_position(shakmaty::CastlingMode::Standard).ok()
+}
+
+/// Stand-in when built without the `syzygy` feature, can never be opened
+#[cfg(not(feature = "syzygy"))]
+#[allow(dead_code)]
+pub struct Tablebase;
+
+#[cfg(not(feature = "syzygy"))]
+impl Tablebase {
+    pub fn open(_dir: &str) -> Result<Self, String> {
+        Err("built without the `syzygy` feature".to_string())
+    }
+
+    pub fn max_pieces(&self) -> u32 {
+        0
+    }
+
+    pub fn probe_wdl(&self, _board: &Board) -> Option<Wdl> {
+        None
+    }
+
+    pub fn best_move(&self, _board: &Board, _halfmove_clock: u16) -> Option<(ChessMove, Wdl)> {
+        None
+    }
+}
+
+impl Tablebase {
+    /// Whether `board` may be in the tables (few enough pieces and no castling right),
+    /// checked before the much slower probe
+    #[cfg_attr(not(feature = "syzygy"), allow(dead_code))]
+    pub fn covers(&self, board: &Board) -> bool {
+        board.combined().popcnt() <= self.max_pieces()
+            && board.castle_rights(Color::White) == CastleRights::NoRights
+            && board.castle_rights(Color::Black) == CastleRights::NoRights
+    }
+}
+
+/// Tablebases probed by the search, None unless `set_tablebase` was called
+pub fn tablebase() -> Option<&'static Tablebase> {
+    TABLEBASE.get()
+}
+
+/// Probe `tablebase` for the rest of the program
+pub fn set_tablebase(tablebase: Tablebase) -> Result<(), String> {
+    TABLEBASE
+        .set(tablebase)
+        .map_err(|_| "a tablebase is already in use".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tablebase, Wdl, TABLEBASE_WIN_SCORE};
+
+    #[test]
+    fn test_wdl_score() {
+        assert_eq!(Wdl::Win.score(0, 0), TABLEBASE_WIN_SCORE);
+        assert!(Wdl::Win.score(2, 0) > Wdl::Win.score(5, 0));
+        assert!(Wdl::Loss.score(2, 0) < Wdl::Loss.score(5, 0));
+        assert_eq!(Wdl::Draw.score(3, -20), -20);
+        assert_eq!(Wdl::Loss.score(3, 0), -Wdl::Win.score(3, 0));
+    }
+
+    #[test]
+    fn test_open_missing_tablebase() {
+        assert!(Tablebase::open("asset/no_such_tablebase").is_err());
+    }
+
+    #[cfg(feature = "syzygy")]
+    #[test]
+    fn test_to_position() {
+        use chess::Board;
+        use shakmaty::Position;
+        use std::str::FromStr;
+
+        let board = Board::from_str("8/8/4k3/8/8/3QK3/8/8 b - - 0 1").unwrap();
+        let position = super::to_position(&board, 37).unwrap();
+        assert_eq!(position.halfmoves(), 37);
+        assert_eq!(position.turn(), shakmaty::Color::Black);
+        assert_eq!(position.board().occupied().count(), 3);
+    }
+}
@@ -0,0 +1,673 @@
+use chess::{Board, BoardStatus, ChessMove, Color, File, MoveGen, Piece, Square, EMPTY};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Reason a coordinate move (e.g. `e2e4`, `e7e8q`) can't be played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveParseError {
+    /// not 4 or 5 characters long
+    BadLength(String),
+    InvalidSquare(String),
+    InvalidPromotion(char),
+    NoPiece(Square),
+    WrongSide(Square, Color),
+    MissingPromotion,
+    Illegal(ChessMove),
+}
+
+impl fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveParseError::BadLength(text) => write!(
+                f,
+                "\"{}\" is not a coordinate move (expected e.g. e2e4 or e7e8q)",
+                text
+            ),
+            MoveParseError::InvalidSquare(square) => write!(f, "\"{}\" is not a square", square),
+            MoveParseError::InvalidPromotion(c) => {
+                write!(
+                    f,
+                    "'{}' is not a promotion piece (expected q, r, b or n)",
+                    c
+                )
+            }
+            MoveParseError::NoPiece(square) => write!(f, "no piece on {}", square),
+            MoveParseError::WrongSide(square, color) => write!(
+                f,
+                "piece on {} belong to {:?} but it is {:?} turn",
+                square, color, !*color
+            ),
+            MoveParseError::MissingPromotion => write!(f, "promotion piece is required"),
+            MoveParseError::Illegal(mov) => write!(f, "{} is not a legal move", mov),
+        }
+    }
+}
+
+/// Parse coordinate notation move (`e2e4`, `e7e8q`) and check that it is legal on `board`
+pub fn parse_coordinate_move(text: &str, board: &Board) -> Result<ChessMove, MoveParseError> {
+    let text = text.trim();
+    if !text.is_ascii() || (text.len() != 4 && text.len() != 5) {
+        return Err(MoveParseError::BadLength(text.to_string()));
+    }
+
+    let parse_square = |square: &str| {
+        Square::from_str(square).map_err(|_| MoveParseError::InvalidSquare(square.to_string()))
+    };
+    let source = parse_square(&text[0..2])?;
+    let dest = parse_square(&text[2..4])?;
+
+    let promotion = match text[4..].chars().next().map(|c| c.to_ascii_lowercase()) {
+        None => None,
+        Some('q') => Some(Piece::Queen),
+        Some('r') => Some(Piece::Rook),
+        Some('b') => Some(Piece::Bishop),
+        Some('n') => Some(Piece::Knight),
+        Some(c) => return Err(MoveParseError::InvalidPromotion(c)),
+    };
+
+    match board.color_on(source) {
+        None => return Err(MoveParseError::NoPiece(source)),
+        Some(color) if color != board.side_to_move() => {
+            return Err(MoveParseError::WrongSide(source, color))
+        }
+        Some(_) => {}
+    }
+
+    let mov = ChessMove::new(source, dest, promotion);
+    if board.legal(mov) {
+        return Ok(mov);
+    }
+
+    let reach_last_rank = dest.get_rank() == board.side_to_move().to_their_backrank();
+    let is_pawn = board.piece_on(source) == Some(Piece::Pawn);
+    if promotion.is_none() && is_pawn && reach_last_rank {
+        let promoted = ChessMove::new(source, dest, Some(Piece::Queen));
+        if board.legal(promoted) {
+            return Err(MoveParseError::MissingPromotion);
+        }
+    }
+
+    Err(MoveParseError::Illegal(mov))
+}
+
+/// Verification result of a single move in a move list
+pub struct MoveCheck {
+    /// 1-based index of the move in the list
+    pub ply: usize,
+    pub text: String,
+    pub result: Result<ChessMove, String>,
+}
+
+/// Replay space-separated coordinate moves (`e2e4 e7e5 ...`) from `board`,
+/// stop at the first illegal move.
+///
+/// Every move checked is reported, so the last entry tell why the sequence is illegal (if it is).
+pub fn verify_move_sequence(board: &Board, move_list: &str) -> Vec<MoveCheck> {
+    let mut board = *board;
+    let mut checks = Vec::new();
+
+    for (i, text) in move_list.split_whitespace().enumerate() {
+        let result = check_move(&board, text);
+
+        if let Ok(mov) = result {
+            board = board.make_move_new(mov);
+        }
+
+        let is_legal = result.is_ok();
+        checks.push(MoveCheck {
+            ply: i + 1,
+            text: text.to_string(),
+            result,
+        });
+
+        if !is_legal {
+            break;
+        }
+    }
+
+    checks
+}
+
+fn check_move(board: &Board, text: &str) -> Result<ChessMove, String> {
+    if board.status() != BoardStatus::Ongoing {
+        return Err(format!("game already ended ({:?})", board.status()));
+    }
+
+    parse_coordinate_move(text, board).map_err(|e| e.to_string())
+}
+
+/// Parse FEN that may be truncated after any field (e.g. only the piece placement),
+/// missing fields default to white to move, castling for every king and rook still
+/// on its home square, no en passant and zeroed clocks.
+///
+/// Also return `field: value` of every field that was defaulted.
+pub fn parse_fen(text: &str) -> Result<(Board, Vec<String>), String> {
+    const FIELD_NAMES: [&str; 6] = [
+        "piece placement",
+        "side to move",
+        "castling",
+        "en passant",
+        "halfmove clock",
+        "fullmove number",
+    ];
+
+    let mut fields = text.split_whitespace().collect::<Vec<_>>();
+    if fields.is_empty() {
+        return Err("empty FEN".to_string());
+    }
+    if fields.len() > FIELD_NAMES.len() {
+        return Err(format!(
+            "\"{}\" has more than {} fields",
+            text.trim(),
+            FIELD_NAMES.len()
+        ));
+    }
+
+    // chess crate panic on some malformed placement, check it first
+    check_placement(fields[0])?;
+
+    let given = fields.len();
+    let castling = if given <= 2 {
+        let side = fields.get(1).copied().unwrap_or("w");
+        let placement_only = Board::from_str(&format!("{} {} - - 0 1", fields[0], side))
+            .map_err(|e| e.to_string())?;
+        home_castle_rights(&placement_only)
+    } else {
+        String::new()
+    };
+
+    let defaults = ["", "w", &castling, "-", "0", "1"];
+    fields.extend_from_slice(&defaults[given..]);
+    let board = Board::from_str(&fields.join(" ")).map_err(|e| e.to_string())?;
+
+    let defaulted = (given..FIELD_NAMES.len())
+        .map(|i| format!("{}: {}", FIELD_NAMES[i], fields[i]))
+        .collect();
+    Ok((board, defaulted))
+}
+
+/// Halfmove clock field of a FEN (`Board` doesn't keep it), 0 if missing or invalid
+pub fn fen_halfmove_clock(text: &str) -> u16 {
+    text.split_whitespace()
+        .nth(4)
+        .and_then(|clock| clock.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Error if the FEN piece placement isn't 8 ranks of 8 squares
+fn check_placement(placement: &str) -> Result<(), String> {
+    let ranks = placement.split('/').collect::<Vec<_>>();
+    if ranks.len() != 8 {
+        return Err(format!(
+            "\"{}\" has {} ranks instead of 8",
+            placement,
+            ranks.len()
+        ));
+    }
+
+    for rank in ranks {
+        let mut files = 0;
+        for c in rank.chars() {
+            files += match c {
+                '1'..='8' => c.to_digit(10).unwrap(),
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => 1,
+                _ => return Err(format!("'{}' is not a piece in \"{}\"", c, placement)),
+            };
+        }
+        if files != 8 {
+            return Err(format!("rank \"{}\" doesn't have 8 squares", rank));
+        }
+    }
+
+    Ok(())
+}
+
+/// FEN castling field allowing every castle whose king and rook are on their home square
+fn home_castle_rights(board: &Board) -> String {
+    let on = |square, piece, color| {
+        board.piece_on(square) == Some(piece) && board.color_on(square) == Some(color)
+    };
+
+    let rights = [
+        (Color::White, Square::E1, Square::H1, 'K'),
+        (Color::White, Square::E1, Square::A1, 'Q'),
+        (Color::Black, Square::E8, Square::H8, 'k'),
+        (Color::Black, Square::E8, Square::A8, 'q'),
+    ]
+    .iter()
+    .filter(|(color, king, rook, _)| {
+        on(*king, Piece::King, *color) && on(*rook, Piece::Rook, *color)
+    })
+    .map(|(_, _, _, right)| *right)
+    .collect::<String>();
+
+    if rights.is_empty() {
+        "-".to_string()
+    } else {
+        rights
+    }
+}
+
+/// Standard algebraic notation of legal `mov` on `board` (e.g. `Nbd7`, `exd6`, `e8=Q+`, `O-O`)
+pub fn san(board: &Board, mov: ChessMove) -> String {
+    let source = mov.get_source();
+    let dest = mov.get_dest();
+    let piece = board.piece_on(source).expect("move source has a piece");
+
+    let mut out = if piece == Piece::King
+        && source
+            .get_file()
+            .to_index()
+            .abs_diff(dest.get_file().to_index())
+            == 2
+    {
+        if dest.get_file() == File::G {
+            "O-O".to_string()
+        } else {
+            "O-O-O".to_string()
+        }
+    } else {
+        // a pawn moving to another file always capture (maybe en passant)
+        let is_capture = board.piece_on(dest).is_some()
+            || (piece == Piece::Pawn && source.get_file() != dest.get_file());
+        let mut out = String::new();
+
+        if piece == Piece::Pawn {
+            if is_capture {
+                out.push(file_char(source.get_file()));
+            }
+        } else {
+            out.push_str(&piece.to_string(Color::White));
+
+            // other piece of the same kind that can also reach `dest`
+            let rivals = MoveGen::new_legal(board)
+                .filter(|other| {
+                    other.get_dest() == dest
+                        && other.get_source() != source
+                        && board.piece_on(other.get_source()) == Some(piece)
+                })
+                .map(|other| other.get_source())
+                .collect::<Vec<_>>();
+            if !rivals.is_empty() {
+                if rivals
+                    .iter()
+                    .all(|rival| rival.get_file() != source.get_file())
+                {
+                    out.push(file_char(source.get_file()));
+                } else if rivals
+                    .iter()
+                    .all(|rival| rival.get_rank() != source.get_rank())
+                {
+                    out.push_str(&(source.get_rank().to_index() + 1).to_string());
+                } else {
+                    out.push_str(&source.to_string());
+                }
+            }
+        }
+
+        if is_capture {
+            out.push('x');
+        }
+        out.push_str(&dest.to_string());
+        if let Some(promotion) = mov.get_promotion() {
+            out.push('=');
+            out.push_str(&promotion.to_string(Color::White));
+        }
+        out
+    };
+
+    let after = board.make_move_new(mov);
+    if after.status() == BoardStatus::Checkmate {
+        out.push('#');
+    } else if *after.checkers() != EMPTY {
+        out.push('+');
+    }
+    out
+}
+
+/// Reason a SAN move (e.g. `Nbd7`, `exd6`, `O-O`) can't be played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanParseError {
+    Invalid(String),
+    Illegal(String),
+    /// more than one legal move match, the disambiguation is missing
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanParseError::Invalid(text) => write!(f, "\"{}\" is not a SAN move", text),
+            SanParseError::Illegal(text) => write!(f, "{} is not a legal move", text),
+            SanParseError::Ambiguous(text) => {
+                write!(f, "{} is ambiguous (more than one piece can make it)", text)
+            }
+        }
+    }
+}
+
+/// Parse standard algebraic notation move and resolve it to a legal move on `board`,
+/// check/mate and annotation suffix (`+`, `#`, `!`, `?`) are ignored
+pub fn parse_san(text: &str, board: &Board) -> Result<ChessMove, SanParseError> {
+    let text = text.trim();
+    let invalid = || SanParseError::Invalid(text.to_string());
+    let body = text.trim_end_matches(['+', '#', '!', '?']);
+    if !body.is_ascii() {
+        return Err(invalid());
+    }
+
+    let legal = MoveGen::new_legal(board);
+    let candidates = match body {
+        "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+            let file = if body.len() == 3 { File::G } else { File::C };
+            legal
+                .filter(|mov| {
+                    board.piece_on(mov.get_source()) == Some(Piece::King)
+                        && mov.get_source().get_file() == File::E
+                        && mov.get_dest().get_file() == file
+                })
+                .collect::<Vec<_>>()
+        }
+        _ => {
+            let (piece, rest) = match body.chars().next().and_then(san_piece) {
+                Some(piece) => (piece, &body[1..]),
+                None => (Piece::Pawn, body),
+            };
+            let (rest, promotion) = match rest.rsplit_once('=') {
+                Some((rest, promotion)) => {
+                    let mut chars = promotion.chars();
+                    match (chars.next().and_then(san_piece), chars.next()) {
+                        (Some(promotion), None) => (rest, Some(promotion)),
+                        _ => return Err(invalid()),
+                    }
+                }
+                None => (rest, None),
+            };
+
+            let rest = rest.replace('x', "");
+            if rest.len() < 2 || rest.len() > 4 {
+                return Err(invalid());
+            }
+            let (hint, dest) = rest.split_at(rest.len() - 2);
+            let dest = Square::from_str(dest).map_err(|_| invalid())?;
+
+            // disambiguation, source file and/or rank
+            let mut source_file = None;
+            let mut source_rank = None;
+            for c in hint.chars() {
+                match c {
+                    'a'..='h' if source_file.is_none() => source_file = Some(c),
+                    '1'..='8' if source_rank.is_none() => source_rank = Some(c),
+                    _ => return Err(invalid()),
+                }
+            }
+
+            legal
+                .filter(|mov| {
+                    let source = mov.get_source();
+                    let square = source.to_string();
+                    board.piece_on(source) == Some(piece)
+                        && mov.get_dest() == dest
+                        && mov.get_promotion() == promotion
+                        && source_file.is_none_or(|file| square.starts_with(file))
+                        && source_rank.is_none_or(|rank| square.ends_with(rank))
+                })
+                .collect::<Vec<_>>()
+        }
+    };
+
+    match candidates.as_slice() {
+        [mov] => Ok(*mov),
+        [] => Err(SanParseError::Illegal(text.to_string())),
+        _ => Err(SanParseError::Ambiguous(text.to_string())),
+    }
+}
+
+/// Piece of SAN piece letter, pawn have no letter
+fn san_piece(c: char) -> Option<Piece> {
+    match c {
+        'K' => Some(Piece::King),
+        'Q' => Some(Piece::Queen),
+        'R' => Some(Piece::Rook),
+        'B' => Some(Piece::Bishop),
+        'N' => Some(Piece::Knight),
+        _ => None,
+    }
+}
+
+fn file_char(file: File) -> char {
+    (b'a' + file.to_index() as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_coordinate_move, parse_fen, parse_san, san, verify_move_sequence, MoveParseError,
+        SanParseError,
+    };
+    use chess::{Board, CastleRights, ChessMove, Color, MoveGen, Piece, Square};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_verify_legal_sequence() {
+        let checks = verify_move_sequence(&Board::default(), "e2e4 e7e5 g1f3 b8c6");
+
+        assert_eq!(checks.len(), 4);
+        assert!(checks.iter().all(|check| check.result.is_ok()));
+    }
+
+    #[test]
+    fn test_verify_stop_at_illegal_move() {
+        let checks = verify_move_sequence(&Board::default(), "e2e4 e7e5 e4e5 g1f3");
+
+        assert_eq!(checks.len(), 3);
+        assert!(checks[..2].iter().all(|check| check.result.is_ok()));
+        assert_eq!(checks[2].ply, 3);
+        assert!(checks[2].result.is_err());
+    }
+
+    #[test]
+    fn test_verify_wrong_side_and_malformed() {
+        let wrong_side = verify_move_sequence(&Board::default(), "e7e5");
+        assert!(wrong_side[0].result.as_ref().unwrap_err().contains("turn"));
+
+        let malformed = verify_move_sequence(&Board::default(), "e2-e4");
+        assert!(malformed[0].result.is_err());
+    }
+
+    #[test]
+    fn test_verify_from_fen() {
+        let board = Board::from_str("7k/8/8/8/8/8/8/K5R1 w - - 0 1").unwrap();
+        let checks = verify_move_sequence(&board, "g1g7 h8g7 a1b1");
+
+        assert_eq!(checks.len(), 3);
+        assert!(checks.iter().all(|check| check.result.is_ok()));
+    }
+
+    #[test]
+    fn test_parse_valid_move() {
+        let board = Board::default();
+
+        assert_eq!(
+            parse_coordinate_move("e2e4", &board),
+            Ok(ChessMove::new(Square::E2, Square::E4, None))
+        );
+        assert_eq!(
+            parse_coordinate_move(" g1f3\n", &board),
+            Ok(ChessMove::new(Square::G1, Square::F3, None))
+        );
+    }
+
+    #[test]
+    fn test_parse_promotion() {
+        let board = Board::from_str("8/4P2k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+
+        assert_eq!(
+            parse_coordinate_move("e7e8q", &board),
+            Ok(ChessMove::new(Square::E7, Square::E8, Some(Piece::Queen)))
+        );
+        assert_eq!(
+            parse_coordinate_move("e7e8N", &board),
+            Ok(ChessMove::new(Square::E7, Square::E8, Some(Piece::Knight)))
+        );
+        assert_eq!(
+            parse_coordinate_move("e7e8", &board),
+            Err(MoveParseError::MissingPromotion)
+        );
+        assert_eq!(
+            parse_coordinate_move("e7e8k", &board),
+            Err(MoveParseError::InvalidPromotion('k'))
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        let board = Board::default();
+
+        assert!(matches!(
+            parse_coordinate_move("e2e4e4", &board),
+            Err(MoveParseError::BadLength(_))
+        ));
+        assert!(matches!(
+            parse_coordinate_move("e2-e4", &board),
+            Err(MoveParseError::InvalidSquare(_))
+        ));
+        assert!(matches!(
+            parse_coordinate_move("", &board),
+            Err(MoveParseError::BadLength(_))
+        ));
+        assert_eq!(
+            parse_coordinate_move("i2e4", &board),
+            Err(MoveParseError::InvalidSquare("i2".to_string()))
+        );
+        assert_eq!(
+            parse_coordinate_move("e2e9", &board),
+            Err(MoveParseError::InvalidSquare("e9".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_illegal_but_well_formed() {
+        let board = Board::default();
+
+        assert_eq!(
+            parse_coordinate_move("e3e4", &board),
+            Err(MoveParseError::NoPiece(Square::E3))
+        );
+        assert_eq!(
+            parse_coordinate_move("e7e5", &board),
+            Err(MoveParseError::WrongSide(Square::E7, Color::Black))
+        );
+        assert_eq!(
+            parse_coordinate_move("e2e5", &board),
+            Err(MoveParseError::Illegal(ChessMove::new(
+                Square::E2,
+                Square::E5,
+                None
+            )))
+        );
+        // promotion suffix on non-promotion move
+        assert!(matches!(
+            parse_coordinate_move("e2e4q", &board),
+            Err(MoveParseError::Illegal(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_full_fen() {
+        let (board, defaulted) =
+            parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+
+        assert_eq!(board, Board::default());
+        assert!(defaulted.is_empty());
+    }
+
+    #[test]
+    fn test_parse_board_only_fen() {
+        let (board, defaulted) = parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").unwrap();
+        assert_eq!(board, Board::default());
+        assert_eq!(defaulted.len(), 5);
+        assert_eq!(defaulted[1], "castling: KQkq");
+
+        // only castling with king and rook at home, side to move given
+        let (board, defaulted) = parse_fen("r3k3/8/8/8/8/8/8/4K2R b").unwrap();
+        assert_eq!(board.side_to_move(), Color::Black);
+        assert_eq!(defaulted[0], "castling: Kq");
+
+        let (board, _) = parse_fen("4k3/8/8/8/8/8/8/R3K2R w - -").unwrap();
+        assert_eq!(board.castle_rights(Color::White), CastleRights::NoRights);
+    }
+
+    #[test]
+    fn test_parse_malformed_fen() {
+        assert!(parse_fen("").is_err());
+        assert!(parse_fen("not a fen").is_err());
+        assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP").is_err());
+        assert!(parse_fen("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").is_err());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra").is_err());
+        // black king in check with white to move
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4R1K1").is_err());
+    }
+
+    #[test]
+    fn test_san() {
+        let san_of = |fen: &str, mov: &str| {
+            let board = Board::from_str(fen).unwrap();
+            san(&board, parse_coordinate_move(mov, &board).unwrap())
+        };
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san_of(start, "e2e4"), "e4");
+        assert_eq!(san_of(start, "g1f3"), "Nf3");
+
+        // castling, en passant, promotion with capture and check
+        let fen = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1";
+        assert_eq!(san_of(fen, "e1g1"), "O-O");
+        assert_eq!(san_of(fen, "e1c1"), "O-O-O");
+        assert_eq!(san_of(fen, "e5d6"), "exd6");
+        assert_eq!(san_of(fen, "b7a8q"), "bxa8=Q+");
+        assert_eq!(san_of(fen, "b7b8n"), "b8=N");
+
+        // disambiguation by file, by rank, and by both
+        assert_eq!(san_of("4k3/8/8/8/8/8/R6R/4K3 w - - 0 1", "a2d2"), "Rad2");
+        assert_eq!(san_of("4k3/R7/8/8/8/8/8/R3K3 w - - 0 1", "a1a4"), "R1a4");
+        assert_eq!(san_of("4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1", "a3b2"), "Qa3b2");
+
+        // mate
+        assert_eq!(san_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let board = |fen: &str| Board::from_str(fen).unwrap();
+        let parse = |fen: &str, text: &str| parse_san(text, &board(fen));
+
+        // every legal move parse back from its own SAN
+        let fen = "r3k2r/1P6/8/3pP3/8/2N3N1/8/R3K2R w KQkq d6 0 1";
+        for mov in MoveGen::new_legal(&board(fen)) {
+            assert_eq!(parse(fen, &san(&board(fen), mov)), Ok(mov));
+        }
+
+        assert_eq!(
+            parse(fen, "0-0"),
+            Ok(ChessMove::new(Square::E1, Square::G1, None))
+        );
+        assert_eq!(
+            parse(fen, "Ne4!?"),
+            Err(SanParseError::Ambiguous("Ne4!?".to_string()))
+        );
+        assert_eq!(
+            parse(fen, "Nf6"),
+            Err(SanParseError::Illegal("Nf6".to_string()))
+        );
+        assert_eq!(
+            parse(fen, "b8=P"),
+            Err(SanParseError::Invalid("b8=P".to_string()))
+        );
+        assert_eq!(
+            parse(fen, "Nz4"),
+            Err(SanParseError::Invalid("Nz4".to_string()))
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use crate::chess_minmax::{is_insufficient_material, RepetitionTracker, FIFTY_MOVE_PLY};
+
+use chess::{Board, Color, Game, GameResult};
+
+use std::fmt;
+
+/// Why a game is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Checkmate { winner: Color },
+    Resignation { winner: Color },
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    DrawAgreed,
+    DrawDeclared,
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameOutcome::Checkmate { winner } => write!(f, "{:?} wins by checkmate", winner),
+            GameOutcome::Resignation { winner } => write!(f, "{:?} wins by resignation", winner),
+            GameOutcome::Stalemate => write!(f, "Draw by stalemate"),
+            GameOutcome::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            GameOutcome::FiftyMoveRule => write!(f, "Draw by fifty-move rule"),
+            GameOutcome::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            GameOutcome::DrawAgreed => write!(f, "Draw agreed"),
+            GameOutcome::DrawDeclared => write!(f, "Draw declared"),
+        }
+    }
+}
+
+impl GameOutcome {
+    /// How the game ended then who won, for the game over banner (e.g. "Checkmate — White wins")
+    pub fn banner(&self) -> String {
+        let (reason, winner) = match self {
+            GameOutcome::Checkmate { winner } => ("Checkmate", Some(winner)),
+            GameOutcome::Resignation { winner } => ("Resignation", Some(winner)),
+            GameOutcome::Stalemate => ("Stalemate", None),
+            GameOutcome::ThreefoldRepetition => ("Threefold repetition", None),
+            GameOutcome::FiftyMoveRule => ("Fifty-move rule", None),
+            GameOutcome::InsufficientMaterial => ("Insufficient material", None),
+            GameOutcome::DrawAgreed => ("Draw agreed", None),
+            GameOutcome::DrawDeclared => ("Draw declared", None),
+        };
+        match winner {
+            Some(winner) => format!("{} — {:?} wins", reason, winner),
+            None => format!("{} — Draw", reason),
+        }
+    }
+}
+
+/// Why a draw can be claimed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+impl From<DrawReason> for GameOutcome {
+    fn from(reason: DrawReason) -> Self {
+        match reason {
+            DrawReason::ThreefoldRepetition => GameOutcome::ThreefoldRepetition,
+            DrawReason::FiftyMoveRule => GameOutcome::FiftyMoveRule,
+            DrawReason::InsufficientMaterial => GameOutcome::InsufficientMaterial,
+        }
+    }
+}
+
+impl From<GameResult> for GameOutcome {
+    fn from(result: GameResult) -> Self {
+        match result {
+            GameResult::WhiteCheckmates => GameOutcome::Checkmate {
+                winner: Color::White,
+            },
+            GameResult::BlackCheckmates => GameOutcome::Checkmate {
+                winner: Color::Black,
+            },
+            GameResult::WhiteResigns => GameOutcome::Resignation {
+                winner: Color::Black,
+            },
+            GameResult::BlackResigns => GameOutcome::Resignation {
+                winner: Color::White,
+            },
+            GameResult::Stalemate => GameOutcome::Stalemate,
+            GameResult::DrawAccepted => GameOutcome::DrawAgreed,
+            GameResult::DrawDeclared => GameOutcome::DrawDeclared,
+        }
+    }
+}
+
+/// Result of `game`, None if the game is still ongoing.
+/// `repetition` has every move of `game` recorded.
+///
+/// Beside what `Game::result` report, threefold repetition, fifty-move rule
+/// and insufficient material end the game automatically.
+pub fn game_outcome(game: &Game, repetition: &RepetitionTracker) -> Option<GameOutcome> {
+    match game.result() {
+        Some(result) => Some(result.into()),
+        None => can_claim_draw(&game.current_position(), repetition).map(GameOutcome::from),
+    }
+}
+
+/// Draw that can be claimed in `board`, the position after the moves recorded in `repetition`,
+/// None if there is none.
+///
+/// This doesn't look at `Game::result`, use `game_outcome` for the full result.
+pub fn can_claim_draw(board: &Board, repetition: &RepetitionTracker) -> Option<DrawReason> {
+    if is_insufficient_material(board) {
+        Some(DrawReason::InsufficientMaterial)
+    } else if repetition.position_count(board) >= 3 {
+        Some(DrawReason::ThreefoldRepetition)
+    } else if repetition.halfmove_clock() >= FIFTY_MOVE_PLY {
+        Some(DrawReason::FiftyMoveRule)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{can_claim_draw, game_outcome, DrawReason, GameOutcome};
+    use crate::chess_minmax::RepetitionTracker;
+    use crate::chess_notation::fen_halfmove_clock;
+    use chess::{Board, BoardStatus, ChessMove, Color, Game, MoveGen};
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    fn play(base: &Board, moves: &[&str]) -> (Game, RepetitionTracker) {
+        let mut game = Game::new_with_board(*base);
+        let mut repetition = RepetitionTracker::default();
+        for mov in moves {
+            let mov = ChessMove::from_str(mov).unwrap();
+            repetition.push(&game.current_position(), mov);
+            assert!(game.make_move(mov));
+        }
+        (game, repetition)
+    }
+
+    fn outcome_of(game: &(Game, RepetitionTracker)) -> Option<GameOutcome> {
+        game_outcome(&game.0, &game.1)
+    }
+
+    fn outcome_of_fen(fen: &str) -> Option<GameOutcome> {
+        let board = Board::from_str(fen).unwrap();
+        let mut repetition = RepetitionTracker::default();
+        repetition.set_halfmove_clock(fen_halfmove_clock(fen));
+        game_outcome(&Game::new_with_board(board), &repetition)
+    }
+
+    fn claim(game: &(Game, RepetitionTracker)) -> Option<DrawReason> {
+        can_claim_draw(&game.0.current_position(), &game.1)
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate() {
+        let base = Board::default();
+        let fools_mate = play(&base, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(
+            outcome_of(&fools_mate),
+            Some(GameOutcome::Checkmate {
+                winner: Color::Black
+            })
+        );
+
+        assert_eq!(
+            outcome_of_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            Some(GameOutcome::Stalemate)
+        );
+        assert_eq!(outcome_of_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_resignation_and_agreement() {
+        let mut game = Game::new();
+        game.resign(Color::White);
+        assert_eq!(
+            game_outcome(&game, &RepetitionTracker::default()),
+            Some(GameOutcome::Resignation {
+                winner: Color::Black
+            })
+        );
+
+        let mut game = Game::new();
+        game.offer_draw(Color::White);
+        game.accept_draw();
+        assert_eq!(
+            game_outcome(&game, &RepetitionTracker::default()),
+            Some(GameOutcome::DrawAgreed)
+        );
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        assert_eq!(
+            outcome_of_fen("8/8/4k3/8/8/3K4/8/8 w - - 0 1"),
+            Some(GameOutcome::InsufficientMaterial)
+        );
+        assert_eq!(
+            outcome_of_fen("8/8/4k3/8/8/3KN3/8/8 w - - 0 1"),
+            Some(GameOutcome::InsufficientMaterial)
+        );
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let base = Board::default();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        // starting position occurred twice, not yet a draw
+        let twice = play(&base, &shuffle);
+        assert_eq!(outcome_of(&twice), None);
+
+        let thrice = play(&base, &[&shuffle[..], &shuffle[..]].concat());
+        assert_eq!(outcome_of(&thrice), Some(GameOutcome::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn test_fifty_move_rule() {
+        let base = Board::from_str("rn2k3/8/8/8/8/8/8/RN2K3 w - - 0 1").unwrap();
+        let mut game = Game::new_with_board(base);
+        let mut repetition = RepetitionTracker::default();
+        let mut seen = HashSet::new();
+        seen.insert(base);
+
+        // quiet moves that never revisit a position
+        for ply in 0..100 {
+            assert_eq!(game_outcome(&game, &repetition), None, "ply {}", ply);
+
+            let board = game.current_position();
+            let mov = MoveGen::new_legal(&board)
+                .find(|mov| {
+                    let next = board.make_move_new(*mov);
+                    board.piece_on(mov.get_dest()).is_none()
+                        && next.status() == BoardStatus::Ongoing
+                        && !seen.contains(&next)
+                })
+                .unwrap();
+            seen.insert(board.make_move_new(mov));
+            repetition.push(&board, mov);
+            game.make_move(mov);
+        }
+
+        assert_eq!(
+            game_outcome(&game, &repetition),
+            Some(GameOutcome::FiftyMoveRule)
+        );
+        assert_eq!(
+            can_claim_draw(&game.current_position(), &repetition),
+            Some(DrawReason::FiftyMoveRule)
+        );
+
+        // the clock of a FEN counts too, one more quiet move complete the fifty moves
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 99 80";
+        assert_eq!(outcome_of_fen(fen), None);
+        let mut game = (Game::from_str(fen).unwrap(), RepetitionTracker::default());
+        game.1.set_halfmove_clock(fen_halfmove_clock(fen));
+        let rook_lift = ChessMove::from_str("a1a2").unwrap();
+        game.1.push(&game.0.current_position(), rook_lift);
+        game.0.make_move(rook_lift);
+        assert_eq!(outcome_of(&game), Some(GameOutcome::FiftyMoveRule));
+    }
+
+    #[test]
+    fn test_can_claim_draw() {
+        let base = Board::default();
+        let shuffle = ["b1c3", "b8c6", "c3b1", "c6b8"];
+
+        // second occurrence of the starting position is a near-miss
+        assert_eq!(claim(&play(&base, &shuffle)), None);
+        assert_eq!(
+            claim(&play(&base, &[&shuffle[..], &shuffle[..]].concat())),
+            Some(DrawReason::ThreefoldRepetition)
+        );
+
+        let bare_kings = Board::from_str("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap();
+        assert_eq!(
+            can_claim_draw(&bare_kings, &RepetitionTracker::default()),
+            Some(DrawReason::InsufficientMaterial)
+        );
+
+        // checkmate isn't a draw to claim
+        let fools_mate = play(&base, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(claim(&fools_mate), None);
+    }
+
+    #[test]
+    fn test_banner() {
+        let mate = GameOutcome::Checkmate {
+            winner: Color::White,
+        };
+        assert_eq!(mate.banner(), "Checkmate — White wins");
+        assert_eq!(GameOutcome::Stalemate.banner(), "Stalemate — Draw");
+        assert_eq!(
+            GameOutcome::ThreefoldRepetition.banner(),
+            "Threefold repetition — Draw"
+        );
+        assert_eq!(
+            GameOutcome::FiftyMoveRule.banner(),
+            "Fifty-move rule — Draw"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use chess::{Board, ChessMove, MoveGen};
+
+/// Number of leaf positions `depth` plies below `board`, counting every legal move sequence
+pub fn perft(board: &Board, depth: u8) -> u64 {
+    let moves = MoveGen::new_legal(board);
+    match depth {
+        0 => 1,
+        // leaves don't need to be made, only counted
+        1 => moves.len() as u64,
+        _ => moves
+            .map(|mov| perft(&board.make_move_new(mov), depth - 1))
+            .sum(),
+    }
+}
+
+/// `perft` of each root move (searched `depth - 1` plies further), in move generation order
+pub fn perft_divide(board: &Board, depth: u8) -> Vec<(ChessMove, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    MoveGen::new_legal(board)
+        .map(|mov| (mov, perft(&board.make_move_new(mov), depth - 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{perft, perft_divide};
+    use chess::{Board, ChessMove};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_perft_start_position() {
+        let board = Board::default();
+        let expected = [1, 20, 400, 8_902, 197_281];
+        for (depth, &count) in expected.iter().enumerate() {
+            assert_eq!(perft(&board, depth as u8), count, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        // "Kiwipete", exercise castling, en passant and promotion
+        let board =
+            Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let divide = perft_divide(&board, 2);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), 2_039);
+
+        let castle = ChessMove::from_str("e1g1").unwrap();
+        assert!(divide.iter().any(|&(mov, _)| mov == castle));
+        assert!(perft_divide(&board, 0).is_empty());
+    }
+}
@@ -0,0 +1,402 @@
+use crate::chess_notation::{parse_fen, parse_san, san};
+use crate::chess_outcome::GameOutcome;
+
+use chess::{Board, ChessMove, Color};
+
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// PGN export format keep movetext lines at most this long
+const MAX_LINE_LENGTH: usize = 79;
+
+/// PGN result tag of a game, `*` if it is still ongoing
+pub fn result_tag(outcome: Option<GameOutcome>) -> &'static str {
+    match outcome {
+        None => "*",
+        Some(GameOutcome::Checkmate { winner }) | Some(GameOutcome::Resignation { winner }) => {
+            match winner {
+                Color::White => "1-0",
+                Color::Black => "0-1",
+            }
+        }
+        Some(_) => "1/2-1/2",
+    }
+}
+
+/// Complete PGN record (tag pairs, blank line, SAN movetext) of `moves` played from `base`,
+/// `date` is in PGN format (`YYYY.MM.DD`)
+pub fn pgn_record(
+    base: &Board,
+    moves: &[ChessMove],
+    outcome: Option<GameOutcome>,
+    date: &str,
+) -> String {
+    pgn_record_with_tags(base, moves, outcome, &[("Date", date.to_string())])
+}
+
+/// Same as `pgn_record` but `extra_tags` replace the default tag of the same name
+/// (e.g. `White`), or are added after the seven standard tags
+pub fn pgn_record_with_tags(
+    base: &Board,
+    moves: &[ChessMove],
+    outcome: Option<GameOutcome>,
+    extra_tags: &[(&str, String)],
+) -> String {
+    let result = result_tag(outcome);
+
+    let mut tags = vec![
+        ("Event", "Casual game".to_string()),
+        ("Site", "chess_try".to_string()),
+        ("Date", "????.??.??".to_string()),
+        ("Round", "-".to_string()),
+        ("White", "?".to_string()),
+        ("Black", "?".to_string()),
+        ("Result", result.to_string()),
+    ];
+    for (name, value) in extra_tags {
+        match tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, default)) => *default = value.clone(),
+            None => tags.push((name, value.clone())),
+        }
+    }
+    if *base != Board::default() {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", base.to_string()));
+    }
+    if let Some(outcome) = outcome {
+        tags.push(("Termination", outcome.to_string()));
+    }
+
+    let mut record = String::new();
+    for (name, value) in tags {
+        record.push_str(&format!("[{} \"{}\"]\n", name, value));
+    }
+    record.push('\n');
+    record.push_str(&movetext(base, moves, result));
+    record
+}
+
+/// SAN moves with move numbers followed by the result, wrapped at `MAX_LINE_LENGTH`
+fn movetext(base: &Board, moves: &[ChessMove], result: &str) -> String {
+    let mut tokens = Vec::new();
+    let mut board = *base;
+    let mut move_number = 1;
+    for (i, &mov) in moves.iter().enumerate() {
+        match board.side_to_move() {
+            Color::White => tokens.push(format!("{}.", move_number)),
+            Color::Black if i == 0 => tokens.push(format!("{}...", move_number)),
+            Color::Black => {}
+        }
+        tokens.push(san(&board, mov));
+
+        if board.side_to_move() == Color::Black {
+            move_number += 1;
+        }
+        board = board.make_move_new(mov);
+    }
+    tokens.push(result.to_string());
+
+    let mut text = String::new();
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+        text.push_str(&token);
+        line_length += token.len();
+    }
+    text.push('\n');
+    text
+}
+
+/// Starting position and moves of a PGN game (tag pairs optional, the `FEN` tag set the
+/// starting position), movetext is SAN with optional move numbers, `{}`/`;` comments,
+/// NAGs (`$1`) and result marker.
+///
+/// Error at the first move that can't be played, with its move number.
+pub fn parse_pgn(text: &str) -> Result<(Board, Vec<ChessMove>), String> {
+    let text = strip_comments(text);
+    let (tags, movetext): (Vec<_>, Vec<_>) = text
+        .lines()
+        .map(str::trim)
+        .partition(|line| line.starts_with('['));
+
+    let mut base = Board::default();
+    for tag in tags {
+        if let Some(fen) = tag
+            .strip_prefix("[FEN \"")
+            .and_then(|fen| fen.strip_suffix("\"]"))
+        {
+            base = parse_fen(fen).map_err(|e| format!("FEN tag: {}", e))?.0;
+        }
+    }
+
+    let mut board = base;
+    let mut moves = Vec::new();
+    let mut move_number = 1;
+    for token in movetext.iter().flat_map(|line| line.split_whitespace()) {
+        // move number may be attached to the move (`1.e4`)
+        let digits = token.len() - token.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let token = if token[digits..].starts_with('.') {
+            token[digits..].trim_start_matches('.')
+        } else {
+            token
+        };
+
+        match token {
+            "" => continue,
+            "1-0" | "0-1" | "1/2-1/2" | "*" => break,
+            _ if token.starts_with('$') => continue,
+            _ => {}
+        }
+
+        let mov = parse_san(token, &board).map_err(|e| {
+            let dots = match board.side_to_move() {
+                Color::White => ".",
+                Color::Black => "...",
+            };
+            format!("move {}{} {}: {}", move_number, dots, token, e)
+        })?;
+        if board.side_to_move() == Color::Black {
+            move_number += 1;
+        }
+        board = board.make_move_new(mov);
+        moves.push(mov);
+    }
+
+    Ok((base, moves))
+}
+
+/// `text` without `{...}` and `;` (rest of line) comments
+fn strip_comments(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut in_brace = false;
+    let mut in_line = false;
+    for c in text.chars() {
+        match c {
+            '{' if !in_line => in_brace = true,
+            '}' if in_brace => {
+                in_brace = false;
+                stripped.push(' ');
+            }
+            ';' if !in_brace => in_line = true,
+            '\n' if in_line => {
+                in_line = false;
+                stripped.push(c);
+            }
+            _ if in_brace || in_line => {}
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Append `record` to the PGN database at `path` (created if missing),
+/// keeping exactly one blank line between games.
+///
+/// The file is locked while writing so concurrent sessions don't interleave their games.
+pub fn append_pgn(path: &str, record: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    file.lock()?;
+
+    let len = file.metadata()?.len();
+    let separator = if len == 0 {
+        ""
+    } else {
+        let mut tail = [0; 2];
+        let tail_len = len.min(2) as usize;
+        file.seek(SeekFrom::End(-(tail_len as i64)))?;
+        file.read_exact(&mut tail[..tail_len])?;
+        match &tail[..tail_len] {
+            b"\n\n" => "",
+            [.., b'\n'] => "\n",
+            _ => "\n\n",
+        }
+    };
+
+    let written = write!(file, "{}{}", separator, record);
+    file.unlock()?;
+    written
+}
+
+/// Today's date (UTC) in PGN format
+pub fn pgn_date(now: SystemTime) -> String {
+    let days = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Gregorian (year, month, day) of `days` since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        append_pgn, civil_from_days, parse_pgn, pgn_record, pgn_record_with_tags, result_tag,
+    };
+    use crate::chess_outcome::GameOutcome;
+    use chess::{Board, ChessMove, Color};
+    use std::fs;
+    use std::str::FromStr;
+
+    fn moves(list: &[&str]) -> Vec<ChessMove> {
+        list.iter()
+            .map(|mov| ChessMove::from_str(mov).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_pgn_record() {
+        let fools_mate = moves(&["f2f3", "e7e5", "g2g4", "d8h4"]);
+        let outcome = Some(GameOutcome::Checkmate {
+            winner: Color::Black,
+        });
+        let record = pgn_record(&Board::default(), &fools_mate, outcome, "2024.01.31");
+
+        assert!(record.starts_with("[Event \"Casual game\"]\n"));
+        assert!(record.contains("[Date \"2024.01.31\"]\n"));
+        assert!(record.contains("[Result \"0-1\"]\n"));
+        assert!(!record.contains("[FEN"));
+        assert!(record.ends_with("\n\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+
+        let base = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        let record = pgn_record(&base, &moves(&["e8d7"]), None, "????.??.??");
+        assert!(record.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n"));
+        assert!(record.ends_with("\n\n1... Kd7 *\n"));
+    }
+
+    #[test]
+    fn test_pgn_record_with_tags() {
+        let tags = [
+            ("White", "chess_try depth 3".to_string()),
+            ("Opening", "King's Pawn".to_string()),
+        ];
+        let record = pgn_record_with_tags(&Board::default(), &moves(&["e2e4"]), None, &tags);
+        assert!(record.contains(
+            "[White \"chess_try depth 3\"]\n[Black \"?\"]\n[Result \"*\"]\n[Opening \"King's Pawn\"]\n"
+        ));
+        assert!(record.contains("[Date \"????.??.??\"]\n"));
+    }
+
+    #[test]
+    fn test_pgn_special_moves() {
+        // en passant, short castling and a disambiguated knight move
+        let game = moves(&[
+            "e2e4", "g8f6", "e4e5", "d7d5", "e5d6", "e7d6", "g1f3", "f8e7", "f1e2", "e8g8", "e1g1",
+            "b8d7", "b1c3", "d7b6", "c3d5", "f6d5",
+        ]);
+        let record = pgn_record(&Board::default(), &game, None, "????.??.??");
+
+        let movetext = record.split("\n\n").nth(1).unwrap();
+        let tokens: Vec<_> = movetext.split_whitespace().collect();
+        assert_eq!(
+            tokens.join(" "),
+            "1. e4 Nf6 2. e5 d5 3. exd6 exd6 4. Nf3 Be7 5. Be2 O-O 6. O-O Nbd7 7. Nc3 Nb6 8. Nd5 Nfxd5 *"
+        );
+    }
+
+    #[test]
+    fn test_parse_pgn() {
+        let text = "[Event \"Casual game\"]\n[Result \"0-1\"]\n\n\
+                    1. f3 {weak} e5 2.g4?? $4 ; blunder\nQh4# 0-1\n";
+        let (base, parsed) = parse_pgn(text).unwrap();
+        assert_eq!(base, Board::default());
+        assert_eq!(parsed, moves(&["f2f3", "e7e5", "g2g4", "d8h4"]));
+
+        // export and import round trip, with a custom starting position
+        let base = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        let game = moves(&["e8d7", "e2e4"]);
+        let record = pgn_record(&base, &game, None, "????.??.??");
+        assert_eq!(parse_pgn(&record), Ok((base, game)));
+    }
+
+    #[test]
+    fn test_parse_pgn_error_move_number() {
+        assert_eq!(
+            parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf3").unwrap_err(),
+            "move 3... Nf3: Nf3 is not a legal move"
+        );
+        let knights = "[FEN \"4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1\"]\n1. Nd2";
+        assert!(parse_pgn(knights)
+            .unwrap_err()
+            .starts_with("move 1. Nd2: Nd2 is ambiguous"));
+    }
+
+    #[test]
+    fn test_long_movetext_is_wrapped() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let long = moves(&shuffle.repeat(10));
+        let record = pgn_record(&Board::default(), &long, None, "????.??.??");
+
+        assert!(record.lines().all(|line| line.len() <= 79));
+        assert_eq!(record.matches("Nf3").count(), 10);
+    }
+
+    #[test]
+    fn test_result_tag() {
+        assert_eq!(result_tag(None), "*");
+        assert_eq!(
+            result_tag(Some(GameOutcome::Resignation {
+                winner: Color::White
+            })),
+            "1-0"
+        );
+        assert_eq!(result_tag(Some(GameOutcome::Stalemate)), "1/2-1/2");
+    }
+
+    #[test]
+    fn test_append_pgn_separation() {
+        let path = std::env::temp_dir().join(format!("chess_try_test_{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let record = pgn_record(&Board::default(), &moves(&["e2e4"]), None, "????.??.??");
+        append_pgn(path, &record).unwrap();
+        append_pgn(path, &record).unwrap();
+
+        // a database edited by hand may lack the trailing newline
+        let mut text = fs::read_to_string(path).unwrap();
+        text.pop();
+        fs::write(path, &text).unwrap();
+        append_pgn(path, &record).unwrap();
+
+        let text = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert!(text.starts_with("[Event"));
+        assert_eq!(text.matches("[Event").count(), 3);
+        assert_eq!(text.matches("*\n\n[Event").count(), 2);
+        assert!(!text.contains("\n\n\n"));
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_753), (2024, 1, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+}
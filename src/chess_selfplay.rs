@@ -0,0 +1,255 @@
+use crate::chess_minmax::{random_move, search_root, RepetitionTracker, SearchOptions};
+use crate::chess_notation::parse_coordinate_move;
+use crate::chess_outcome::{game_outcome, GameOutcome};
+use crate::chess_pgn::{pgn_record_with_tags, result_tag};
+
+use chess::{Board, ChessMove, Color, Game};
+use lru::LruCache;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// Openings a self-play game may start from (coordinate moves from the starting position),
+/// besides the starting position itself
+pub const OPENINGS: [(&str, &str); 8] = [
+    ("Sicilian Defense", "e2e4 c7c5 g1f3 d7d6"),
+    ("Ruy Lopez", "e2e4 e7e5 g1f3 b8c6 f1b5"),
+    ("Italian Game", "e2e4 e7e5 g1f3 b8c6 f1c4"),
+    ("French Defense", "e2e4 e7e6 d2d4 d7d5"),
+    ("Caro-Kann Defense", "e2e4 c7c6 d2d4 d7d5"),
+    ("Queen's Gambit Declined", "d2d4 d7d5 c2c4 e7e6"),
+    ("King's Indian Defense", "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7"),
+    ("English Opening", "c2c4 e7e5"),
+];
+
+/// Engine settings of a self-play game
+#[derive(Debug, Clone, Copy)]
+pub struct SelfPlayConfig {
+    /// search depth of each side, indexed by `Color::to_index`,
+    /// 0 plays random moves (see `random_move`)
+    pub depth: [u8; 2],
+    /// the game is drawn after this many ply (opening included)
+    pub max_length: usize,
+}
+
+/// A finished self-play game, played from the starting position
+pub struct SelfPlayGame {
+    /// name of the opening (see `OPENINGS`), None when started from the starting position
+    pub opening: Option<&'static str>,
+    pub moves: Vec<ChessMove>,
+    pub outcome: Option<GameOutcome>,
+    /// total time each side spent choosing its moves, indexed by `Color::to_index`
+    pub think_time: [Duration; 2],
+}
+
+impl SelfPlayGame {
+    /// PGN record of the game, `round` is its number in the batch
+    pub fn pgn(&self, config: &SelfPlayConfig, round: usize, date: &str) -> String {
+        let engine = |color: Color| match config.depth[color.to_index()] {
+            0 => "chess_try random".to_string(),
+            depth => format!("chess_try depth {}", depth),
+        };
+        let mut tags = vec![
+            ("Event", "Self-play".to_string()),
+            ("Date", date.to_string()),
+            ("Round", round.to_string()),
+            ("White", engine(Color::White)),
+            ("Black", engine(Color::Black)),
+        ];
+        if let Some(opening) = self.opening {
+            tags.push(("Opening", opening.to_string()));
+        }
+        pgn_record_with_tags(&Board::default(), &self.moves, self.outcome, &tags)
+    }
+
+    /// e.g. "White 1.52s, Black 0.21s"
+    pub fn think_time_summary(&self) -> String {
+        let [white, black] = self.think_time;
+        format!("White {:.2?}, Black {:.2?}", white, black)
+    }
+
+    /// e.g. "1-0 (White wins by checkmate) in 87 ply"
+    pub fn summary(&self) -> String {
+        let reason = self
+            .outcome
+            .map_or(String::new(), |outcome| format!(" ({})", outcome));
+        format!(
+            "{}{} in {} ply",
+            result_tag(self.outcome),
+            reason,
+            self.moves.len()
+        )
+    }
+}
+
+/// Moves of `OPENINGS[index]`
+fn opening_moves(index: usize) -> Vec<ChessMove> {
+    let (name, moves) = OPENINGS[index];
+    let mut board = Board::default();
+    moves
+        .split_whitespace()
+        .map(|text| {
+            let mov = parse_coordinate_move(text, &board)
+                .unwrap_or_else(|e| panic!("opening {} {}: {}", name, text, e));
+            board = board.make_move_new(mov);
+            mov
+        })
+        .collect()
+}
+
+/// Play a game engine against engine from a random opening (or the starting position)
+pub fn play_game(config: &SelfPlayConfig, rng: &mut impl Rng) -> SelfPlayGame {
+    // one past the openings is the starting position
+    let opening = rng.gen_range(0..=OPENINGS.len());
+    let moves = if opening < OPENINGS.len() {
+        opening_moves(opening)
+    } else {
+        Vec::new()
+    };
+
+    let (moves, outcome, think_time) = play_from(&moves, config, rng);
+    SelfPlayGame {
+        opening: OPENINGS.get(opening).map(|&(name, _)| name),
+        moves,
+        outcome,
+        think_time,
+    }
+}
+
+/// Every move of the game starting with `opening`, its result and the time each side thought
+fn play_from(
+    opening: &[ChessMove],
+    config: &SelfPlayConfig,
+    rng: &mut impl Rng,
+) -> (Vec<ChessMove>, Option<GameOutcome>, [Duration; 2]) {
+    let mut game = Game::new();
+    let mut think_time = [Duration::ZERO; 2];
+    let mut moves = Vec::new();
+    let mut repetition = RepetitionTracker::default();
+    let mut cache = LruCache::new(crate::CACHE_SIZE);
+
+    for &mov in opening {
+        play_move(&mut game, &mut moves, &mut repetition, mov);
+    }
+
+    loop {
+        if let Some(outcome) = game_outcome(&game, &repetition) {
+            return (moves, Some(outcome), think_time);
+        }
+        // same as the GUI, a game too long is drawn
+        if moves.len() >= config.max_length {
+            let side = game.side_to_move();
+            if game.offer_draw(side) && game.accept_draw() {
+                continue;
+            }
+            return (moves, None, think_time);
+        }
+
+        let board = game.current_position();
+        let options = SearchOptions {
+            halfmove_clock: repetition.halfmove_clock(),
+            ..Default::default()
+        };
+        let side = board.side_to_move().to_index();
+        let depth = config.depth[side];
+        let start = Instant::now();
+        let best = if depth == 0 {
+            random_move(&board, rng)
+        } else {
+            let (best, _) = search_root(
+                &board,
+                depth,
+                &options,
+                rng,
+                &mut cache,
+                repetition.repeated(),
+            );
+            best.map(|(mov, _)| mov)
+        };
+        think_time[side] += start.elapsed();
+        match best {
+            Some(mov) => play_move(&mut game, &mut moves, &mut repetition, mov),
+            // `game_outcome` already report checkmate and stalemate
+            None => return (moves, None, think_time),
+        }
+    }
+}
+
+fn play_move(
+    game: &mut Game,
+    moves: &mut Vec<ChessMove>,
+    repetition: &mut RepetitionTracker,
+    mov: ChessMove,
+) {
+    repetition.push(&game.current_position(), mov);
+    game.make_move(mov);
+    moves.push(mov);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{opening_moves, play_from, SelfPlayConfig, SelfPlayGame, OPENINGS};
+    use crate::chess_minmax::test_rng;
+    use crate::chess_outcome::GameOutcome;
+    use crate::chess_pgn::parse_pgn;
+    use chess::Board;
+
+    #[test]
+    fn test_openings_are_legal() {
+        for index in 0..OPENINGS.len() {
+            assert!(!opening_moves(index).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_self_play_game() {
+        let config = SelfPlayConfig {
+            depth: [1, 2],
+            max_length: 12,
+        };
+        let opening = opening_moves(0);
+        let (moves, outcome, think_time) = play_from(&opening, &config, &mut test_rng());
+        assert_eq!(&moves[..opening.len()], &opening[..]);
+        assert_eq!(moves.len(), 12);
+        assert_eq!(outcome, Some(GameOutcome::DrawAgreed));
+        // both sides searched after the opening
+        assert!(think_time.iter().all(|time| !time.is_zero()));
+
+        let game = SelfPlayGame {
+            opening: Some(OPENINGS[0].0),
+            moves,
+            outcome,
+            think_time,
+        };
+        let record = game.pgn(&config, 3, "2024.01.31");
+        assert!(record.contains("[Round \"3\"]\n"));
+        assert!(record.contains("[Black \"chess_try depth 2\"]\n"));
+        assert!(record.contains("[Opening \"Sicilian Defense\"]\n"));
+        assert!(record.ends_with(" 1/2-1/2\n"));
+        assert_eq!(
+            parse_pgn(&record),
+            Ok((Board::default(), game.moves.clone()))
+        );
+        assert_eq!(game.summary(), "1/2-1/2 (Draw agreed) in 12 ply");
+    }
+
+    #[test]
+    fn test_random_side() {
+        // depth 0 plays random moves instead of searching
+        let config = SelfPlayConfig {
+            depth: [0, 1],
+            max_length: 20,
+        };
+        let (moves, _, think_time) = play_from(&[], &config, &mut test_rng());
+        assert_eq!(moves.len(), 20);
+
+        let game = SelfPlayGame {
+            opening: None,
+            moves,
+            outcome: None,
+            think_time,
+        };
+        let record = game.pgn(&config, 1, "2024.01.31");
+        assert!(record.contains("[White \"chess_try random\"]\n"));
+        assert!(record.contains("[Black \"chess_try depth 1\"]\n"));
+    }
+}
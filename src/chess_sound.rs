@@ -0,0 +1,122 @@
+use chess::{Board, ChessMove, Piece, EMPTY};
+
+/// Sound played after a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveSound {
+    Move,
+    Capture,
+    Check,
+}
+
+impl MoveSound {
+    /// Sound of playing `mov` on `board`, a check take precedence over a capture
+    pub fn of(board: &Board, mov: ChessMove) -> Self {
+        // a pawn changing file onto an empty square capture en passant
+        let captured = board.piece_on(mov.get_dest()).is_some()
+            || (board.piece_on(mov.get_source()) == Some(Piece::Pawn)
+                && mov.get_source().get_file() != mov.get_dest().get_file());
+
+        if *board.make_move_new(mov).checkers() != EMPTY {
+            MoveSound::Check
+        } else if captured {
+            MoveSound::Capture
+        } else {
+            MoveSound::Move
+        }
+    }
+
+    #[cfg_attr(not(feature = "sound"), allow(dead_code))]
+    pub fn asset_path(self) -> &'static str {
+        match self {
+            MoveSound::Move => "asset/move.wav",
+            MoveSound::Capture => "asset/capture.wav",
+            MoveSound::Check => "asset/check.wav",
+        }
+    }
+}
+
+/// Audio output of the move sounds, needs the `sound` feature
+#[cfg(feature = "sound")]
+pub struct SoundPlayer {
+    // the stream stop playing once dropped
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+}
+
+#[cfg(feature = "sound")]
+impl SoundPlayer {
+    /// Open the default audio device
+    pub fn open() -> Result<Self, String> {
+        let (stream, handle) = rodio::OutputStream::try_default().map_err(|e| e.to_string())?;
+        Ok(SoundPlayer {
+            _stream: stream,
+            handle,
+        })
+    }
+
+    /// Start playing `sound` without waiting for it to finish
+    pub fn play(&self, sound: MoveSound) -> Result<(), String> {
+        let path = sound.asset_path();
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let sink = self
+            .handle
+            .play_once(std::io::BufReader::new(file))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        sink.detach();
+        Ok(())
+    }
+}
+
+/// Stand-in when built without the `sound` feature, can never be opened
+#[cfg(not(feature = "sound"))]
+#[allow(dead_code)]
+pub struct SoundPlayer;
+
+#[cfg(not(feature = "sound"))]
+impl SoundPlayer {
+    pub fn open() -> Result<Self, String> {
+        Err("built without the `sound` feature".to_string())
+    }
+
+    pub fn play(&self, _sound: MoveSound) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveSound;
+    use chess::{Board, ChessMove};
+    use std::path::Path;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_move_sound() {
+        let sound = |fen: &str, mov: &str| {
+            MoveSound::of(
+                &Board::from_str(fen).unwrap(),
+                ChessMove::from_str(mov).unwrap(),
+            )
+        };
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(sound(start, "e2e4"), MoveSound::Move);
+        // exd5
+        let scandinavian = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(sound(scandinavian, "e4d5"), MoveSound::Capture);
+        // en passant capture land on an empty square
+        let en_passant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        assert_eq!(sound(en_passant, "e5d6"), MoveSound::Capture);
+        assert_eq!(sound(en_passant, "e5e6"), MoveSound::Move);
+        // Bxf7+ capture with check, check win
+        let italian = "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/8/PPPP1PPP/RNBQK1NR w KQkq - 2 3";
+        assert_eq!(sound(italian, "c4f7"), MoveSound::Check);
+    }
+
+    #[test]
+    fn test_sound_assets_exist() {
+        for sound in [MoveSound::Move, MoveSound::Capture, MoveSound::Check] {
+            assert!(Path::new(sound.asset_path()).is_file(), "{:?}", sound);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::chess_minmax::main_evalation::{debug_evaluation, evaluate_white_pov};
+
+use chess::Board;
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Parse labeled position `<fen>;<result>`, result is from white perspective and can be
+/// written as `1-0`, `0-1`, `1/2-1/2` or as number (`1.0`, `0.5`, `0.0`)
+pub fn parse_labeled_position(line: &str) -> Result<(Board, f64), String> {
+    let (fen, result) = line
+        .rsplit_once(';')
+        .ok_or_else(|| "expected <fen>;<result>".to_string())?;
+
+    let board = Board::from_str(fen.trim()).map_err(|e| e.to_string())?;
+    let result = match result.trim() {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        other => match other.parse::<f64>() {
+            Ok(x) if (0.0..=1.0).contains(&x) => x,
+            _ => return Err(format!("invalid result \"{}\"", other)),
+        },
+    };
+
+    Ok((board, result))
+}
+
+/// Write every evaluation component of each labeled position (see `parse_labeled_position`)
+/// as CSV for tuning the evaluation with external tool (e.g. Texel tuning).
+///
+/// Blank line and line starting with `#` are skipped, malformed line are reported and skipped.
+/// Return number of position written.
+pub fn export_eval_csv(input: &str, output: &mut impl Write) -> io::Result<usize> {
+    let header = debug_evaluation(&Board::default())
+        .iter()
+        .flat_map(|c| vec![format!("{}_white", c.name), format!("{}_black", c.name)])
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(output, "fen,{},eval,result", header)?;
+
+    let mut count = 0;
+    for (line_num, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (board, result) = match parse_labeled_position(line) {
+            Ok(labeled) => labeled,
+            Err(e) => {
+                eprintln!("Line {}: {}, skipped", line_num + 1, e);
+                continue;
+            }
+        };
+
+        let components = debug_evaluation(&board)
+            .iter()
+            .map(|c| format!("{},{}", c.white, c.black))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(
+            output,
+            "{},{},{},{}",
+            board,
+            components,
+            evaluate_white_pov(&board),
+            result
+        )?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_eval_csv, parse_labeled_position};
+
+    #[test]
+    fn test_parse_labeled_position() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+        assert_eq!(
+            parse_labeled_position(&format!("{};1-0", fen)).unwrap().1,
+            1.0
+        );
+        assert_eq!(
+            parse_labeled_position(&format!("{}; 0-1", fen)).unwrap().1,
+            0.0
+        );
+        assert_eq!(
+            parse_labeled_position(&format!("{};1/2-1/2", fen))
+                .unwrap()
+                .1,
+            0.5
+        );
+        assert_eq!(
+            parse_labeled_position(&format!("{};0.5", fen)).unwrap().1,
+            0.5
+        );
+
+        assert!(parse_labeled_position(fen).is_err());
+        assert!(parse_labeled_position(&format!("{};2-0", fen)).is_err());
+        assert!(parse_labeled_position("not a fen;1-0").is_err());
+    }
+
+    #[test]
+    fn test_export_eval_csv() {
+        let input = "# comment\n\
+                     4k3/8/8/8/8/8/4P3/4K3 w - - 0 1;1-0\n\
+                     \n\
+                     garbage\n\
+                     4k3/4p3/8/8/8/8/8/4K3 b - - 0 1;0-1\n";
+
+        let mut output = Vec::new();
+        let count = export_eval_csv(input, &mut output).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        let rows = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(count, 2);
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("fen,pawn_white,pawn_black,"));
+        assert!(rows[0].ends_with(",eval,result"));
+
+        // every row has the same number of column as header
+        let columns = rows[0].split(',').count();
+        assert!(rows.iter().all(|row| row.split(',').count() == columns));
+        assert!(rows[1].ends_with(",1"));
+        assert!(rows[2].ends_with(",0"));
+    }
+}
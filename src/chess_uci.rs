@@ -0,0 +1,322 @@
+use crate::chess_minmax::{
+    mate_distance, principal_variation, search_root, BoardHash, RepetitionTracker, SearchOptions,
+    TranspositionItem, MAX_TIMED_DEPTH,
+};
+use crate::chess_notation::{fen_halfmove_clock, parse_coordinate_move, parse_fen};
+
+use chess::{Board, Color, MoveGen};
+use itertools::Itertools;
+use lru::LruCache;
+use rand::rngs::ThreadRng;
+use rand::thread_rng;
+use seahash::SeaHasher;
+
+use std::hash::BuildHasherDefault;
+use std::io::{self, BufRead, Write};
+use std::iter;
+use std::time::Duration;
+
+// number of moves the remaining clock time is shared between when `go` has no `movestogo`
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Universal Chess Interface engine, the search is synchronous so `stop` has no effect
+/// (use `go depth`, `go movetime` or the clock of `go wtime ...` to bound it)
+pub struct UciEngine {
+    board: Board,
+    /// positions played so far and the halfmove clock of `board`
+    repetition: RepetitionTracker,
+    cache: LruCache<BoardHash, TranspositionItem, BuildHasherDefault<SeaHasher>>,
+    rng: ThreadRng,
+}
+
+impl UciEngine {
+    pub fn new() -> Self {
+        UciEngine {
+            board: Board::default(),
+            repetition: RepetitionTracker::default(),
+            cache: LruCache::with_hasher(crate::CACHE_SIZE, Default::default()),
+            rng: thread_rng(),
+        }
+    }
+
+    /// Respond to a single command line, None when the engine should quit
+    pub fn handle(&mut self, line: &str) -> Option<Vec<String>> {
+        let mut tokens = line.split_whitespace();
+        let response = match tokens.next() {
+            Some("uci") => vec![
+                format!("id name chess_try {}", env!("CARGO_PKG_VERSION")),
+                format!("id author {}", env!("CARGO_PKG_AUTHORS")),
+                "uciok".to_string(),
+            ],
+            Some("isready") => vec!["readyok".to_string()],
+            Some("ucinewgame") => {
+                self.cache.clear();
+                self.set_position(Board::default(), 0);
+                Vec::new()
+            }
+            Some("position") => self.position(&tokens.collect::<Vec<_>>()),
+            Some("go") => self.go(&tokens.collect::<Vec<_>>()),
+            Some("quit") => return None,
+            // unknown command (and `stop`, `ponderhit`, ...) are ignored as the protocol require
+            _ => Vec::new(),
+        };
+        Some(response)
+    }
+
+    fn set_position(&mut self, board: Board, halfmove_clock: u16) {
+        self.board = board;
+        self.repetition.clear();
+        self.repetition.set_halfmove_clock(halfmove_clock);
+    }
+
+    /// `position [startpos | fen <fen>] [moves <move> ...]`
+    fn position(&mut self, args: &[&str]) -> Vec<String> {
+        let moves_at = args
+            .iter()
+            .position(|&arg| arg == "moves")
+            .unwrap_or(args.len());
+        let (setup, moves) = args.split_at(moves_at);
+
+        match setup {
+            ["startpos"] => self.set_position(Board::default(), 0),
+            ["fen", fen @ ..] => {
+                let fen = fen.join(" ");
+                match parse_fen(&fen) {
+                    Ok((board, _)) => self.set_position(board, fen_halfmove_clock(&fen)),
+                    Err(e) => return vec![format!("info string invalid FEN \"{}\": {}", fen, e)],
+                }
+            }
+            _ => {
+                return vec![format!(
+                    "info string invalid position command \"{}\"",
+                    setup.join(" ")
+                )]
+            }
+        }
+
+        for text in moves.iter().skip(1) {
+            match parse_coordinate_move(text, &self.board) {
+                Ok(mov) => {
+                    self.repetition.push(&self.board, mov);
+                    self.board = self.board.make_move_new(mov);
+                }
+                Err(e) => return vec![format!("info string move {}: {}", text, e)],
+            }
+        }
+        Vec::new()
+    }
+
+    /// `go [depth <plies>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>]
+    /// [movestogo <moves>]`, the first of depth, movetime and the side to move clock given
+    /// bound the search, other parameters are ignored
+    fn go(&mut self, args: &[&str]) -> Vec<String> {
+        // no legal move, null move as the protocol require
+        // (checked first, the search would print to stdout)
+        if MoveGen::new_legal(&self.board).len() == 0 {
+            return vec!["bestmove 0000".to_string()];
+        }
+
+        let param = |name| {
+            args.iter()
+                .position(|&arg| arg == name)
+                .and_then(|i| args.get(i + 1))
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        let mut options = SearchOptions {
+            halfmove_clock: self.repetition.halfmove_clock(),
+            ..Default::default()
+        };
+        let (time, increment) = match self.board.side_to_move() {
+            Color::White => ("wtime", "winc"),
+            Color::Black => ("btime", "binc"),
+        };
+        let clock = param(time).map(|remaining| {
+            clock_budget(
+                Duration::from_millis(remaining),
+                Duration::from_millis(param(increment).unwrap_or(0)),
+                param("movestogo").map(|moves| moves as u32),
+            )
+        });
+        let movetime = param("movetime").map(Duration::from_millis);
+        let depth = match (param("depth"), movetime.or(clock)) {
+            (Some(depth), _) => depth.clamp(1, u64::from(MAX_TIMED_DEPTH)) as u8,
+            (None, Some(budget)) => {
+                options.time_budget = Some(budget);
+                MAX_TIMED_DEPTH
+            }
+            (None, None) => crate::DEFAULT_SEARCH_DEPTH,
+        };
+
+        let (best, stats) = search_root(
+            &self.board,
+            depth,
+            &options,
+            &mut self.rng,
+            &mut self.cache,
+            self.repetition.repeated(),
+        );
+        let (mov, score) = best.expect("position has a legal move");
+        // the rest of the line is searched again (at the default depth when the search was timed),
+        // cheap with the cache just filled
+        let pv_depth = if options.time_budget.is_some() {
+            crate::DEFAULT_SEARCH_DEPTH
+        } else {
+            depth
+        };
+        let reply_pv = principal_variation(
+            &self.board.make_move_new(mov),
+            pv_depth.saturating_sub(1),
+            &options,
+            &mut self.rng,
+            &mut self.cache,
+            self.repetition.repeated(),
+        );
+        let pv = iter::once(mov).chain(reply_pv.into_iter().map(|(mov, _)| mov));
+        vec![
+            format!(
+                "info depth {} score {} pv {}",
+                stats.depth,
+                uci_score(score, stats.depth),
+                pv.format(" ")
+            ),
+            format!("bestmove {}", mov),
+        ]
+    }
+}
+
+/// `score` of a search to `depth` as UCI `mate <moves>` if it is a checkmate, `cp <centipawn>`
+/// otherwise
+fn uci_score(score: i16, depth: u8) -> String {
+    match mate_distance(score, depth) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+/// Time to think on a move with `remaining` on the clock and `increment` added after each move,
+/// an even share of the clock over the next `moves_to_go` (`DEFAULT_MOVES_TO_GO` if unknown)
+/// moves plus the increment, but never more than half the clock
+fn clock_budget(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+    let share = remaining / moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    (share + increment).min(remaining / 2)
+}
+
+/// Speak UCI over stdin/stdout until `quit` or end of input
+pub fn run_uci() -> io::Result<()> {
+    let mut engine = UciEngine::new();
+    let stdout = io::stdout();
+
+    for line in io::stdin().lock().lines() {
+        let Some(response) = engine.handle(&line?) else {
+            break;
+        };
+
+        let mut out = stdout.lock();
+        for line in response {
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clock_budget, uci_score, UciEngine};
+    use chess::{Board, ChessMove};
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    fn send(engine: &mut UciEngine, line: &str) -> Vec<String> {
+        engine.handle(line).expect("engine quit")
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut engine = UciEngine::new();
+
+        let response = send(&mut engine, "uci");
+        assert!(response[0].starts_with("id name "));
+        assert_eq!(response.last().unwrap(), "uciok");
+        assert_eq!(send(&mut engine, "isready"), vec!["readyok"]);
+        assert!(send(&mut engine, "ucinewgame").is_empty());
+        assert!(send(&mut engine, "setoption name Hash value 16").is_empty());
+        assert_eq!(engine.handle("quit"), None);
+    }
+
+    #[test]
+    fn test_position_and_go() {
+        let mut engine = UciEngine::new();
+
+        assert!(send(&mut engine, "position startpos moves e2e4 e7e5 g1f3").is_empty());
+        let expected =
+            Board::from_str("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+                .unwrap();
+        assert_eq!(engine.board, expected);
+        assert_eq!(engine.repetition.halfmove_clock(), 1);
+
+        // back rank mate in one
+        send(
+            &mut engine,
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+        );
+        let response = send(&mut engine, "go depth 3");
+        assert!(response[0].starts_with("info depth 3 score mate 1 pv a1a8"));
+        assert_eq!(response.last().unwrap(), "bestmove a1a8");
+
+        let response = send(&mut engine, "go movetime 50");
+        assert_eq!(response.last().unwrap(), "bestmove a1a8");
+
+        // checkmated, no move to play
+        send(&mut engine, "position fen 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(send(&mut engine, "go depth 2"), vec!["bestmove 0000"]);
+    }
+
+    #[test]
+    fn test_uci_score() {
+        assert_eq!(uci_score(35, 4), "cp 35");
+        assert_eq!(uci_score(-120, 4), "cp -120");
+        // mating on the 1st ply of a depth 4 search, 3 depth left
+        assert_eq!(uci_score(21500, 4), "mate 1");
+        // mated on the 4th ply (2nd move)
+        assert_eq!(uci_score(-20000, 4), "mate -2");
+        // mating on the 3rd ply
+        assert_eq!(uci_score(20500, 4), "mate 2");
+    }
+
+    #[test]
+    fn test_invalid_position() {
+        let mut engine = UciEngine::new();
+
+        let response = send(&mut engine, "position startpos moves e2e4 e2e4");
+        assert_eq!(response.len(), 1);
+        assert!(response[0].starts_with("info string move e2e4"));
+        // moves before the illegal one are kept
+        let e4 = ChessMove::from_str("e2e4").unwrap();
+        assert_eq!(engine.board, Board::default().make_move_new(e4));
+
+        let response = send(&mut engine, "position fen 8/8/8 w - - 0 1");
+        assert!(response[0].starts_with("info string invalid FEN"));
+    }
+
+    #[test]
+    fn test_go_with_clock() {
+        let ms = Duration::from_millis;
+        assert_eq!(clock_budget(ms(60_000), ms(0), None), ms(2_000));
+        assert_eq!(clock_budget(ms(60_000), ms(1_000), Some(10)), ms(7_000));
+        // little time left, the increment doesn't lure it into flagging
+        assert_eq!(clock_budget(ms(1_000), ms(2_000), None), ms(500));
+
+        let mut engine = UciEngine::new();
+        send(
+            &mut engine,
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+        );
+        // white's clock bound the search, black's is ignored
+        let start = Instant::now();
+        let response = send(&mut engine, "go wtime 3000 btime 3600000 winc 0 binc 0");
+        assert_eq!(response.last().unwrap(), "bestmove a1a8");
+        assert!(start.elapsed() < ms(2_000));
+    }
+}
@@ -1,147 +1,562 @@
-use chess::{Board, ChessMove, Color, Game, MoveGen, Piece, Square};
-use rand::thread_rng;
+mod chess_minmax;
 
-use std::time::Instant;
+mod chess_graphic;
+use chess_graphic::{ChessGraphic, FONT_PATH};
 
-use std::io::stdin;
-use std::str::FromStr;
+mod chess_notation;
 
-mod chess_minmax;
-use chess_minmax::negamax_prelude;
+mod chess_tuning;
+use chess_tuning::export_eval_csv;
 
-mod chess_graphic;
-use chess_graphic::ChessGraphic;
-use lru::LruCache;
-use std::fs::OpenOptions;
+mod chess_epd;
+use chess_epd::parse_epd;
+
+mod chess_outcome;
+
+mod chess_pgn;
+
+mod chess_uci;
+use chess_uci::run_uci;
+
+mod chess_book;
+mod chess_sound;
+
+mod chess_perft;
+mod chess_selfplay;
+use chess_book::{OpeningBook, DEFAULT_BOOK_DEPTH};
+use chess_notation::{fen_halfmove_clock, parse_fen, verify_move_sequence};
+use chess_perft::perft_divide;
+use chess_selfplay::{play_game, SelfPlayConfig};
+
+use chess::Board;
+use chess_minmax::analyze;
+use chess_minmax::main_evalation::eval_params::{eval_params, set_eval_params, EvalParams};
+use chess_minmax::main_evalation::evaluate_stm;
+use chess_minmax::tablebase::{set_tablebase, Tablebase};
+use itertools::Itertools;
+use std::process::exit;
+use std::str::FromStr;
+use std::time::Duration;
 
 pub const CACHE_SIZE: usize = 4096;
 
+// depth of `--search` and `--selfplay` when not given
+const DEFAULT_SEARCH_DEPTH: u8 = 4;
+
+// PGN file `--selfplay` append its games to when not given
+const DEFAULT_SELFPLAY_OUTPUT: &str = "selfplay.pgn";
+
 fn main() {
-    graphic();
-    // batch_generator();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    load_eval_params(&mut args);
+    load_tablebase(&mut args);
+
+    match args.first().map(String::as_str) {
+        Some("--print-eval-params") => print!("{}", eval_params().to_toml()),
+        Some("--verify") => verify(&args[1..]),
+        Some("--export-eval") => export_eval(&args[1..]),
+        Some("--search") => search(&args[1..]),
+        Some("--perft") => perft(&args[1..]),
+        Some("--selfplay") => selfplay(&args[1..]),
+        Some("--uci") => uci(),
+        _ => graphic(&args),
+    }
 }
 
-fn graphic() {
-    use piston_window::*;
+fn print_usage() {
+    eprintln!("Usage:");
+    eprintln!("  chess_try [--win-scale <cp>] [--book <file>] [--book-depth <plies>]");
+    eprintln!("            [--max-length <plies>] [--seed-epd <file>] [--depth <n> | --random]");
+    eprintln!("            [--node-limit <nodes>] [--takeback-limit <plies>]");
+    eprintln!("            [--quiescence-depth <plies>] [--second-best-margin <cp>]");
+    eprintln!("            [--autoplay-speed <moves per second>] [--stalemate-warning]");
+    eprintln!("            [--ai-delay <ms>] [--press <-1.0 to 1.0>] [--pgn-db <pgn file>]");
+    eprintln!("            [--time-budget <ms per move>] [--load-pgn <pgn file>]");
+    eprintln!("            [--save-file <pgn file for F5/F9>] [--analysis-lines <moves>]");
+    eprintln!("            [--ponder] [--contempt <cp, positive avoid draws>]");
+    eprintln!("                                              open graphic window");
+    eprintln!("  chess_try --verify \"<moves>\" [--fen \"<fen>\"]  check legality of move list");
+    eprintln!("  chess_try --search \"<fen>\" [--depth <n>]      search position once and exit");
+    eprintln!("  chess_try --uci                                 speak UCI on stdin/stdout");
+    eprintln!("  chess_try --perft <depth> [--fen \"<fen>\"]      count leaf nodes by root move");
+    eprintln!("  chess_try --selfplay <games> [--depth <n>]      play engine against engine,");
+    eprintln!("            [--white-depth <n>] [--black-depth <n>]   appending the games as PGN");
+    eprintln!("            [--white-random] [--black-random]         side playing random moves");
+    eprintln!("            [--max-length <plies>] [--output <pgn file>]");
+    eprintln!("  chess_try --export-eval <positions> [--output <csv>]");
+    eprintln!("                                              export evaluation components of");
+    eprintln!("                                              labeled position (<fen>;<result>)");
+    eprintln!("  chess_try --print-eval-params                   print the evaluation weights");
+    eprintln!("Every mode accept --eval-params <file> to load the evaluation weights from a file");
+    eprintln!("(same format as --print-eval-params, missing weights keep their default)");
+    eprintln!(
+        "and --syzygy <dir> to probe the Syzygy tablebases of a directory (`syzygy` feature)"
+    );
+}
 
-    let mut window: PistonWindow = WindowSettings::new("Chess?", (640, 480))
-        .exit_on_esc(true)
-        .build()
-        .unwrap_or_else(|e| panic!("Failed to build PistonWindow: {}", e));
+/// Take `--eval-params <file>` out of `args` and use the weights of that file,
+/// must run before anything is evaluated
+fn load_eval_params(args: &mut Vec<String>) {
+    let Some(path) = take_flag_value(args, "--eval-params") else {
+        return;
+    };
+    let params = EvalParams::load(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", path, e);
+        exit(2);
+    });
+    set_eval_params(params).expect("nothing is evaluated yet");
+}
 
-    let mut game = ChessGraphic::new(&mut window.create_texture_context());
-    window.set_max_fps(10);
+/// Take `--syzygy <dir>` out of `args` and probe the tablebases of that directory,
+/// the search run without them if they can't be opened
+fn load_tablebase(args: &mut Vec<String>) {
+    let Some(dir) = take_flag_value(args, "--syzygy") else {
+        return;
+    };
+    match Tablebase::open(&dir) {
+        Ok(tablebase) => {
+            eprintln!("Syzygy tablebase: up to {} pieces", tablebase.max_pieces());
+            set_tablebase(tablebase).expect("nothing is searched yet");
+        }
+        Err(e) => eprintln!("Syzygy tablebase disabled: {}", e),
+    }
+}
 
-    while let Some(e) = window.next() {
-        window.draw_2d(&e, |c, g, _| {
-            game.draw(c, g);
-        });
+/// Remove `flag` and its value from `args`, exit with message if the value is missing
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == flag)?;
+    let Some(value) = args.get(i + 1).cloned() else {
+        eprintln!("Missing value for {}", flag);
+        exit(2);
+    };
+    args.drain(i..=i + 1);
+    Some(value)
+}
 
-        if let Some(button) = e.press_args() {
-            game.button_input(&button);
-        }
+/// Value following `flag` in `args`, e.g. `--fen <value>`
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
 
-        if let Some(mouse_pos) = e.mouse_cursor_args() {
-            game.on_mouse_position(mouse_pos);
+/// Parse value following `flag`, exit with message if it is malformed
+fn parse_flag<T: FromStr>(args: &[String], flag: &str) -> Option<T> {
+    flag_value(args, flag).map(|value| {
+        value.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid value for {}: {}", flag, value);
+            exit(2);
+        })
+    })
+}
+
+fn verify(args: &[String]) {
+    let move_list = match args.first() {
+        Some(move_list) if !move_list.starts_with("--") => move_list,
+        _ => {
+            print_usage();
+            exit(2);
         }
+    };
+
+    let board = match flag_value(args, "--fen").map(parse_fen) {
+        None => Board::default(),
+        Some(Ok((board, _))) => board,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
 
-        if let Some(resize_args) = e.resize_args() {
-            game.on_resize(resize_args);
+    let checks = verify_move_sequence(&board, move_list);
+    for check in &checks {
+        match &check.result {
+            Ok(_) => println!("{:>3}. {:<6} ok", check.ply, check.text),
+            Err(reason) => println!("{:>3}. {:<6} ILLEGAL: {}", check.ply, check.text, reason),
         }
     }
+
+    if checks.iter().any(|check| check.result.is_err()) {
+        exit(1);
+    }
+    println!("All {} moves are legal", checks.len());
 }
-/*
-fn io() {
-    loop {
-        let mut input_str: String = String::new();
 
-        stdin().read_line(&mut input_str).unwrap();
+fn search(args: &[String]) {
+    let fen = match args.first() {
+        Some(fen) if !fen.starts_with("--") => fen,
+        _ => {
+            print_usage();
+            exit(2);
+        }
+    };
+    if let Some(unknown) = unknown_flag(&args[1..], &["--depth"], &[]) {
+        eprintln!("Unknown option: {}", unknown);
+        print_usage();
+        exit(2);
+    }
 
-        let board = Board::from_str(&input_str).expect("Invalid FEN");
-        let rng = &mut thread_rng();
-        let mut cache = LruCache::new(CACHE_SIZE);
+    let (board, _) = parse_fen(fen).unwrap_or_else(|e| {
+        eprintln!("Invalid FEN \"{}\": {}", fen, e);
+        exit(2);
+    });
+    let depth = parse_flag(args, "--depth").unwrap_or(DEFAULT_SEARCH_DEPTH);
+
+    match analyze(&board, fen_halfmove_clock(fen), depth) {
+        Some(result) => {
+            println!("bestmove {}", result.best_move);
+            println!("score {} (side to move)", result.score);
+            println!("pv {}", result.pv.iter().join(" "));
+            println!("nodes {}", result.nodes);
+            println!("tthits {}", result.tt_hits);
+            println!("static {} (side to move)", evaluate_stm(&board));
+        }
+        None => {
+            eprintln!("No legal move in this position ({:?})", board.status());
+            exit(1);
+        }
+    }
+}
 
-        const DEPTH: u8 = 5;
+fn perft(args: &[String]) {
+    use std::time::Instant;
 
-        let result = negamax_prelude(&board, DEPTH, rng, &mut cache).unwrap();
+    let depth = match args.first().map(|depth| depth.parse::<u8>()) {
+        Some(Ok(depth)) => depth,
+        _ => {
+            print_usage();
+            exit(2);
+        }
+    };
+    if let Some(unknown) = unknown_flag(&args[1..], &["--fen"], &[]) {
+        eprintln!("Unknown option: {}", unknown);
+        print_usage();
+        exit(2);
+    }
+    let board = match flag_value(args, "--fen").map(parse_fen) {
+        None => Board::default(),
+        Some(Ok((board, _))) => board,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
 
-        println!("{} {}", result.0, result.1);
+    let start = Instant::now();
+    let divide = perft_divide(&board, depth);
+    let elapsed = start.elapsed();
+
+    for (mov, count) in &divide {
+        println!("{}: {}", mov, count);
     }
+    // depth 0 has no root move, only the position itself
+    let total = if depth == 0 {
+        1
+    } else {
+        divide.iter().map(|(_, count)| count).sum()
+    };
+    println!();
+    println!("Nodes searched: {}", total);
+    println!(
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
 }
 
-fn batch_generator() {
-    use std::io::Write;
-
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .open(concat!(env!("CARGO_MANIFEST_DIR"), "/chess.txt"))
-        .unwrap();
-
-    // sicilian defense
-    let mut game = Game::new();
-    game.make_move(ChessMove::new(Square::E2, Square::E4, None));
-    game.make_move(ChessMove::new(Square::C7, Square::C5, None));
-    write!(file, "e2e4 c7c5 {{Sicilian Defense}} ").unwrap();
-    game.make_move(ChessMove::new(Square::G1, Square::F3, None));
-    game.make_move(ChessMove::new(Square::D7, Square::D6, None));
-    write!(file, "g1f3 d7d6 {{Sicilian Continue}} ").unwrap();
-    // fen
-    // let mut game = Game::from_str("4k3/8/1p6/8/8/8/5QQQ/4K3 w - - 0 1").unwrap();
-
-    const START_NUM: u32 = 4;
-    const MAX_LENGTH: u32 = 150;
-
-    const WHITE_DEPTH: u8 = 6;
-    const BLACK_DEPTH: u8 = 6;
-
-    let rng = &mut thread_rng();
-    let mut cache = LruCache::new(CACHE_SIZE);
-
-    let start_time = Instant::now();
-
-    for _ in START_NUM..MAX_LENGTH + START_NUM {
-        let mut step = |game: &mut Game, mov| {
-            game.make_move(mov);
-
-            write!(file, "{}{}", mov.get_source(), mov.get_dest()).unwrap();
-            write!(
-                file,
-                "{} ",
-                mov.get_promotion()
-                    .map_or(String::new(), |x| x.to_string(Color::White))
-            )
-            .unwrap();
-
-            /*if score < SCORE_DISPLAY_THRESHOLD || score > -SCORE_DISPLAY_THRESHOLD {
-                print!("{{{:.2}}} ", score);
-            } else if score > 0.0 {
-                print!("{{inf}} ")
-            } else {
-                print!("{{-inf}}")
-            }*/
-        };
-
-        if let Some((mov, _score)) =
-            negamax_prelude(&game.current_position(), WHITE_DEPTH, rng, &mut cache)
-        {
-            step(&mut game, mov);
-        } else {
-            break;
+fn selfplay(args: &[String]) {
+    use chess_graphic::DEFAULT_MAX_GAME_LENGTH;
+    use chess_pgn::{append_pgn, pgn_date};
+    use std::time::{Instant, SystemTime};
+
+    let games = match args.first().map(|games| games.parse::<usize>()) {
+        Some(Ok(games)) => games,
+        _ => {
+            print_usage();
+            exit(2);
         }
+    };
+    const VALUE_FLAGS: [&str; 5] = [
+        "--depth",
+        "--white-depth",
+        "--black-depth",
+        "--max-length",
+        "--output",
+    ];
+    const SWITCH_FLAGS: [&str; 2] = ["--white-random", "--black-random"];
+    if let Some(unknown) = unknown_flag(&args[1..], &VALUE_FLAGS, &SWITCH_FLAGS) {
+        eprintln!("Unknown option: {}", unknown);
+        print_usage();
+        exit(2);
+    }
 
-        if let Some((mov, _score)) =
-            negamax_prelude(&game.current_position(), BLACK_DEPTH, rng, &mut cache)
-        {
-            step(&mut game, mov);
+    let depth = parse_flag(args, "--depth").unwrap_or(DEFAULT_SEARCH_DEPTH);
+    // depth 0 plays random moves
+    let side_depth = |depth_flag: &str, random_flag: &str| {
+        if args.iter().any(|arg| arg == random_flag) {
+            0
         } else {
-            break;
+            parse_flag(args, depth_flag).unwrap_or(depth)
+        }
+    };
+    let config = SelfPlayConfig {
+        depth: [
+            side_depth("--white-depth", "--white-random"),
+            side_depth("--black-depth", "--black-random"),
+        ],
+        max_length: parse_flag(args, "--max-length").unwrap_or(DEFAULT_MAX_GAME_LENGTH),
+    };
+    let output = flag_value(args, "--output").unwrap_or(DEFAULT_SELFPLAY_OUTPUT);
+    let date = pgn_date(SystemTime::now());
+    let rng = &mut rand::thread_rng();
+
+    let start = Instant::now();
+    for round in 1..=games {
+        let game_start = Instant::now();
+        let game = play_game(&config, rng);
+        if let Err(e) = append_pgn(output, &game.pgn(&config, round, &date)) {
+            eprintln!("Failed to write {}: {}", output, e);
+            exit(1);
         }
+        println!(
+            "Game {}/{} ({}): {}, took {:.2?} (thinking: {})",
+            round,
+            games,
+            game.opening.unwrap_or("starting position"),
+            game.summary(),
+            game_start.elapsed(),
+            game.think_time_summary()
+        );
     }
+    println!(
+        "{} game(s) appended to {}, time used: {:.2?}",
+        games,
+        output,
+        start.elapsed()
+    );
+}
 
-    let end_time = Instant::now();
+fn uci() {
+    if let Err(e) = run_uci() {
+        eprintln!("UCI I/O error: {}", e);
+        exit(1);
+    }
+}
 
-    println!();
-    println!("time used: {:?}", end_time - start_time);
+fn export_eval(args: &[String]) {
+    use std::fs::{self, File};
+    use std::io::{self, BufWriter};
+
+    let input_path = match args.first() {
+        Some(path) if !path.starts_with("--") => path,
+        _ => {
+            print_usage();
+            exit(2);
+        }
+    };
+    let input = fs::read_to_string(input_path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", input_path, e);
+        exit(2);
+    });
+
+    let result = match flag_value(args, "--output") {
+        Some(path) => {
+            File::create(path).and_then(|file| export_eval_csv(&input, &mut BufWriter::new(file)))
+        }
+        None => export_eval_csv(&input, &mut io::stdout().lock()),
+    };
+
+    match result {
+        Ok(count) => eprintln!("Exported {} positions", count),
+        Err(e) => {
+            eprintln!("Failed to write CSV: {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// First argument that is neither a known flag nor the value of a flag
+fn unknown_flag<'a>(
+    args: &'a [String],
+    value_flags: &[&str],
+    switch_flags: &[&str],
+) -> Option<&'a String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if value_flags.contains(&arg.as_str()) {
+            args.next();
+        } else if !switch_flags.contains(&arg.as_str()) {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+fn graphic(args: &[String]) {
+    use piston_window::*;
+
+    const VALUE_FLAGS: [&str; 19] = [
+        "--win-scale",
+        "--book",
+        "--book-depth",
+        "--max-length",
+        "--seed-epd",
+        "--depth",
+        "--node-limit",
+        "--takeback-limit",
+        "--autoplay-speed",
+        "--quiescence-depth",
+        "--second-best-margin",
+        "--ai-delay",
+        "--press",
+        "--pgn-db",
+        "--time-budget",
+        "--load-pgn",
+        "--save-file",
+        "--analysis-lines",
+        "--contempt",
+    ];
+    const SWITCH_FLAGS: [&str; 3] = ["--random", "--stalemate-warning", "--ponder"];
+    if let Some(unknown) = unknown_flag(args, &VALUE_FLAGS, &SWITCH_FLAGS) {
+        eprintln!("Unknown option: {}", unknown);
+        print_usage();
+        exit(2);
+    }
+
+    let mut window: PistonWindow = WindowSettings::new("Chess?", (680, 480))
+        .exit_on_esc(true)
+        .build()
+        .unwrap_or_else(|e| panic!("Failed to build PistonWindow: {}", e));
+
+    let mut texture_context = window.create_texture_context();
+    let mut game = match flag_value(args, "--load-pgn") {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|pgn| ChessGraphic::from_pgn(&pgn, &mut texture_context))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load {}: {}", path, e);
+                exit(1);
+            }),
+        None => ChessGraphic::new(&mut texture_context),
+    };
+    if let Some(scale) = parse_flag(args, "--win-scale") {
+        game.set_win_probability_scale(scale);
+    }
+    if args.iter().any(|arg| arg == "--random") {
+        game.set_depth(0);
+    } else if let Some(depth) = parse_flag(args, "--depth") {
+        game.set_depth(depth);
+    }
+    if args.iter().any(|arg| arg == "--stalemate-warning") {
+        game.set_stalemate_warning(true);
+    }
+    if args.iter().any(|arg| arg == "--ponder") {
+        game.set_pondering(true);
+    }
+    if let Some(undo_limit) = parse_flag(args, "--takeback-limit") {
+        game.set_undo_limit(undo_limit);
+    }
+    if let Some(quiescence_depth) = parse_flag(args, "--quiescence-depth") {
+        game.set_quiescence_depth(quiescence_depth);
+    }
+    // applied before --second-best-margin so an explicit margin take precedence
+    if let Some(press) = parse_flag(args, "--press") {
+        game.set_press(press);
+    }
+    // after --press, which set it too
+    if let Some(contempt) = parse_flag(args, "--contempt") {
+        game.set_contempt(contempt);
+    }
+    if let Some(margin) = parse_flag(args, "--second-best-margin") {
+        game.set_second_best_margin(margin);
+    }
+    if let Some(node_limit) = parse_flag(args, "--node-limit") {
+        game.set_node_limit(node_limit);
+    }
+    if let Some(path) = flag_value(args, "--seed-epd") {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let count = game.seed_cache(&parse_epd(&text));
+                println!("Transposition table seeded with {} positions", count);
+            }
+            Err(e) => eprintln!("Failed to read {}: {}", path, e),
+        }
+    }
+    if let Some(speed) = parse_flag(args, "--autoplay-speed") {
+        game.set_autoplay_speed(speed);
+    }
+    if let Some(budget) = parse_flag(args, "--time-budget") {
+        game.set_time_budget(Duration::from_millis(budget));
+    }
+    if let Some(delay) = parse_flag(args, "--ai-delay") {
+        game.set_ai_move_delay(Duration::from_millis(delay));
+    }
+    if let Some(lines) = parse_flag(args, "--analysis-lines") {
+        game.set_analysis_lines(lines);
+    }
+    if let Some(path) = flag_value(args, "--save-file") {
+        game.set_save_path(path);
+    }
+    if let Some(path) = flag_value(args, "--pgn-db") {
+        game.set_pgn_database(path);
+    }
+    if let Some(max_length) = parse_flag(args, "--max-length") {
+        game.set_max_game_length(max_length);
+    }
+    if let Some(path) = flag_value(args, "--book") {
+        let book_depth = parse_flag(args, "--book-depth").unwrap_or(DEFAULT_BOOK_DEPTH);
+        match OpeningBook::load(path) {
+            Ok(book) => game.set_book(book.with_max_depth(book_depth)),
+            Err(e) => eprintln!("Failed to load opening book, playing without it: {}", e),
+        }
+    }
+    match window.load_font(FONT_PATH) {
+        Ok(glyphs) => game.set_glyphs(glyphs),
+        Err(e) => eprintln!(
+            "Failed to load font, board coordinates won't be shown: {}",
+            e
+        ),
+    }
+    // high enough for the piece and board animations to look smooth
+    window.set_max_fps(60);
+
+    while let Some(e) = window.next() {
+        let drawn = window.draw_2d(&e, |c, g, device| {
+            let drawn = game.draw(c, g);
+            // text is queued on the glyph cache's own encoder
+            if let Some(glyphs) = game.glyphs_mut() {
+                glyphs.factory.encoder.flush(device);
+            }
+            drawn
+        });
+        // keep showing the last frame when nothing was drawn
+        if let Some(drawn) = drawn {
+            window.set_swap_buffers(drawn);
+        }
+
+        if let Some(args) = e.update_args() {
+            game.update(args.dt);
+        }
+
+        if let Some(button) = e.press_args() {
+            game.button_input(&button);
+        }
+
+        if let Some(button) = e.release_args() {
+            game.button_release(&button);
+        }
+
+        if let Some(mouse_pos) = e.mouse_cursor_args() {
+            game.on_mouse_position(mouse_pos);
+        }
+
+        if let Some(scroll) = e.mouse_scroll_args() {
+            game.on_mouse_scroll(scroll);
+        }
+
+        if let Some(resize_args) = e.resize_args() {
+            game.on_resize(resize_args);
+        }
+    }
 }
-*/
\ No newline at end of file